@@ -0,0 +1,222 @@
+#!/usr/bin/env python3
+#
+# Generate the codepoint tables used by the Text format from local copies of
+# the Unicode Character Database.
+#
+# Usage: python3 scripts/unicode.py <ucd-dir>
+#
+# where <ucd-dir> contains the following files from
+# https://www.unicode.org/Public/<version>/ucd/:
+# - PropList.txt
+# - DerivedCoreProperties.txt
+# - NameAliases.txt
+# - Blocks.txt
+# - extracted/DerivedGeneralCategory.txt
+#
+# This regenerates src/tables.rs and tests/disallowed_codepoints.rs. Both
+# are checked into git. When updating to a new version of Unicode, also
+# update the version pinned by unicode-normalization, and check that the
+# tests in tests/text-profiles.rs still pass; if a set of disallowed
+# codepoints changes, that requires a new `TextProfile`.
+
+import os
+import re
+import sys
+
+MAX_CODEPOINT = 0x10FFFF
+SURROGATES = range(0xD800, 0xE000)
+
+# Control codes which are permitted in Text, or which have their own rules.
+PERMITTED_CONTROLS = {0x09, 0x0A, 0x0C, 0x1B}
+
+PREAMBLE = """// DO NOT EDIT THIS FILE. IT WAS AUTOMATICALLY GENERATED BY:
+//
+//   python3 scripts/unicode.py <ucd-dir>
+//
+// Unicode version: %s.
+"""
+
+
+def ucd_path(ucd_dir, name):
+    return os.path.join(ucd_dir, *name.split("/"))
+
+
+def read_version(ucd_dir):
+    """Read the Unicode version from the header of PropList.txt."""
+    with open(ucd_path(ucd_dir, "PropList.txt"), encoding="utf-8") as f:
+        m = re.match(r"# PropList-(\d+)\.(\d+)\.(\d+)\.txt", f.readline())
+    if m is None:
+        sys.exit("couldn't determine the Unicode version from PropList.txt")
+    return tuple(int(x) for x in m.groups())
+
+
+def parse_ranges(ucd_dir, name):
+    """Parse a file of `XXXX..YYYY ; Value` lines into a dict mapping each
+    value to a set of codepoints."""
+    result = {}
+    with open(ucd_path(ucd_dir, name), encoding="utf-8") as f:
+        for line in f:
+            line = line.split("#", 1)[0].strip()
+            if not line:
+                continue
+            fields = [field.strip() for field in line.split(";")]
+            bounds = fields[0].split("..")
+            lo = int(bounds[0], 16)
+            hi = int(bounds[-1], 16)
+            result.setdefault(fields[1], set()).update(range(lo, hi + 1))
+    return result
+
+
+def parse_control_aliases(ucd_dir):
+    """Return the set of codepoints with a `control` alias in NameAliases.txt."""
+    controls = set()
+    with open(ucd_path(ucd_dir, "NameAliases.txt"), encoding="utf-8") as f:
+        for line in f:
+            line = line.split("#", 1)[0].strip()
+            if not line:
+                continue
+            codepoint, _alias, kind = [field.strip() for field in line.split(";")]
+            if kind == "control":
+                controls.add(int(codepoint, 16))
+    return controls
+
+
+def to_ranges(codepoints):
+    """Convert a set of codepoints into a sorted list of inclusive ranges."""
+    ranges = []
+    for c in sorted(codepoints):
+        if ranges and ranges[-1][1] + 1 == c:
+            ranges[-1][1] = c
+        else:
+            ranges.append([c, c])
+    return [tuple(r) for r in ranges]
+
+
+def char(c):
+    return "'\\u{%x}'" % c
+
+
+def load(ucd_dir):
+    prop_list = parse_ranges(ucd_dir, "PropList.txt")
+    core = parse_ranges(ucd_dir, "DerivedCoreProperties.txt")
+    categories = parse_ranges(ucd_dir, "extracted/DerivedGeneralCategory.txt")
+    blocks = parse_ranges(ucd_dir, "Blocks.txt")
+
+    deprecated = prop_list["Deprecated"]
+    ignorable = core["Default_Ignorable_Code_Point"]
+
+    # Each codepoint gets at most one category; earlier ones take precedence.
+    disallowed = {}
+    sets = [
+        ("DeprecatedFormat", deprecated & ignorable),
+        ("Deprecated", deprecated),
+        ("Tag", blocks["Tags"]),
+        ("Noncharacter", prop_list["Noncharacter_Code_Point"]),
+        ("PrivateUse", categories["Co"]),
+    ]
+    for kind, codepoints in sets:
+        for c in codepoints:
+            disallowed.setdefault(c, kind)
+
+    unassigned = categories["Cn"] | categories["Cs"]
+    assigned = set(range(MAX_CODEPOINT + 1)) - unassigned - set(SURROGATES)
+
+    controls = parse_control_aliases(ucd_dir) - PERMITTED_CONTROLS
+
+    return {
+        "assigned": assigned,
+        "disallowed": disallowed,
+        "controls": controls,
+        "sets": dict(sets),
+    }
+
+
+def disallowed_ranges(disallowed):
+    """Group consecutive codepoints with the same kind into ranges."""
+    ranges = []
+    for c in sorted(disallowed):
+        kind = disallowed[c]
+        if ranges and ranges[-1][1] + 1 == c and ranges[-1][2] == kind:
+            ranges[-1][1] = c
+        else:
+            ranges.append([c, c, kind])
+    return ranges
+
+
+def write_tables(path, version, tables):
+    version_str = "%d.%d.%d" % version
+    with open(path, "w", encoding="utf-8") as f:
+        f.write(PREAMBLE % version_str)
+        f.write("\n")
+        f.write("use crate::unicode::Disallowed::{self, *};\n")
+        f.write("\n")
+        f.write("/// The version of Unicode whose assigned codepoints and normalization data\n")
+        f.write("/// define the Text format implemented by this crate, as (major, minor, update).\n")
+        f.write("pub const UNICODE_VERSION: (u8, u8, u8) = (%d, %d, %d);\n" % version)
+        f.write("\n")
+        f.write("/// The ranges of codepoints whose General_Category is not Cn (Unassigned)\n")
+        f.write("/// or Cs (Surrogate), in order.\n")
+        f.write("#[rustfmt::skip]\n")
+        f.write("pub(crate) const ASSIGNED: &[(char, char)] = &[\n")
+        items = ["(%s, %s)" % (char(lo), char(hi)) for lo, hi in to_ranges(tables["assigned"])]
+        for i in range(0, len(items), 4):
+            f.write("    %s,\n" % ", ".join(items[i : i + 4]))
+        f.write("];\n")
+        f.write("\n")
+        f.write("/// The ranges of disallowed codepoints, other than control codes, in order.\n")
+        f.write("#[rustfmt::skip]\n")
+        f.write("pub(crate) const DISALLOWED: &[(char, char, Disallowed)] = &[\n")
+        for lo, hi, kind in disallowed_ranges(tables["disallowed"]):
+            f.write("    (%s, %s, %s),\n" % (char(lo), char(hi), kind))
+        f.write("];\n")
+
+
+def write_tests(path, version, tables):
+    version_str = "%d.%d.%d" % version
+    sets = tables["sets"]
+    sections = [
+        (
+            [
+                "All C0, U+007F, and C1 control codes other than U+000A (newline),",
+                "U+0009 (horizontal tab), U+000C (form feed), and U+001B (escape).",
+            ],
+            sorted(tables["controls"]),
+        ),
+        (["Deprecated Format Characters"], sorted(sets["DeprecatedFormat"])),
+        (["Tag Characters"], sorted(sets["Tag"] - sets["DeprecatedFormat"])),
+        (["Noncharacters"], sorted(sets["Noncharacter"])),
+        (
+            [
+                "Private-Use Characters",
+                "These are just the limits of the ranges; the ranges are big.",
+            ],
+            [c for r in to_ranges(sets["PrivateUse"]) for c in r],
+        ),
+    ]
+    count = sum(len(codepoints) for _, codepoints in sections)
+    with open(path, "w", encoding="utf-8") as f:
+        f.write(PREAMBLE % version_str)
+        f.write("\n")
+        f.write("pub const DISALLOWED_CODEPOINTS: [char; %d] = [\n" % count)
+        for comments, codepoints in sections:
+            for comment in comments:
+                f.write("    // %s\n" % comment)
+            for c in codepoints:
+                f.write("    %s,\n" % char(c))
+        f.write("];\n")
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit("usage: %s <ucd-dir>" % sys.argv[0])
+    ucd_dir = sys.argv[1]
+    root = os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+
+    version = read_version(ucd_dir)
+    tables = load(ucd_dir)
+    write_tables(os.path.join(root, "src", "tables.rs"), version, tables)
+    write_tests(os.path.join(root, "tests", "disallowed_codepoints.rs"), version, tables)
+
+
+if __name__ == "__main__":
+    main()
@@ -1,157 +1,107 @@
 //! On output, several disallowed codepoints are rejected, to catch
 //! applications attempting to use them.
 
-use crate::unicode::{BOM, SUB};
-use std::{cell::RefCell, io, rc::Rc};
+use crate::{
+    text_profile::TextProfile,
+    unicode::{
+        disallowed, is_assigned, is_tag_spec_character, Disallowed, BOM, CANCEL_TAG,
+        MAX_TAG_SPEC_LEN, WAVING_BLACK_FLAG,
+    },
+    TextError, ViolationKind,
+};
+use std::collections::VecDeque;
 
 pub(crate) struct Categorize<Iter: Iterator<Item = char>> {
     iter: Iter,
 
-    // Becuase we wrap this iterator in the NFC etc. iterator chain, it has
-    // to yield `char`s, and can't directly return errors. We indicate errors
-    // by returning the special `SUB` value, which we intercept on the other
-    // side to report the error stored in this error field.
-    error: Rc<RefCell<Option<io::Error>>>,
+    /// The version of the rules to apply.
+    profile: TextProfile,
+
+    /// The rest of a well-formed emoji tag sequence, waiting to be returned.
+    buffer: VecDeque<char>,
+
+    /// A `char` read from `iter` while scanning for an emoji tag sequence,
+    /// which hasn't been processed yet.
+    lookahead: Option<char>,
 }
 
 impl<Iter: Iterator<Item = char>> Categorize<Iter> {
     #[inline]
-    pub(crate) fn new(iter: Iter, error: Rc<RefCell<Option<io::Error>>>) -> Self {
-        Self { iter, error }
+    pub(crate) fn new(iter: Iter, profile: TextProfile) -> Self {
+        Self {
+            iter,
+            profile,
+            buffer: VecDeque::new(),
+            lookahead: None,
+        }
     }
 }
 
 impl<Iter: Iterator<Item = char>> Iterator for Categorize<Iter> {
-    type Item = char;
+    type Item = Result<char, TextError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(match self.iter.next()? {
-            c if c.is_control() && c != '\n' && c != '\t' => self.control(c),
-            c @ '\u{149}'
-            | c @ '\u{673}'
-            | c @ '\u{f77}'
-            | c @ '\u{f79}'
-            | c @ '\u{17a3}'
-            | c @ '\u{17a4}'
-            | c @ '\u{2329}'
-            | c @ '\u{232a}'
-            | c @ '\u{e0001}' => self.deprecated(c),
-            c @ '\u{2126}' | c @ '\u{212a}' | c @ '\u{212b}' => self.obsolete_compatibility(c),
-            // Deprecated Format Characters
-            c @ '\u{206a}'..='\u{206f}' => self.deprecated_format_character(c),
-            // Tag Characters
-            c @ '\u{e0000}'..='\u{e007f}' => self.tag_character(c),
-            // Noncharacters
-            c @ '\u{fffe}'..='\u{ffff}'
-            | c @ '\u{1fffe}'..='\u{1ffff}'
-            | c @ '\u{2fffe}'..='\u{2ffff}'
-            | c @ '\u{3fffe}'..='\u{3ffff}'
-            | c @ '\u{4fffe}'..='\u{4ffff}'
-            | c @ '\u{5fffe}'..='\u{5ffff}'
-            | c @ '\u{6fffe}'..='\u{6ffff}'
-            | c @ '\u{7fffe}'..='\u{7ffff}'
-            | c @ '\u{8fffe}'..='\u{8ffff}'
-            | c @ '\u{9fffe}'..='\u{9ffff}'
-            | c @ '\u{afffe}'..='\u{affff}'
-            | c @ '\u{bfffe}'..='\u{bffff}'
-            | c @ '\u{cfffe}'..='\u{cffff}'
-            | c @ '\u{dfffe}'..='\u{dffff}'
-            | c @ '\u{efffe}'..='\u{effff}'
-            | c @ '\u{ffffe}'..='\u{fffff}'
-            | c @ '\u{10fffe}'..='\u{10ffff}'
-            | c @ '\u{fdd0}'..='\u{fdef}' => self.noncharacter(c),
-            // Private-Use Characters
-            c @ '\u{e000}'..='\u{f8ff}'
-            | c @ '\u{f0000}'..='\u{ffffd}'
-            | c @ '\u{100000}'..='\u{10fffd}' => self.private_use_character(c),
-            BOM => self.bom(),
-            c => c,
+        if let Some(c) = self.buffer.pop_front() {
+            return Some(Ok(c));
+        }
+
+        let c = match self.lookahead.take() {
+            Some(c) => c,
+            None => self.iter.next()?,
+        };
+
+        Some(match c {
+            c if c.is_control() && c != '\n' && c != '\t' => error(ViolationKind::ControlCode, c),
+            c @ '\u{2126}' | c @ '\u{212a}' | c @ '\u{212b}' => {
+                error(ViolationKind::ObsoleteCompatibility, c)
+            }
+            // Emoji tag sequences
+            WAVING_BLACK_FLAG if self.profile.permits_emoji_tag_sequences() => self.tag_base(),
+            BOM => error(ViolationKind::ByteOrderMark, c),
+            c => match disallowed(c) {
+                Some(Disallowed::Deprecated) => error(ViolationKind::Deprecated, c),
+                Some(Disallowed::DeprecatedFormat) => {
+                    error(ViolationKind::DeprecatedFormatCharacter, c)
+                }
+                // Tag Characters, other than in emoji tag sequences
+                Some(Disallowed::Tag) => error(ViolationKind::TagCharacter, c),
+                Some(Disallowed::Noncharacter) => error(ViolationKind::Noncharacter, c),
+                Some(Disallowed::PrivateUse) => error(ViolationKind::PrivateUse, c),
+                None if self.profile.disallows_unassigned() && !is_assigned(c) => {
+                    error(ViolationKind::Unassigned, c)
+                }
+                None => Ok(c),
+            },
         })
     }
 }
 
 impl<Iter: Iterator<Item = char>> Categorize<Iter> {
-    #[cold]
-    fn control(&mut self, c: char) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Control code written to text output stream: {:?}", c),
-        ));
-        SUB
-    }
-
-    #[cold]
-    fn deprecated(&mut self, c: char) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Deprecated character written to text output stream: {:?}",
-                c
-            ),
-        ));
-        SUB
-    }
-
-    #[cold]
-    fn obsolete_compatibility(&mut self, c: char) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Obsolete compatibility written to text output stream: {:?}",
-                c
-            ),
-        ));
-        SUB
-    }
-
-    #[cold]
-    fn deprecated_format_character(&mut self, c: char) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Deprecated format character written to text output stream: {:?}",
-                c
-            ),
-        ));
-        SUB
-    }
-
-    #[cold]
-    fn tag_character(&mut self, c: char) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Tag character written to text output stream: {:?}", c),
-        ));
-        SUB
-    }
-
-    #[cold]
-    fn noncharacter(&mut self, c: char) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Noncharacter written to text output stream: {:?}", c),
-        ));
-        SUB
-    }
-
-    #[cold]
-    fn private_use_character(&mut self, c: char) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Private use character written to text output stream: {:?}",
-                c
-            ),
-        ));
-        SUB
+    /// After a tag base, read any following tag characters into `buffer`,
+    /// and fail if they don't form a well-formed emoji tag sequence.
+    fn tag_base(&mut self) -> Result<char, TextError> {
+        loop {
+            match self.iter.next() {
+                Some(c) if is_tag_spec_character(c) && self.buffer.len() < MAX_TAG_SPEC_LEN => {
+                    self.buffer.push_back(c)
+                }
+                Some(CANCEL_TAG) if !self.buffer.is_empty() => {
+                    self.buffer.push_back(CANCEL_TAG);
+                    return Ok(WAVING_BLACK_FLAG);
+                }
+                other => {
+                    if let Some(c) = self.buffer.pop_front() {
+                        return error(ViolationKind::TagCharacter, c);
+                    }
+                    self.lookahead = other;
+                    return Ok(WAVING_BLACK_FLAG);
+                }
+            }
+        }
     }
+}
 
-    #[cold]
-    fn bom(&mut self) -> char {
-        *self.error.borrow_mut() = Some(io::Error::new(
-            io::ErrorKind::Other,
-            "Byte-order mark written to text output stream",
-        ));
-        SUB
-    }
+#[cold]
+fn error(kind: ViolationKind, c: char) -> Result<char, TextError> {
+    Err(TextError::new(kind, c))
 }
@@ -0,0 +1,73 @@
+//! Defines `ControlPictures`, and the mapping from control codes to visible
+//! forms used by `TextReader::with_control_pictures`.
+
+use crate::unicode::DEL;
+use std::fmt::{self, Write};
+
+/// A `Display` adapter for a `&str` which shows control codes in a visible
+/// form, using the same mapping as [`TextReader::with_control_pictures`]:
+/// C0 control codes other than newline and tab are shown as their Control
+/// Pictures (U+2400–U+241F), DEL as U+2421 (SYMBOL FOR DELETE), and C1
+/// control codes as escapes like `\u{85}`. Backslashes are doubled, and
+/// Control Pictures in the text itself are shown as escapes like
+/// `\u{2407}`, so that the output can be told apart from text which looks
+/// like it.
+///
+/// Unlike `TextReader`, this doesn't apply any other Text rules, so for
+/// example a CRLF is shown as "␍\n".
+///
+/// ```rust
+/// use text_streams::ControlPictures;
+///
+/// assert_eq!(
+///     ControlPictures("bell\x07 esc\x1b[0m\u{85}").to_string(),
+///     "bell\u{2407} esc\u{241b}[0m\\u{85}"
+/// );
+/// assert_eq!(ControlPictures("C:\\dir").to_string(), "C:\\\\dir");
+/// assert_eq!(ControlPictures("\u{2407}").to_string(), "\\u{2407}");
+/// ```
+///
+/// [`TextReader::with_control_pictures`]: crate::TextReader::with_control_pictures
+#[derive(Debug, Clone, Copy)]
+pub struct ControlPictures<'a>(pub &'a str);
+
+impl fmt::Display for ControlPictures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '\n' | '\t' => f.write_char(c)?,
+                '\\' => f.write_str("\\\\")?,
+                c if c.is_control() || is_control_picture(c) => write_visible(c, f)?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Test whether `c` is one of the Control Pictures (U+2400–U+2426), which
+/// are escaped so that they aren't mistaken for the control codes they
+/// depict.
+pub(crate) fn is_control_picture(c: char) -> bool {
+    ('\u{2400}'..='\u{2426}').contains(&c)
+}
+
+/// Write the visible form of `c`, a control code or a Control Picture.
+pub(crate) fn write_visible(c: char, w: &mut impl Write) -> fmt::Result {
+    match c {
+        '\0'..='\x1f' => w.write_char(char::from_u32(0x2400 + c as u32).unwrap()),
+        DEL => w.write_char(SYMBOL_FOR_DELETE),
+        c => write!(w, "\\u{{{:x}}}", c as u32),
+    }
+}
+
+/// Write the visible form of `bytes`, an invalid UTF-8 sequence.
+pub(crate) fn write_invalid(bytes: &[u8], w: &mut impl Write) -> fmt::Result {
+    for b in bytes {
+        write!(w, "\\x{:02x}", b)?;
+    }
+    Ok(())
+}
+
+/// SYMBOL FOR DELETE
+const SYMBOL_FOR_DELETE: char = '\u{2421}';
@@ -0,0 +1,62 @@
+//! Defines `InputProfile` and `LineEndings`.
+
+/// A summary of the conventions and irregularities a `TextReader` has seen in
+/// its input so far.
+///
+/// `TextReader` removes a leading BOM and translates CRLF to LF, so this is
+/// how an application such as an editor can find out what the original
+/// looked like, and write content back in the same form with
+/// [`TextWriter::with_input_profile`].
+///
+/// [`TextWriter::with_input_profile`]: crate::TextWriter::with_input_profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct InputProfile {
+    /// Whether the input began with a U+FEFF (BOM), which was removed.
+    pub bom: bool,
+
+    /// The style of line endings in the input.
+    pub line_endings: LineEndings,
+
+    /// Whether any escape sequences were removed.
+    pub escape_sequences: bool,
+
+    /// Whether any content was replaced, for example invalid UTF-8 or
+    /// disallowed codepoints replaced by U+FFFD (REPLACEMENT CHARACTER).
+    /// This doesn't include BOM removal, CRLF translation, escape sequences,
+    /// or normalization.
+    pub replacements: bool,
+}
+
+/// The style of line endings in an input stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEndings {
+    /// No line endings have been seen.
+    None,
+
+    /// All line endings are "\n".
+    Lf,
+
+    /// All line endings are "\r\n".
+    Crlf,
+
+    /// All line endings are "\r" not followed by "\n". `TextReader`
+    /// replaces these with U+FFFD (REPLACEMENT CHARACTER).
+    LoneCr,
+
+    /// More than one style of line ending is present.
+    Mixed,
+}
+
+impl LineEndings {
+    /// Determine the style from the number of each kind of line ending.
+    pub(crate) fn from_counts(lf: u64, crlf: u64, lone_cr: u64) -> Self {
+        match (lf != 0, crlf != 0, lone_cr != 0) {
+            (false, false, false) => Self::None,
+            (true, false, false) => Self::Lf,
+            (false, true, false) => Self::Crlf,
+            (false, false, true) => Self::LoneCr,
+            _ => Self::Mixed,
+        }
+    }
+}
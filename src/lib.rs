@@ -3,24 +3,68 @@
 #![deny(missing_docs)]
 
 mod categorize;
-mod rc_char_queue;
+mod control_pictures;
+mod input_profile;
+mod line_editor;
+#[cfg(any(feature = "log", feature = "tracing-subscriber"))]
+mod log_sinks;
+mod offset_map;
+mod read_buffer;
 mod replace_selected;
+mod replacement_observer;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod tables;
+#[cfg(test)]
+mod test_streams;
+mod text_chars;
+mod text_concat;
+mod text_error;
+mod text_profile;
 mod text_reader;
 mod text_reader_impl;
+mod text_reader_snapshot;
 mod text_reader_writer;
+mod text_string;
+mod text_transform;
 mod text_writer;
 mod text_writer_impl;
+mod to_text;
+#[cfg(feature = "rayon")]
+mod to_text_parallel;
+mod transcript;
 mod unicode;
 mod utf8_reader;
 mod utf8_reader_impl;
 mod utf8_reader_writer;
 mod utf8_writer;
 mod utf8_writer_impl;
+mod validate;
 
+pub use control_pictures::ControlPictures;
+pub use input_profile::{InputProfile, LineEndings};
+#[cfg(feature = "log")]
+pub use log_sinks::TextLogger;
+#[cfg(feature = "tracing-subscriber")]
+pub use log_sinks::{TextMakeWriter, TextRecordWriter};
+pub use offset_map::{OffsetMap, OffsetSpan};
+pub use replacement_observer::{ReplacementCounts, ReplacementObserver};
+pub use tables::UNICODE_VERSION;
+pub use text_chars::{TextCategorize, TextChars, TextReplacements};
+pub use text_concat::TextConcat;
+pub use text_error::TextError;
+pub use text_profile::TextProfile;
 pub use text_reader::TextReader;
+pub use text_reader_snapshot::TextReaderSnapshot;
 pub use text_reader_writer::TextReaderWriter;
+pub use text_string::{TextLineStr, TextLineString, TextStr, TextString};
+pub use text_transform::{TextTransform, TransformStage};
 pub use text_writer::TextWriter;
+pub use to_text::{is_text, to_text};
+#[cfg(feature = "rayon")]
+pub use to_text_parallel::{to_text_parallel, to_text_parallel_with_chunk_size};
 pub use unicode::NORMALIZATION_BUFFER_SIZE;
 pub use utf8_reader::{ReadStr, Utf8Reader};
 pub use utf8_reader_writer::Utf8ReaderWriter;
 pub use utf8_writer::{Utf8Writer, WriteWrapper};
+pub use validate::{validate, validate_with_profile, ValidationReport, Violation, ViolationKind};
@@ -0,0 +1,214 @@
+//! Defines `LineEditor`, which applies terminal line-editing controls for
+//! `TextReader::with_line_editing`.
+
+use crate::{
+    replacement_observer::TerminalEvent,
+    unicode::{DEL, ESC},
+};
+use std::mem;
+
+/// ASCII BS, sent by the backspace key on some terminals.
+const BS: char = '\u{8}';
+
+/// Ctrl-U, which kills the line before the cursor.
+const KILL: char = '\u{15}';
+
+/// Ctrl-W, which erases the word before the cursor.
+const WERASE: char = '\u{17}';
+
+/// Interprets the line-editing controls in raw terminal input, producing
+/// only the edited lines.
+pub(crate) struct LineEditor {
+    /// The line being edited.
+    line: Vec<char>,
+
+    /// The position of the cursor in `line`.
+    cursor: usize,
+
+    /// Escape-sequence state machine.
+    state: State,
+
+    /// True if the previous char was a '\r' which ended a line, so that a
+    /// following '\n' doesn't end another.
+    after_cr: bool,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum State {
+    // Default state.
+    Ground,
+
+    // After a '\x1b'.
+    Esc,
+
+    // Within a sequence started by "\x1b[", with the parameter bytes so far.
+    Csi(String),
+
+    // After a "\x1bO".
+    Ss3,
+}
+
+impl LineEditor {
+    pub(crate) fn new() -> Self {
+        Self {
+            line: Vec::new(),
+            cursor: 0,
+            state: State::Ground,
+            after_cr: false,
+        }
+    }
+
+    /// Apply the keystrokes in `input`, appending each line which is ended
+    /// to `output`. If `end` is true, the input has ended, and a partially
+    /// edited line is appended too. What's discarded without being
+    /// interpreted is recorded in `events`, with its position in `input`.
+    pub(crate) fn apply(
+        &mut self,
+        input: &str,
+        end: bool,
+        output: &mut String,
+        events: &mut Vec<(usize, TerminalEvent)>,
+    ) {
+        for (index, c) in input.char_indices() {
+            self.char(c, output, &mut |event| events.push((index, event)));
+        }
+        if end {
+            output.extend(self.line.drain(..));
+            self.cursor = 0;
+            if mem::replace(&mut self.state, State::Ground) != State::Ground {
+                events.push((input.len(), TerminalEvent::IgnoredSequence));
+            }
+        }
+    }
+
+    fn char(&mut self, c: char, output: &mut String, event: &mut dyn FnMut(TerminalEvent)) {
+        let after_cr = self.after_cr;
+        self.after_cr = false;
+
+        match mem::replace(&mut self.state, State::Ground) {
+            State::Ground => {}
+            State::Esc => {
+                match c {
+                    '[' => self.state = State::Csi(String::new()),
+                    'O' => self.state = State::Ss3,
+                    // Ignore other sequences, such as Alt-modified keys.
+                    c if !c.is_control() => event(TerminalEvent::IgnoredSequence),
+                    c => {
+                        event(TerminalEvent::IgnoredSequence);
+                        self.char(c, output, event)
+                    }
+                }
+                return;
+            }
+            State::Csi(mut params) => {
+                match c {
+                    '\u{20}'..='\u{3f}' => {
+                        params.push(c);
+                        self.state = State::Csi(params);
+                    }
+                    '\u{40}'..='\u{7e}' => {
+                        if !self.csi(&params, c) {
+                            event(TerminalEvent::IgnoredSequence)
+                        }
+                    }
+                    // An interrupted sequence is ignored.
+                    c => {
+                        event(TerminalEvent::IgnoredSequence);
+                        self.char(c, output, event)
+                    }
+                }
+                return;
+            }
+            State::Ss3 => {
+                match c {
+                    c if !c.is_control() => {
+                        if !self.cursor_key(c, 1) {
+                            event(TerminalEvent::IgnoredSequence)
+                        }
+                    }
+                    c => {
+                        event(TerminalEvent::IgnoredSequence);
+                        self.char(c, output, event)
+                    }
+                }
+                return;
+            }
+        }
+
+        match c {
+            '\n' if after_cr => {}
+            '\r' | '\n' => {
+                output.extend(self.line.drain(..));
+                output.push('\n');
+                self.cursor = 0;
+                self.after_cr = c == '\r';
+            }
+            BS | DEL => {
+                if self.cursor != 0 {
+                    self.cursor -= 1;
+                    self.line.remove(self.cursor);
+                }
+            }
+            WERASE => {
+                let mut start = self.cursor;
+                while start != 0 && self.line[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                while start != 0 && !self.line[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                self.line.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            KILL => {
+                self.line.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            ESC => {
+                event(TerminalEvent::Escape);
+                self.state = State::Esc
+            }
+            c => {
+                self.line.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+        }
+    }
+
+    /// Handle a CSI sequence with parameters `params` and final byte
+    /// `final_`, and return false if it's ignored.
+    fn csi(&mut self, params: &str, final_: char) -> bool {
+        // The first parameter, as in "\x1b[5D", or in "\x1b[3~" for keys
+        // such as Delete.
+        let first = params.split(';').next().unwrap_or("");
+        let first = first.parse::<usize>().ok();
+        match final_ {
+            '~' => match first {
+                Some(1) | Some(7) => self.cursor_key('H', 1),
+                Some(4) | Some(8) => self.cursor_key('F', 1),
+                Some(3) => {
+                    if self.cursor < self.line.len() {
+                        self.line.remove(self.cursor);
+                    }
+                    true
+                }
+                _ => false,
+            },
+            c => self.cursor_key(c, first.unwrap_or(1).max(1)),
+        }
+    }
+
+    /// Handle a cursor key, identified by the final byte of its CSI or SS3
+    /// sequence, repeated `count` times, and return false if it's ignored.
+    fn cursor_key(&mut self, c: char, count: usize) -> bool {
+        match c {
+            'C' => self.cursor = self.line.len().min(self.cursor.saturating_add(count)),
+            'D' => self.cursor = self.cursor.saturating_sub(count),
+            'H' => self.cursor = 0,
+            'F' => self.cursor = self.line.len(),
+            // Ignore other keys, such as up and down.
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,228 @@
+//! Sinks for log output which sanitize each record into valid Text, so that
+//! attacker-controlled strings in log messages can't inject terminal escape
+//! sequences or fake log lines. `TextLogger` is enabled with the `log`
+//! feature, and `TextMakeWriter` with the `tracing-subscriber` feature.
+
+use crate::{TextReader, TextWriter};
+use io_ext::{SliceReader, WriteExt};
+use std::{
+    io::{self, Read, Write},
+    sync::{Mutex, MutexGuard},
+};
+
+/// SYMBOL FOR NEWLINE, which embedded newlines in single-line records are
+/// replaced with.
+const SYMBOL_FOR_NEWLINE: &str = "\u{2424}";
+
+/// SYMBOL FOR CARRIAGE RETURN, which shows the CR of an embedded CRLF in
+/// single-line records.
+const SYMBOL_FOR_CARRIAGE_RETURN: &str = "\u{240d}";
+
+/// Translate `record` into valid Text ending with a newline, showing control
+/// codes, escape sequences, and invalid UTF-8 in a visible form, and escaping
+/// backslashes and Control Pictures so that those forms can't be forged, as
+/// `TextReader::with_control_pictures` does. If `single_line` is true,
+/// newlines other than a final one are then replaced with U+2424 (SYMBOL FOR
+/// NEWLINE), and the CRs of CRLFs among them with U+240D (SYMBOL FOR
+/// CARRIAGE RETURN).
+pub(crate) fn sanitize_record(record: &[u8], single_line: bool) -> String {
+    let body = match record.strip_suffix(b"\n") {
+        Some(body) => body.strip_suffix(b"\r").unwrap_or(body),
+        None => record,
+    };
+
+    let mut s = String::new();
+    TextReader::with_control_pictures(SliceReader::new(body))
+        .read_to_string(&mut s)
+        .expect("reading from a slice can't fail");
+
+    match s.strip_suffix('\n') {
+        Some(lines) if single_line && lines.contains('\n') => {
+            // Each newline in `body` is a newline in `lines`, but the
+            // CRs of CRLFs aren't shown.
+            let crlfs = body
+                .split(|b| *b == b'\n')
+                .map(|line| line.ends_with(b"\r"));
+            let lines = lines.split('\n').collect::<Vec<_>>();
+            let mut single = String::new();
+            for (index, (line, crlf)) in lines.iter().zip(crlfs).enumerate() {
+                if index != 0 {
+                    single.push_str(SYMBOL_FOR_NEWLINE);
+                }
+                single.push_str(line);
+                // A CR at the very end isn't part of a CRLF, and is shown
+                // already.
+                if crlf && index + 1 < lines.len() {
+                    single.push_str(SYMBOL_FOR_CARRIAGE_RETURN);
+                }
+            }
+            single.push('\n');
+            single
+        }
+        _ => s,
+    }
+}
+
+/// A `TextWriter` shared between threads, which records are written to.
+struct Sink<Inner: WriteExt> {
+    writer: Mutex<TextWriter<Inner>>,
+    single_line: bool,
+}
+
+impl<Inner: WriteExt> Sink<Inner> {
+    fn new(writer: TextWriter<Inner>, single_line: bool) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            single_line,
+        }
+    }
+
+    /// Sanitize `record` and write it as a unit.
+    fn write_record(&self, record: &[u8]) -> io::Result<()> {
+        let record = sanitize_record(record, self.single_line);
+        if record.is_empty() {
+            return Ok(());
+        }
+        let mut writer = self.lock();
+        writer.write_str(&record)?;
+        writer.flush()
+    }
+
+    /// Lock the writer. If another thread panicked while holding the lock,
+    /// the writer is still usable, since each record is written as a unit.
+    fn lock(&self) -> MutexGuard<'_, TextWriter<Inner>> {
+        self.writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A [`log::Log`] implementation which writes each record as a single line
+/// of the form "LEVEL target: message" to a `TextWriter`.
+///
+/// Control codes and escape sequences in messages are shown in a visible
+/// form, as [`TextReader::with_control_pictures`] does, and embedded
+/// newlines are replaced with U+2424 (SYMBOL FOR NEWLINE), so each record is
+/// exactly one line of valid Text. Literal Control Pictures in messages,
+/// including U+2424, are escaped first, so they can't be mistaken for
+/// either. Errors writing records are ignored, since there's nowhere to
+/// report them.
+///
+/// [`TextReader::with_control_pictures`]: crate::TextReader::with_control_pictures
+#[cfg(feature = "log")]
+pub struct TextLogger<Inner: WriteExt> {
+    sink: Sink<Inner>,
+}
+
+#[cfg(feature = "log")]
+impl<Inner: WriteExt> TextLogger<Inner> {
+    /// Construct a new instance of `TextLogger` writing to `writer`.
+    #[inline]
+    pub fn new(writer: TextWriter<Inner>) -> Self {
+        Self {
+            sink: Sink::new(writer, true),
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl<Inner: WriteExt + Send> log::Log for TextLogger<Inner> {
+    #[inline]
+    fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+        self.sink.write_record(line.as_bytes()).ok();
+    }
+
+    fn flush(&self) {
+        self.sink.lock().flush().ok();
+    }
+}
+
+/// A [`MakeWriter`] for `tracing-subscriber`'s `fmt` layer which writes each
+/// event to a `TextWriter` as a unit.
+///
+/// Control codes and escape sequences in field values are shown in a
+/// visible form, as [`TextReader::with_control_pictures`] does, so each
+/// record is valid Text. By default, records are assumed to be single
+/// lines, and embedded newlines are replaced with U+2424 (SYMBOL FOR
+/// NEWLINE), after escaping any literal U+2424; use
+/// [`with_multi_line_records`] for multi-line formats such as `pretty`. ANSI colors should be disabled in the layer, since they're
+/// escape sequences too. Errors writing records are ignored, since there's
+/// nowhere to report them.
+///
+/// [`MakeWriter`]: tracing_subscriber::fmt::MakeWriter
+/// [`TextReader::with_control_pictures`]: crate::TextReader::with_control_pictures
+/// [`with_multi_line_records`]: TextMakeWriter::with_multi_line_records
+#[cfg(feature = "tracing-subscriber")]
+pub struct TextMakeWriter<Inner: WriteExt> {
+    sink: Sink<Inner>,
+}
+
+#[cfg(feature = "tracing-subscriber")]
+impl<Inner: WriteExt> TextMakeWriter<Inner> {
+    /// Construct a new instance of `TextMakeWriter` writing single-line
+    /// records to `writer`.
+    #[inline]
+    pub fn new(writer: TextWriter<Inner>) -> Self {
+        Self {
+            sink: Sink::new(writer, true),
+        }
+    }
+
+    /// Like `new`, but keeps newlines within records.
+    #[inline]
+    pub fn with_multi_line_records(writer: TextWriter<Inner>) -> Self {
+        Self {
+            sink: Sink::new(writer, false),
+        }
+    }
+}
+
+#[cfg(feature = "tracing-subscriber")]
+impl<'a, Inner: WriteExt + 'a> tracing_subscriber::fmt::MakeWriter<'a> for TextMakeWriter<Inner> {
+    type Writer = TextRecordWriter<'a, Inner>;
+
+    #[inline]
+    fn make_writer(&'a self) -> Self::Writer {
+        TextRecordWriter {
+            sink: &self.sink,
+            record: Vec::new(),
+        }
+    }
+}
+
+/// The writer returned by `TextMakeWriter`, which collects a record and
+/// writes it when flushed or dropped.
+#[cfg(feature = "tracing-subscriber")]
+pub struct TextRecordWriter<'a, Inner: WriteExt> {
+    sink: &'a Sink<Inner>,
+    record: Vec<u8>,
+}
+
+#[cfg(feature = "tracing-subscriber")]
+impl<Inner: WriteExt> io::Write for TextRecordWriter<'_, Inner> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.record.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.record.is_empty() {
+            return Ok(());
+        }
+        let record = std::mem::take(&mut self.record);
+        self.sink.write_record(&record)
+    }
+}
+
+#[cfg(feature = "tracing-subscriber")]
+impl<Inner: WriteExt> Drop for TextRecordWriter<'_, Inner> {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
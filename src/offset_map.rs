@@ -0,0 +1,109 @@
+//! Defines `OffsetMap` and `OffsetSpan`.
+
+use std::ops::Range;
+
+/// A map from byte offsets in the output of a `TextReader` to byte offsets
+/// in its input.
+///
+/// The map is a sequence of spans, each relating a range of the output to
+/// the range of the input it was produced from. Runs of input which pass
+/// through unmodified are merged into single exact spans, so the map stays
+/// small for mostly-valid input.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OffsetMap {
+    spans: Vec<OffsetSpan>,
+}
+
+/// A span of an `OffsetMap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetSpan {
+    /// The range of output bytes.
+    pub output: Range<u64>,
+
+    /// The range of input bytes the output was produced from.
+    pub input: Range<u64>,
+
+    /// True if the output is a byte-for-byte copy of the input, so that
+    /// offsets within the span correspond exactly. Otherwise, the input was
+    /// transformed, for example by normalization, replacement, or the
+    /// removal of a BOM, carriage return, or escape sequence, and offsets
+    /// within the span only correspond as a whole. Input which produces no
+    /// output, such as a trailing escape sequence, has an empty output range.
+    pub exact: bool,
+}
+
+impl OffsetMap {
+    /// Construct an empty `OffsetMap`.
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self { spans: Vec::new() }
+    }
+
+    /// Return the spans, in order.
+    #[inline]
+    pub fn spans(&self) -> &[OffsetSpan] {
+        &self.spans
+    }
+
+    /// Test whether there are no spans.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Map a range of output bytes to the range of input bytes it was
+    /// produced from. Ranges which begin or end within a span which isn't
+    /// exact are widened to cover the whole span. Returns `None` if the
+    /// range isn't covered by the map.
+    pub fn to_input(&self, output: Range<u64>) -> Option<Range<u64>> {
+        let output = output.start..output.end.max(output.start);
+        let first = self.span_index(output.start)?;
+        let last = if output.end > output.start {
+            self.span_index(output.end - 1)?
+        } else {
+            first
+        };
+
+        let first = &self.spans[first];
+        let start = if first.exact {
+            first.input.start + (output.start - first.output.start)
+        } else {
+            first.input.start
+        };
+
+        let last = &self.spans[last];
+        let end = if last.exact {
+            last.input.start + (output.end - last.output.start)
+        } else {
+            last.input.end
+        };
+
+        Some(start..end)
+    }
+
+    /// Find the span containing the output byte at `offset`.
+    fn span_index(&self, offset: u64) -> Option<usize> {
+        let index = self.spans.partition_point(|span| span.output.end <= offset);
+        match self.spans.get(index) {
+            Some(span) if span.output.start <= offset => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Append a span, merging it into the previous one if both are exact
+    /// and contiguous.
+    pub(crate) fn push(&mut self, span: OffsetSpan) {
+        if let Some(last) = self.spans.last_mut() {
+            if last.exact
+                && span.exact
+                && last.output.end == span.output.start
+                && last.input.end == span.input.start
+            {
+                last.output.end = span.output.end;
+                last.input.end = span.input.end;
+                return;
+            }
+        }
+        self.spans.push(span);
+    }
+}
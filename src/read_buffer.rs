@@ -0,0 +1,88 @@
+//! Defines `ReadBuffer`.
+
+use io_ext::Status;
+use std::mem;
+
+/// The size of the buffer `fill_buf` reads into.
+pub(crate) const READ_BUFFER_SIZE: usize = 4096;
+
+/// Output which has been produced for `BufRead::fill_buf` but not yet
+/// consumed. The contents always end on a scalar value boundary.
+pub(crate) struct ReadBuffer {
+    /// The storage, which stays at `READ_BUFFER_SIZE` once allocated, so
+    /// that it isn't cleared for each read.
+    buf: Vec<u8>,
+
+    /// The number of bytes at the front of `buf` which have been consumed.
+    pos: usize,
+
+    /// The end of the contents of `buf`.
+    end: usize,
+
+    /// The status of the read which filled `buf`, reported once the buffer
+    /// has been drained by a read.
+    status: Status,
+}
+
+impl ReadBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            pos: 0,
+            end: 0,
+            status: Status::active(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos == self.end
+    }
+
+    /// The unconsumed contents of the buffer.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.buf[self.pos..self.end]
+    }
+
+    /// Take the underlying storage, sized for a read, leaving this buffer
+    /// empty.
+    pub(crate) fn take_storage(&mut self) -> Vec<u8> {
+        let mut buf = mem::take(&mut self.buf);
+        self.pos = 0;
+        self.end = 0;
+        self.status = Status::active();
+        buf.resize(READ_BUFFER_SIZE, 0);
+        buf
+    }
+
+    /// Install `buf`, holding `len` bytes of contents, as the storage of
+    /// this buffer.
+    pub(crate) fn fill(&mut self, buf: Vec<u8>, len: usize, status: Status) {
+        debug_assert!(self.is_empty());
+        self.buf = buf;
+        self.pos = 0;
+        self.end = len;
+        self.status = status;
+    }
+
+    pub(crate) fn consume(&mut self, amt: usize) {
+        self.pos = self.end.min(self.pos + amt);
+    }
+
+    /// Copy as much of the unconsumed contents into `buf` as will fit without
+    /// splitting a scalar value. `buf` must be at least 4 bytes long.
+    pub(crate) fn drain(&mut self, buf: &mut [u8]) -> (usize, Status) {
+        let data = self.data();
+        let mut size = buf.len().min(data.len());
+        while size < data.len() && (data[size] & 0xc0) == 0x80 {
+            size -= 1;
+        }
+        buf[..size].copy_from_slice(&data[..size]);
+        self.pos += size;
+
+        if self.is_empty() {
+            (size, mem::replace(&mut self.status, Status::active()))
+        } else {
+            (size, Status::active())
+        }
+    }
+}
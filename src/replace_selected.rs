@@ -2,24 +2,74 @@
 //! containing them can still be read, but applications don't have to
 //! handle them.
 
-use crate::unicode::{BOM, REPL, WJ};
+use crate::{
+    text_profile::TextProfile,
+    unicode::{
+        disallowed, is_tag_spec_character, BOM, CANCEL_TAG, MAX_TAG_SPEC_LEN, REPL,
+        WAVING_BLACK_FLAG, WJ,
+    },
+};
+use std::collections::VecDeque;
 
 /// An iterator over `char`s which replaces occurrences of
 /// characters that have replacement sequences.
 pub(crate) struct ReplaceSelected<Inner: Iterator<Item = char>> {
     inner: Inner,
 
-    /// At this time, the longest replacement sequences is 2 USVs,
-    /// so we need at most one in the buffer.
-    buffer: Option<char>,
+    /// The version of the rules to apply.
+    profile: TextProfile,
+
+    /// The rest of a replacement sequence, or of an emoji tag sequence,
+    /// waiting to be returned.
+    buffer: VecDeque<char>,
+
+    /// A `char` read from `inner` while scanning an emoji tag sequence,
+    /// which hasn't been processed yet.
+    lookahead: Option<char>,
+
+    /// The number of `char`s which have been replaced.
+    replacements: u64,
 }
 
 impl<Inner: Iterator<Item = char>> ReplaceSelected<Inner> {
     #[inline]
-    pub(crate) fn new(inner: Inner) -> Self {
+    pub(crate) fn new(inner: Inner, profile: TextProfile) -> Self {
         Self {
             inner,
-            buffer: None,
+            profile,
+            buffer: VecDeque::new(),
+            lookahead: None,
+            replacements: 0,
+        }
+    }
+
+    /// The number of `char`s which have been replaced so far.
+    #[inline]
+    pub(crate) fn replacements(&self) -> u64 {
+        self.replacements
+    }
+
+    /// After a tag base, read any following tag characters into `buffer`,
+    /// replacing them if they don't form a well-formed emoji tag sequence.
+    fn tag_sequence(&mut self) {
+        loop {
+            match self.inner.next() {
+                Some(c) if is_tag_spec_character(c) && self.buffer.len() < MAX_TAG_SPEC_LEN => {
+                    self.buffer.push_back(c)
+                }
+                Some(CANCEL_TAG) if !self.buffer.is_empty() => {
+                    self.buffer.push_back(CANCEL_TAG);
+                    return;
+                }
+                other => {
+                    for c in &mut self.buffer {
+                        *c = REPL;
+                    }
+                    self.replacements += self.buffer.len() as u64;
+                    self.lookahead = other;
+                    return;
+                }
+            }
         }
     }
 }
@@ -29,62 +79,48 @@ impl<Inner: Iterator<Item = char>> Iterator for ReplaceSelected<Inner> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(c) = self.buffer.take() {
+        if let Some(c) = self.buffer.pop_front() {
             return Some(c);
         }
 
-        match self.inner.next()? {
+        let c = match self.lookahead.take() {
+            Some(c) => c,
+            None => self.inner.next()?,
+        };
+
+        let replaced = match c {
             BOM => Some(WJ),
+            WAVING_BLACK_FLAG if self.profile.permits_emoji_tag_sequences() => {
+                self.tag_sequence();
+                return Some(WAVING_BLACK_FLAG);
+            }
             '\u{149}' => {
-                self.buffer = Some('\u{6e}');
+                self.buffer.push_back('\u{6e}');
                 Some('\u{2bc}')
             }
             '\u{673}' => {
-                self.buffer = Some('\u{65f}');
+                self.buffer.push_back('\u{65f}');
                 Some('\u{627}')
             }
             '\u{f77}' => {
-                self.buffer = Some('\u{f81}');
+                self.buffer.push_back('\u{f81}');
                 Some('\u{fb2}')
             }
             '\u{f79}' => {
-                self.buffer = Some('\u{f81}');
+                self.buffer.push_back('\u{f81}');
                 Some('\u{fb3}')
             }
             '\u{17a3}' => Some('\u{17a2}'),
             '\u{17a4}' => {
-                self.buffer = Some('\u{17b6}');
+                self.buffer.push_back('\u{17b6}');
                 Some('\u{17a2}')
             }
-            // Deprecated Format Characters
-            '\u{206a}'..='\u{206f}' |
-            // Tag Characters
-            '\u{e0000}'..='\u{e007f}' |
-            // Noncharacters
-            '\u{fffe}' ..= '\u{ffff}' |
-            '\u{1fffe}' ..= '\u{1ffff}' |
-            '\u{2fffe}' ..= '\u{2ffff}' |
-            '\u{3fffe}' ..= '\u{3ffff}' |
-            '\u{4fffe}' ..= '\u{4ffff}' |
-            '\u{5fffe}' ..= '\u{5ffff}' |
-            '\u{6fffe}' ..= '\u{6ffff}' |
-            '\u{7fffe}' ..= '\u{7ffff}' |
-            '\u{8fffe}' ..= '\u{8ffff}' |
-            '\u{9fffe}' ..= '\u{9ffff}' |
-            '\u{afffe}' ..= '\u{affff}' |
-            '\u{bfffe}' ..= '\u{bffff}' |
-            '\u{cfffe}' ..= '\u{cffff}' |
-            '\u{dfffe}' ..= '\u{dffff}' |
-            '\u{efffe}' ..= '\u{effff}' |
-            '\u{ffffe}' ..= '\u{fffff}' |
-            '\u{10fffe}' ..= '\u{10ffff}' |
-            '\u{fdd0}'..='\u{fdef}' |
-            // Private-Use Characters
-            '\u{e000}'..= '\u{f8ff}' |
-            '\u{f0000}'..= '\u{ffffd}' |
-            '\u{100000}' ..= '\u{10fffd}' => Some(REPL),
-
-            c => Some(c),
-        }
+            // Deprecated Format Characters, Tag Characters other than in emoji
+            // tag sequences, Noncharacters, and Private-Use Characters
+            c if disallowed(c).is_some() => Some(REPL),
+            c => return Some(c),
+        };
+        self.replacements += 1;
+        replaced
     }
 }
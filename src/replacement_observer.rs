@@ -0,0 +1,351 @@
+//! Defines `ReplacementObserver` and `ReplacementCounts`.
+
+use crate::{
+    unicode::REPL,
+    validate::{Position, QueueRules},
+    TextProfile, Violation, ViolationKind,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// A callback for the replacements `Utf8Reader` and `TextReader` make in
+/// their input, for monitoring upstream sources without a separate
+/// validation pass.
+///
+/// Each event is described by a `Violation`, which holds its kind, its
+/// offset in the input, and what it was replaced with, along with the
+/// original bytes: the invalid sequence for `ViolationKind::InvalidUtf8`,
+/// or the UTF-8 encoding of the violation's codepoint otherwise.
+///
+/// Any `FnMut(&Violation, &[u8])` closure can be used as an observer.
+pub trait ReplacementObserver {
+    /// Called for each replacement.
+    fn replaced(&mut self, violation: &Violation, original: &[u8]);
+}
+
+impl<F: FnMut(&Violation, &[u8])> ReplacementObserver for F {
+    #[inline]
+    fn replaced(&mut self, violation: &Violation, original: &[u8]) {
+        self(violation, original)
+    }
+}
+
+/// The number of replacements of each kind a reader has made while a
+/// `ReplacementObserver` was set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplacementCounts {
+    counts: HashMap<ViolationKind, u64>,
+}
+
+impl ReplacementCounts {
+    /// The number of replacements of the given kind.
+    #[inline]
+    pub fn get(&self, kind: ViolationKind) -> u64 {
+        self.counts.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// The total number of replacements of all kinds.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Iterate over the kinds of replacements made and their counts, in
+    /// no particular order.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (ViolationKind, u64)> + '_ {
+        self.counts.iter().map(|(kind, count)| (*kind, *count))
+    }
+}
+
+/// A `ReplacementObserver` and the counts of what it's been sent.
+pub(crate) struct Observing {
+    /// The observer. It's only accessed through `&mut self`, using
+    /// `Mutex::get_mut`, so it's never locked; the `Mutex` is there so that
+    /// streams holding observers can be `Sync`.
+    observer: Mutex<Box<dyn ReplacementObserver + Send>>,
+    counts: ReplacementCounts,
+}
+
+impl Observing {
+    pub(crate) fn new(observer: Box<dyn ReplacementObserver + Send>) -> Self {
+        Self {
+            observer: Mutex::new(observer),
+            counts: ReplacementCounts::default(),
+        }
+    }
+
+    fn report(&mut self, violation: &Violation, original: &[u8]) {
+        *self.counts.counts.entry(violation.kind).or_insert(0) += 1;
+        self.observer
+            .get_mut()
+            .unwrap()
+            .replaced(violation, original);
+    }
+
+    #[inline]
+    pub(crate) fn counts(&self) -> &ReplacementCounts {
+        &self.counts
+    }
+}
+
+/// The original bytes of the input replaced in `violation`, when they're
+/// the encoding of its codepoint.
+fn encoded(violation: &Violation) -> Vec<u8> {
+    let mut buf = [0_u8; 4];
+    violation
+        .codepoint
+        .map_or_else(Vec::new, |c| c.encode_utf8(&mut buf).as_bytes().to_vec())
+}
+
+/// Observation for `Utf8Reader`, which reports invalid UTF-8.
+pub(crate) struct Utf8Observing {
+    observing: Observing,
+
+    /// The position in the input of the start of the next output.
+    position: Position,
+}
+
+impl Utf8Observing {
+    pub(crate) fn new(observer: Box<dyn ReplacementObserver + Send>) -> Self {
+        Self {
+            observing: Observing::new(observer),
+            position: Position::start(),
+        }
+    }
+
+    /// Report the invalid sequences in `invalid`, which are positions in
+    /// `output` paired with the bytes replaced there, and advance past
+    /// `output`.
+    pub(crate) fn output(&mut self, output: &str, invalid: &mut VecDeque<(usize, Vec<u8>)>) {
+        for (index, c) in output.char_indices() {
+            match invalid.front() {
+                Some((at, _)) if *at == index => {
+                    let (_, bytes) = invalid.pop_front().unwrap();
+                    debug_assert_eq!(c, REPL);
+                    let violation = self
+                        .position
+                        .violation(ViolationKind::InvalidUtf8, None, REPL);
+                    self.observing.report(&violation, &bytes);
+                    self.position.advance(c, bytes.len());
+                }
+                _ => self.position.advance(c, c.len_utf8()),
+            }
+        }
+        debug_assert!(invalid.is_empty());
+    }
+
+    #[inline]
+    pub(crate) fn counts(&self) -> &ReplacementCounts {
+        self.observing.counts()
+    }
+}
+
+/// What the terminal modes discard from their input without interpreting
+/// it, for reporting to observers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TerminalEvent {
+    /// A U+001B (ESC) which begins an escape sequence.
+    Escape,
+
+    /// The escape sequence begun by the last `Escape` is discarded.
+    IgnoredSequence,
+
+    /// A control code is discarded.
+    IgnoredControl(char),
+}
+
+/// Observation for `TextReader`. Each stage of the pipeline reports the
+/// replacements it makes as it makes them.
+pub(crate) struct TextObserving {
+    observing: Observing,
+
+    /// The position of the next `char` of input to the Text rules.
+    position: Position,
+
+    /// The position of the `char` the Text rules are processing.
+    current: Position,
+
+    /// The position of the U+000D or U+001B which started the current state.
+    state_start: Position,
+
+    /// The rules applied to the `char`s in the normalization queue, which
+    /// are checked as they're queued, as `validate` does.
+    queue_rules: QueueRules,
+
+    /// Events which may still be preceded by events not made yet, such as
+    /// normalization of the queued `char`s before them.
+    pending: Vec<(Violation, Vec<u8>)>,
+
+    /// The position in the underlying input, when terminal controls are
+    /// interpreted or `BeforeNormalization` transforms are applied, so
+    /// that the Text rules see something other than the input.
+    raw: Position,
+
+    /// The position in the underlying input of the start of the escape
+    /// sequence a terminal mode is in.
+    raw_sequence_start: Position,
+}
+
+impl TextObserving {
+    pub(crate) fn new(observer: Box<dyn ReplacementObserver + Send>, profile: TextProfile) -> Self {
+        Self {
+            observing: Observing::new(observer),
+            position: Position::start(),
+            current: Position::start(),
+            state_start: Position::start(),
+            queue_rules: QueueRules::new(profile),
+            pending: Vec::new(),
+            raw: Position::start(),
+            raw_sequence_start: Position::start(),
+        }
+    }
+
+    /// Report the invalid UTF-8 in `input`, the output of the underlying
+    /// `Utf8Reader`, and the events in `events`, which are positions in
+    /// `input` paired with what a terminal mode discarded there, when the
+    /// Text rules won't see `input` itself.
+    pub(crate) fn raw_input(
+        &mut self,
+        input: &str,
+        invalid: &mut VecDeque<(usize, Vec<u8>)>,
+        events: &[(usize, TerminalEvent)],
+    ) {
+        let mut events = events.iter().peekable();
+        for (index, c) in input.char_indices() {
+            let current = self.raw;
+            match invalid.front() {
+                Some((at, _)) if *at == index => {
+                    let (_, bytes) = invalid.pop_front().unwrap();
+                    let violation = current.violation(ViolationKind::InvalidUtf8, None, REPL);
+                    self.observing.report(&violation, &bytes);
+                    self.raw.advance(c, bytes.len());
+                }
+                _ => self.raw.advance(c, c.len_utf8()),
+            }
+            while let Some((_, event)) = events.next_if(|(at, _)| *at == index) {
+                self.terminal_event(*event, current);
+            }
+        }
+        for (_, event) in events {
+            self.terminal_event(*event, self.raw);
+        }
+        debug_assert!(invalid.is_empty());
+    }
+
+    fn terminal_event(&mut self, event: TerminalEvent, at: Position) {
+        let violation = match event {
+            TerminalEvent::Escape => {
+                self.raw_sequence_start = at;
+                return;
+            }
+            TerminalEvent::IgnoredSequence => {
+                self.raw_sequence_start
+                    .violation(ViolationKind::EscapeSequence, Some('\u{1b}'), "")
+            }
+            TerminalEvent::IgnoredControl(c) => {
+                at.violation(ViolationKind::ControlCode, Some(c), "")
+            }
+        };
+        self.observing.report(&violation, &encoded(&violation));
+    }
+
+    /// Advance past `c`, the next `char` of input to the Text rules, which
+    /// came from `len` bytes of input.
+    #[inline]
+    pub(crate) fn advance(&mut self, c: char, len: usize) {
+        self.current = self.position;
+        self.position.advance(c, len);
+    }
+
+    /// Record that the current `char` starts a state.
+    #[inline]
+    pub(crate) fn start_state(&mut self) {
+        self.state_start = self.current;
+    }
+
+    /// Attribute what follows to the end of the input.
+    #[inline]
+    pub(crate) fn at_end(&mut self) {
+        self.current = self.position;
+    }
+
+    /// Check `c`, a `char` produced from the current one, as it's queued.
+    pub(crate) fn queued(&mut self, c: char) {
+        let mut violations = Vec::new();
+        self.queue_rules.queue(c, self.current, &mut violations);
+        self.pending_violations(violations);
+    }
+
+    /// Check what's left in the normalization queue at the end of the
+    /// stream or a push.
+    pub(crate) fn end(&mut self) {
+        let mut violations = Vec::new();
+        self.queue_rules.end(&mut violations);
+        self.pending_violations(violations);
+    }
+
+    fn pending_violations(&mut self, violations: Vec<Violation>) {
+        for violation in violations {
+            let original = encoded(&violation);
+            self.pending.push((violation, original));
+        }
+    }
+
+    /// Report a replacement of the current `char`.
+    pub(crate) fn replaced(&mut self, kind: ViolationKind, c: Option<char>, replacement: &str) {
+        let violation = self.current.violation(kind, c, replacement);
+        let original = encoded(&violation);
+        self.pending.push((violation, original));
+    }
+
+    /// Report a replacement of the `char` which started the current state.
+    pub(crate) fn replaced_state_start(&mut self, kind: ViolationKind, c: char, replacement: &str) {
+        let violation = self.state_start.violation(kind, Some(c), replacement);
+        let original = encoded(&violation);
+        self.pending.push((violation, original));
+    }
+
+    /// Report a replacement of invalid UTF-8 by the underlying `Utf8Reader`,
+    /// which the Text rules replaced in turn with `replacement`.
+    pub(crate) fn replaced_invalid(&mut self, bytes: Vec<u8>, replacement: &str) {
+        let violation = self
+            .current
+            .violation(ViolationKind::InvalidUtf8, None, replacement);
+        self.pending.push((violation, bytes));
+    }
+
+    /// Report the pending events before `limit`, the offset of the start of
+    /// an incomplete escape sequence or line ending, if any, and before the
+    /// queued `char`s, which may still be replaced by normalization.
+    pub(crate) fn flush(&mut self, limit: Option<u64>) {
+        let limit = limit
+            .into_iter()
+            .chain(self.queue_rules.unsettled_offset())
+            .min()
+            .unwrap_or(u64::MAX);
+        self.pending.sort_by_key(|(violation, _)| violation.offset);
+        let end = self
+            .pending
+            .iter()
+            .position(|(violation, _)| violation.offset >= limit)
+            .unwrap_or(self.pending.len());
+        let ready = self.pending.drain(..end).collect::<Vec<_>>();
+        for (violation, original) in ready {
+            self.observing.report(&violation, &original);
+        }
+    }
+
+    /// The offset of the start of the current state.
+    #[inline]
+    pub(crate) fn state_start_offset(&self) -> u64 {
+        self.state_start.offset
+    }
+
+    #[inline]
+    pub(crate) fn counts(&self) -> &ReplacementCounts {
+        self.observing.counts()
+    }
+}
@@ -0,0 +1,223 @@
+//! Serde support for Text strings, enabled with the `serde` feature.
+//!
+//! `TextStr` and `TextString` implement `Serialize` and `Deserialize`, and
+//! deserializing fails if the input isn't a valid Text document. Likewise for
+//! `TextLineStr` and `TextLineString`, which hold a single line.
+//!
+//! For plain `String` fields, the [`reject`] and [`sanitize`] modules can be
+//! used with `#[serde(with = "...")]`. These apply the same rules as
+//! `TextReader` and `TextWriter` to each string, except that strings aren't
+//! required to end with a newline, since they're typically short values
+//! rather than documents:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     #[serde(with = "text_streams::serde::reject")]
+//!     name: String,
+//!     #[serde(with = "text_streams::serde::sanitize")]
+//!     bio: String,
+//! }
+//! ```
+//!
+//! The Text format permits bidirectional formatting characters, but the
+//! embedding, override, and isolate controls (U+202A through U+202E and
+//! U+2066 through U+2069) can make a string display differently from its
+//! logical order, so `reject` and `sanitize` also reject them or replace them
+//! with U+FFFD (REPLACEMENT CHARACTER). The validated string types follow the
+//! Text format exactly. See [Restricted Text] for further rules which address
+//! visual ambiguity.
+//!
+//! [Restricted Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/restricted-text.md
+
+use crate::{
+    to_text::{is_plain_ascii, to_text},
+    unicode::REPL,
+    validate::first_violation,
+    TextLineStr, TextLineString, TextProfile, TextStr, TextString, Violation, ViolationKind,
+};
+use ::serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::{borrow::Cow, convert::TryFrom};
+
+impl Serialize for TextStr {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl Serialize for TextString {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a TextStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <&str>::deserialize(deserializer)?;
+        <&TextStr>::try_from(s).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for TextString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        TextString::try_from(s).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for TextLineStr {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl Serialize for TextLineString {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a TextLineStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <&str>::deserialize(deserializer)?;
+        <&TextLineStr>::try_from(s).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for TextLineString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        TextLineString::try_from(s).map_err(de::Error::custom)
+    }
+}
+
+/// Test whether `c` is a bidirectional embedding, override, or isolate
+/// control.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Find the first bidirectional control in `s`, if any.
+fn first_bidi_control(s: &str) -> Option<Violation> {
+    let (mut line, mut column) = (1, 1);
+    for (offset, c) in s.char_indices() {
+        if is_bidi_control(c) {
+            return Some(Violation {
+                kind: ViolationKind::BidiControl,
+                codepoint: Some(c),
+                offset: offset as u64,
+                line,
+                column,
+                replacement: REPL.to_string(),
+            });
+        }
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    None
+}
+
+/// Check `s` against the Text rules, other than the rule requiring a
+/// trailing newline, and check that it contains no bidirectional controls.
+fn check(s: &str) -> Result<(), Violation> {
+    if is_plain_ascii(s.as_bytes()) {
+        return Ok(());
+    }
+    let text = match first_violation(s, TextProfile::default()) {
+        Some(violation) if violation.kind != ViolationKind::MissingFinalNewline => Some(violation),
+        _ => None,
+    };
+    match (text, first_bidi_control(s)) {
+        (Some(text), Some(bidi)) => Err(if bidi.offset < text.offset {
+            bidi
+        } else {
+            text
+        }),
+        (Some(violation), None) | (None, Some(violation)) => Err(violation),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Apply the `TextReader` rules to `s`, other than the rule which appends a
+/// trailing newline, and replace bidirectional controls.
+fn sanitize_str(s: &str) -> Cow<'_, str> {
+    let mut text = match to_text(s.as_bytes()) {
+        Cow::Owned(mut text) => {
+            if !s.ends_with('\n') && text.ends_with('\n') {
+                text.pop();
+            }
+            if text == s {
+                Cow::Borrowed(s)
+            } else {
+                Cow::Owned(text)
+            }
+        }
+        borrowed => borrowed,
+    };
+    // The controls and U+FFFD are all starters which never compose, so this
+    // doesn't affect normalization.
+    if text.contains(is_bidi_control) {
+        text = Cow::Owned(text.replace(is_bidi_control, &REPL.to_string()));
+    }
+    text
+}
+
+/// Serialize and deserialize strings, failing if they contain anything the
+/// Text format disallows, or a bidirectional control.
+///
+/// Use with `#[serde(with = "text_streams::serde::reject")]`.
+pub mod reject {
+    use super::*;
+
+    /// Serialize `value`, failing if it isn't valid Text.
+    pub fn serialize<T: AsRef<str> + ?Sized, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let s = value.as_ref();
+        check(s).map_err(ser::Error::custom)?;
+        serializer.serialize_str(s)
+    }
+
+    /// Deserialize a `String`, failing if it isn't valid Text.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        check(&s).map_err(de::Error::custom)?;
+        Ok(s)
+    }
+}
+
+/// Serialize and deserialize strings, replacing anything the Text format
+/// disallows, following the same rules as `TextReader`, and replacing
+/// bidirectional controls.
+///
+/// Use with `#[serde(with = "text_streams::serde::sanitize")]`.
+pub mod sanitize {
+    use super::*;
+
+    /// Serialize `value`, with anything which isn't valid Text replaced.
+    pub fn serialize<T: AsRef<str> + ?Sized, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&sanitize_str(value.as_ref()))
+    }
+
+    /// Deserialize a `String`, with anything which isn't valid Text
+    /// replaced.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(match sanitize_str(&s) {
+            Cow::Borrowed(_) => s,
+            Cow::Owned(text) => text,
+        })
+    }
+}
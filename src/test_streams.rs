@@ -0,0 +1,27 @@
+//! Streams for use in unit tests.
+
+use io_ext::{ReadExt, Status};
+use std::io;
+
+/// A stream which delivers one byte per read, and then reports that no
+/// more data is available yet, as a nonblocking stream would.
+pub(crate) struct Trickle(pub(crate) &'static [u8]);
+
+impl io::Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io_ext::default_read(self, buf)
+    }
+}
+
+impl ReadExt for Trickle {
+    fn read_with_status(&mut self, buf: &mut [u8]) -> io::Result<(usize, Status)> {
+        match self.0.split_first() {
+            Some((first, rest)) => {
+                buf[0] = *first;
+                self.0 = rest;
+                Ok((1, Status::active()))
+            }
+            None => Ok((0, Status::active())),
+        }
+    }
+}
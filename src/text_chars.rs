@@ -0,0 +1,69 @@
+//! Defines the `TextChars` extension trait for `char` iterators.
+
+use crate::{categorize::Categorize, replace_selected::ReplaceSelected, TextError, TextProfile};
+
+/// Extension methods for iterators over `char`s, exposing the individual
+/// stages `TextReader` and `TextWriter` apply.
+///
+/// These operate on codepoints only; they don't perform NFC or Stream-Safe
+/// normalization, newline handling, or escape-sequence stripping. Use
+/// `TextReader`, `TextWriter`, or [`to_text`](crate::to_text) for the
+/// complete transformations.
+pub trait TextChars: Iterator<Item = char> + Sized {
+    /// Replace codepoints which have replacement sequences, following the
+    /// rules `TextReader` applies to its NFC output. For example, a U+FEFF
+    /// (BOM) becomes U+2060 (WJ), and noncharacters become U+FFFD.
+    #[inline]
+    fn text_replacements(self) -> TextReplacements<Self> {
+        self.text_replacements_with_profile(TextProfile::default())
+    }
+
+    /// Like `text_replacements`, but uses the rules of `profile`.
+    #[inline]
+    fn text_replacements_with_profile(self, profile: TextProfile) -> TextReplacements<Self> {
+        TextReplacements(ReplaceSelected::new(self, profile))
+    }
+
+    /// Check each codepoint against the rules `TextWriter` applies to its
+    /// input, yielding an error for each one which isn't permitted.
+    #[inline]
+    fn text_categorize(self) -> TextCategorize<Self> {
+        self.text_categorize_with_profile(TextProfile::default())
+    }
+
+    /// Like `text_categorize`, but uses the rules of `profile`.
+    #[inline]
+    fn text_categorize_with_profile(self, profile: TextProfile) -> TextCategorize<Self> {
+        TextCategorize(Categorize::new(self, profile))
+    }
+}
+
+impl<Iter: Iterator<Item = char>> TextChars for Iter {}
+
+/// An iterator which replaces codepoints that have replacement sequences.
+///
+/// This is returned by [`TextChars::text_replacements`].
+pub struct TextReplacements<Iter: Iterator<Item = char>>(ReplaceSelected<Iter>);
+
+impl<Iter: Iterator<Item = char>> Iterator for TextReplacements<Iter> {
+    type Item = char;
+
+    #[inline]
+    fn next(&mut self) -> Option<char> {
+        self.0.next()
+    }
+}
+
+/// An iterator which checks codepoints against the rules for Text output.
+///
+/// This is returned by [`TextChars::text_categorize`].
+pub struct TextCategorize<Iter: Iterator<Item = char>>(Categorize<Iter>);
+
+impl<Iter: Iterator<Item = char>> Iterator for TextCategorize<Iter> {
+    type Item = Result<char, TextError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
@@ -0,0 +1,172 @@
+//! Defines `TextConcat`.
+
+use crate::{
+    read_buffer::ReadBuffer,
+    unicode::{needs_cgj, CGJ, MAX_UTF8_SIZE, NORMALIZATION_BUFFER_SIZE},
+    TextReader,
+};
+use io_ext::{
+    default_read, default_read_exact, default_read_to_end, default_read_to_string,
+    default_read_vectored, ReadExt, Status,
+};
+use std::{collections::VecDeque, io, str};
+
+/// A `ReadExt` implementation which reads from a sequence of `TextReader`s,
+/// one after another, producing a single valid plain text stream.
+///
+/// NFC isn't closed under concatenation, so wherever the beginning of one
+/// reader's output could combine or reorder with the end of the previous
+/// one's, a U+034F (COMBINING GRAPHEME JOINER) is inserted between them to
+/// keep the output in NFC and Stream-Safe.
+///
+/// Like `TextReader`, `TextConcat` implements `BufRead` using its own buffer.
+pub struct TextConcat<Inner: ReadExt> {
+    /// The readers which haven't reached their end yet.
+    readers: VecDeque<TextReader<Inner>>,
+
+    /// Output which has been read from `readers` but not yet returned.
+    read_buffer: ReadBuffer,
+
+    /// The last `char` read, if any.
+    last: Option<char>,
+
+    /// True if nothing has been read from the front reader yet.
+    at_boundary: bool,
+}
+
+impl<Inner: ReadExt> TextConcat<Inner> {
+    /// Construct a new instance of `TextConcat` reading from each of
+    /// `readers` in turn.
+    #[inline]
+    pub fn new<I: IntoIterator<Item = TextReader<Inner>>>(readers: I) -> Self {
+        Self {
+            readers: readers.into_iter().collect(),
+            read_buffer: ReadBuffer::new(),
+            last: None,
+            at_boundary: true,
+        }
+    }
+
+    /// Add `reader` to the end of the sequence of readers.
+    #[inline]
+    pub fn push(&mut self, reader: TextReader<Inner>) {
+        self.readers.push_back(reader);
+    }
+
+    /// Read from the front reader into `read_buffer`, moving on to the next
+    /// reader when it reaches its end.
+    fn fill(&mut self) -> io::Result<()> {
+        loop {
+            let reader = match self.readers.front_mut() {
+                Some(reader) => reader,
+                None => {
+                    self.read_buffer.fill(Vec::new(), 0, Status::End);
+                    return Ok(());
+                }
+            };
+
+            let mut buf = self.read_buffer.take_storage();
+            let (mut size, status) = match reader.read_with_status(&mut buf) {
+                Ok(size_and_status) => size_and_status,
+                Err(e) => {
+                    self.read_buffer.fill(buf, 0, Status::active());
+                    return Err(e);
+                }
+            };
+            let stalled = reader.inner.impl_.stalled();
+
+            if size != 0 {
+                // `TextReader` always produces valid UTF-8.
+                let s = str::from_utf8(&buf[..size]).unwrap();
+                let first = s.chars().next().unwrap();
+                let last = s.chars().next_back().unwrap();
+                if self.at_boundary && needs_cgj(self.last, first) {
+                    let mut cgj = [0_u8; MAX_UTF8_SIZE];
+                    let cgj = CGJ.encode_utf8(&mut cgj).as_bytes();
+                    buf.splice(..0, cgj.iter().copied());
+                    size += cgj.len();
+                }
+                self.last = Some(last);
+                self.at_boundary = false;
+            }
+
+            // Only report the end of the last reader.
+            let status = if status.is_end() {
+                self.readers.pop_front();
+                self.at_boundary = true;
+                if self.readers.is_empty() {
+                    Status::End
+                } else {
+                    Status::active()
+                }
+            } else {
+                status
+            };
+
+            self.read_buffer.fill(buf, size, status);
+            // Don't spin if the front reader has nothing more for now.
+            if size != 0 || status != Status::active() || stalled {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<Inner: ReadExt> ReadExt for TextConcat<Inner> {
+    fn read_with_status(&mut self, buf: &mut [u8]) -> io::Result<(usize, Status)> {
+        if buf.len() < NORMALIZATION_BUFFER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "buffer for text input must be at least NORMALIZATION_BUFFER_SIZE bytes",
+            ));
+        }
+
+        if self.read_buffer.is_empty() {
+            self.fill()?;
+        }
+
+        Ok(self.read_buffer.drain(buf))
+    }
+}
+
+impl<Inner: ReadExt> io::Read for TextConcat<Inner> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        default_read(self, buf)
+    }
+
+    #[inline]
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        default_read_vectored(self, bufs)
+    }
+
+    #[inline]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        default_read_to_end(self, buf)
+    }
+
+    #[inline]
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        default_read_to_string(self, buf)
+    }
+
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        default_read_exact(self, buf)
+    }
+}
+
+impl<Inner: ReadExt> io::BufRead for TextConcat<Inner> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.read_buffer.is_empty() {
+            self.fill()?;
+        }
+
+        Ok(self.read_buffer.data())
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.read_buffer.consume(amt)
+    }
+}
@@ -0,0 +1,34 @@
+//! Defines `TextError`.
+
+use crate::ViolationKind;
+use std::{error, fmt};
+
+/// A disallowed codepoint encountered in output which is required to be
+/// valid Text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextError {
+    /// What kind of codepoint this is.
+    pub kind: ViolationKind,
+
+    /// The offending codepoint.
+    pub codepoint: char,
+}
+
+impl TextError {
+    #[inline]
+    pub(crate) fn new(kind: ViolationKind, codepoint: char) -> Self {
+        Self { kind, codepoint }
+    }
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} U+{:04X} is not permitted in Text",
+            self.kind, self.codepoint as u32
+        )
+    }
+}
+
+impl error::Error for TextError {}
@@ -0,0 +1,58 @@
+//! Defines `TextProfile`.
+
+/// A version of the rules of the [Text] format.
+///
+/// The rules for what gets replaced on input and rejected on output evolve
+/// over time. Each version freezes a set of rules, so that content which was
+/// sanitized under one version can be reproduced exactly later.
+///
+/// [Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/text.md
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
+pub enum TextProfile {
+    /// The original rules. Tag Characters are always disallowed, and there
+    /// are no restrictions on unassigned codepoints.
+    Text2020,
+
+    /// Like `Text2020`, but permits emoji tag sequences, and disallows
+    /// codepoints which are unassigned in [`UNICODE_VERSION`].
+    ///
+    /// [`UNICODE_VERSION`]: crate::UNICODE_VERSION
+    Text2026,
+
+    /// The most recent version supported by this version of the crate. This
+    /// may change when the crate is upgraded; use a specific version when
+    /// the exact behavior needs to be reproducible.
+    TextLatest,
+}
+
+impl TextProfile {
+    /// Resolve `TextLatest` into the specific version it currently refers to.
+    #[inline]
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::TextLatest => Self::Text2026,
+            profile => profile,
+        }
+    }
+
+    /// Are well-formed emoji tag sequences permitted?
+    #[inline]
+    pub(crate) fn permits_emoji_tag_sequences(self) -> bool {
+        !matches!(self.resolve(), Self::Text2020)
+    }
+
+    /// Are codepoints which are unassigned in `UNICODE_VERSION` disallowed?
+    #[inline]
+    pub(crate) fn disallows_unassigned(self) -> bool {
+        !matches!(self.resolve(), Self::Text2020)
+    }
+}
+
+impl Default for TextProfile {
+    #[inline]
+    fn default() -> Self {
+        Self::TextLatest
+    }
+}
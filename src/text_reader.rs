@@ -1,10 +1,20 @@
-use crate::{text_reader_impl::TextReaderImpl, ReadStr, Utf8Reader};
+use crate::{
+    text_reader_impl::TextReaderImpl, InputProfile, OffsetMap, ReadStr, ReplacementCounts,
+    ReplacementObserver, TextProfile, TextReaderSnapshot, TextTransform, TransformStage,
+    Utf8Reader,
+};
 use io_ext::{ReadExt, Status};
 use std::{io, str};
 
 /// A `ReadExt` implementation which translates from an input `ReadExt`
 /// producing an arbitrary byte sequence into a valid plain text stream.
 ///
+/// `TextReader` also implements `BufRead`, encoding its output directly
+/// into its own buffer. The buffers returned by `fill_buf` always end at a
+/// point where normalization of subsequent input can't change them. If the
+/// underlying stream has no data available yet, `fill_buf` returns an empty
+/// buffer rather than waiting.
+///
 /// TODO: use `from_utf8_unchecked` and `as_mut_vec` to optimize this.
 pub struct TextReader<Inner: ReadExt> {
     /// The wrapped byte stream.
@@ -22,6 +32,283 @@ impl<Inner: ReadExt> TextReader<Inner> {
             impl_: TextReaderImpl::new(),
         }
     }
+
+    /// Like `new`, but passes codepoints which are unassigned in
+    /// [`UNICODE_VERSION`] through unmodified, rather than replacing them
+    /// with U+FFFD (REPLACEMENT CHARACTER).
+    ///
+    /// Note: This gives up the stability guarantees of the [Normalization
+    /// Process for Stabilized Strings]; if the codepoints are assigned in a
+    /// later version of Unicode, normalization of the output may change.
+    ///
+    /// [`UNICODE_VERSION`]: crate::UNICODE_VERSION
+    /// [Normalization Process for Stabilized Strings]: https://unicode.org/reports/tr15/#Normalization_Process_for_Stabilized_Strings
+    #[inline]
+    pub fn with_unassigned_preserved(inner: Inner) -> Self {
+        Self {
+            inner: Utf8Reader::new(inner),
+            impl_: TextReaderImpl::with_unassigned_preserved(),
+        }
+    }
+
+    /// Like `new`, but rather than replacing control codes and invalid UTF-8
+    /// with U+FFFD (REPLACEMENT CHARACTER) and removing escape sequences,
+    /// shows them in a visible form, for debugging tools and log viewers.
+    /// C0 control codes, including ESC and lone CRs, are shown as their
+    /// Control Pictures (U+2400–U+241F), DEL as U+2421 (SYMBOL FOR DELETE),
+    /// C1 control codes as escapes like `\u{85}`, and invalid UTF-8 bytes as
+    /// escapes like `\xff`. Backslashes are doubled, and Control Pictures in
+    /// the input are shown as escapes like `\u{2407}`, so that the output
+    /// can be told apart from text which looks like it. Newlines, CRLFs, and
+    /// tabs are handled as usual, and the output is still valid Text.
+    ///
+    /// The same mapping is available for strings with [`ControlPictures`].
+    ///
+    /// [`ControlPictures`]: crate::ControlPictures
+    #[inline]
+    pub fn with_control_pictures(inner: Inner) -> Self {
+        let mut inner = Utf8Reader::new(inner);
+        inner.impl_.record_invalid();
+        Self {
+            inner,
+            impl_: TextReaderImpl::with_control_pictures(),
+        }
+    }
+
+    /// Like `new`, but interprets the line-editing controls in raw terminal
+    /// input, producing the lines as edited rather than the keystrokes.
+    /// Backspace (U+0008) and DEL erase the character before the cursor,
+    /// Ctrl-W erases the word before the cursor, Ctrl-U erases everything
+    /// before the cursor, the left, right, Home, and End keys move the
+    /// cursor within the line, and the Delete key erases the character at
+    /// the cursor. CR, LF, and CRLF each end a line. Other escape sequences,
+    /// such as the up and down keys, are ignored, and other control codes are
+    /// replaced as usual.
+    ///
+    /// A line is only produced once it's ended, or at the end of the stream.
+    #[inline]
+    pub fn with_line_editing(inner: Inner) -> Self {
+        Self {
+            inner: Utf8Reader::new(inner),
+            impl_: TextReaderImpl::with_line_editing(),
+        }
+    }
+
+    /// Like `new`, but renders terminal output, such as logs captured with
+    /// `script`, CI console logs, and `nroff` output, as the final visible
+    /// text of each line. A CR returns to the start of the line, so that
+    /// following text overwrites it, as in progress bars, and a backspace
+    /// moves back one column, so that overstruck bold and underlined
+    /// characters are shown once. The CSI sequences for moving the cursor
+    /// within the line and erasing in line are applied too. Other escape
+    /// sequences, such as colors, and other control codes are removed.
+    ///
+    /// A line is only produced once it's ended, or at the end of the stream.
+    #[inline]
+    pub fn with_transcript(inner: Inner) -> Self {
+        Self {
+            inner: Utf8Reader::new(inner),
+            impl_: TextReaderImpl::with_transcript(),
+        }
+    }
+
+    /// Like `new`, but rather than repairing input which isn't valid Text,
+    /// fails with an [`io::ErrorKind::InvalidData`] error at the first
+    /// violation. Input before the violation is still returned as it
+    /// arrives, and the error wraps a [`Violation`] describing the kind of
+    /// violation and its offset in the input. Reads after the error fail
+    /// with the same error.
+    ///
+    /// Since valid input passes through unchanged, replacement observers,
+    /// transforms, and `input_profile` don't apply in this mode.
+    ///
+    /// [`io::ErrorKind::InvalidData`]: std::io::ErrorKind::InvalidData
+    /// [`Violation`]: crate::Violation
+    #[inline]
+    pub fn with_strict_validation(inner: Inner) -> Self {
+        let mut inner = Utf8Reader::new(inner);
+        inner.impl_.record_invalid();
+        Self {
+            inner,
+            impl_: TextReaderImpl::with_strict_validation(),
+        }
+    }
+
+    /// Construct a new instance of `TextReader` which resumes reading from
+    /// the state recorded in `snapshot`. `inner` must produce the same
+    /// input as the original reader's underlying stream, starting from
+    /// [`TextReaderSnapshot::offset`]. The output continues exactly where
+    /// the output returned by the original reader left off.
+    ///
+    /// The new reader uses the same profile and options as the original
+    /// reader, and records snapshots itself, with offsets continuing from the
+    /// original reader's.
+    ///
+    /// [`TextReaderSnapshot::offset`]: crate::TextReaderSnapshot::offset
+    #[inline]
+    pub fn resume(inner: Inner, snapshot: &TextReaderSnapshot) -> Self {
+        let mut inner = Utf8Reader::new(inner);
+        inner.impl_.record_invalid();
+        Self {
+            inner,
+            impl_: TextReaderImpl::resume(snapshot),
+        }
+    }
+
+    /// Like `new`, but applies the rules of the given version of the Text
+    /// format, rather than [`TextProfile::TextLatest`].
+    #[inline]
+    pub fn with_profile(inner: Inner, profile: TextProfile) -> Self {
+        Self {
+            inner: Utf8Reader::new(inner),
+            impl_: TextReaderImpl::with_profile(profile),
+        }
+    }
+
+    /// Return a summary of what has been seen in the input so far, such as
+    /// whether it began with a BOM and what style of line endings it uses.
+    /// This is complete once the end of the stream has been reached.
+    #[inline]
+    pub fn input_profile(&self) -> InputProfile {
+        self.impl_.input_profile(self.inner.impl_.saw_invalid())
+    }
+
+    /// Set an observer to be called for each replacement made in the input,
+    /// such as invalid UTF-8, control codes, or noncharacters being replaced
+    /// by U+FFFD (REPLACEMENT CHARACTER), and escape sequences being removed.
+    /// Each is reported with what it was actually replaced with, such as
+    /// its visible form in [`with_control_pictures`] mode. For plain input,
+    /// this reports the same things, with the same offsets, as
+    /// [`validate`] does, in order of offset.
+    ///
+    /// In [`with_line_editing`] and [`with_transcript`] modes, the controls
+    /// which are interpreted aren't reported, but the escape sequences and
+    /// control codes which are discarded are. When controls are interpreted
+    /// or there are `BeforeNormalization` transforms, invalid UTF-8 and
+    /// what the terminal modes discard are reported with their offsets in
+    /// the input as it's read, while replacements made by the Text rules
+    /// after them are reported with their offsets in the text the Text rules
+    /// see, and may be reported out of order with the others. User transforms
+    /// themselves aren't observed.
+    ///
+    /// The observer should be set before reading begins.
+    ///
+    /// [`validate`]: crate::validate
+    /// [`with_control_pictures`]: Self::with_control_pictures
+    /// [`with_line_editing`]: Self::with_line_editing
+    /// [`with_transcript`]: Self::with_transcript
+    #[inline]
+    pub fn set_replacement_observer<O: ReplacementObserver + Send + 'static>(
+        &mut self,
+        observer: O,
+    ) {
+        self.inner.impl_.record_invalid();
+        self.impl_.set_replacement_observer(Box::new(observer));
+    }
+
+    /// Return the number of replacements of each kind made while an
+    /// observer was set.
+    #[inline]
+    pub fn replacement_counts(&self) -> ReplacementCounts {
+        self.impl_.replacement_counts()
+    }
+
+    /// Enable or disable follow mode, for reading a file which is still
+    /// being written, as `tail -f` does.
+    ///
+    /// In follow mode, reaching the end of the underlying stream isn't
+    /// final: an incomplete UTF-8 sequence, CR, or escape sequence at the end
+    /// is held rather than replaced, no final newline is appended, and reads
+    /// return 0 bytes with an active status, after which the caller can wait
+    /// and read again to poll for new data. The underlying stream must be
+    /// able to produce more data after reporting its end, as a `File` does.
+    /// Note that `io::Read::read` returning `Ok(0)` doesn't mean the end of
+    /// the stream in this mode.
+    ///
+    /// Disabling follow mode closes the stream: the next time the end of
+    /// the underlying stream is reached, the end-of-stream rules are applied.
+    #[inline]
+    pub fn set_follow(&mut self, follow: bool) {
+        self.inner.impl_.set_follow(follow)
+    }
+
+    /// Add a user-defined transform at the given stage of the pipeline.
+    /// Transforms at the same stage are applied in the order they're added.
+    ///
+    /// Transforms should be added before reading begins.
+    #[inline]
+    pub fn add_transform<T: TextTransform + Send + 'static>(
+        &mut self,
+        stage: TransformStage,
+        transform: T,
+    ) {
+        self.impl_.add_transform(stage, Box::new(transform))
+    }
+
+    /// Start recording an [`OffsetMap`] relating byte offsets in the output
+    /// to byte offsets in the input, so that positions in the normalized
+    /// text, such as those of search matches or diagnostics, can be traced
+    /// back to the original bytes.
+    ///
+    /// Input offsets are those of the underlying stream, unless there are
+    /// `BeforeNormalization` transforms, in which case they're offsets in
+    /// the transforms' output. Output offsets are those of the output before
+    /// any `AfterNormalization` transforms. The map may extend slightly past
+    /// the output returned so far.
+    ///
+    /// This should be called before reading begins. The output is the same
+    /// as without it.
+    #[inline]
+    pub fn record_offset_map(&mut self) {
+        self.inner.impl_.record_invalid();
+        self.impl_.record_offset_map();
+    }
+
+    /// Return the `OffsetMap` recorded so far.
+    #[inline]
+    pub fn offset_map(&self) -> &OffsetMap {
+        self.impl_.offset_map()
+    }
+
+    /// Start recording the state needed for [`snapshot`].
+    ///
+    /// This should be called before reading begins.
+    ///
+    /// [`snapshot`]: Self::snapshot
+    #[inline]
+    pub fn record_snapshots(&mut self) {
+        self.inner.impl_.record_invalid();
+        self.impl_.record_snapshots();
+    }
+
+    /// Take a [`TextReaderSnapshot`] of the state of this reader, from which
+    /// reading can be resumed with [`resume`], for example after a process
+    /// restart when tailing a large file. Output returned by `fill_buf` and
+    /// not yet consumed is produced again by the resumed reader.
+    ///
+    /// This fails if `record_snapshots` hasn't been called, or if the reader
+    /// has any transforms added with [`add_transform`], is in
+    /// [`with_line_editing`] or [`with_transcript`] mode, or was created
+    /// with [`with_strict_validation`], since their state can't be recorded.
+    /// Replacement observers and offset maps aren't part of the snapshot.
+    ///
+    /// [`resume`]: Self::resume
+    /// [`add_transform`]: Self::add_transform
+    /// [`with_line_editing`]: Self::with_line_editing
+    /// [`with_transcript`]: Self::with_transcript
+    /// [`with_strict_validation`]: Self::with_strict_validation
+    #[inline]
+    pub fn snapshot(&self) -> io::Result<TextReaderSnapshot> {
+        self.impl_.snapshot()
+    }
+
+    /// Return the `OffsetMap` recorded so far, and start a new one which
+    /// continues from the same offsets. This can be used to get the spans
+    /// for each read.
+    #[inline]
+    pub fn take_offset_map(&mut self) -> OffsetMap {
+        self.impl_.take_offset_map()
+    }
 }
 
 impl<Inner: ReadExt> ReadExt for TextReader<Inner> {
@@ -76,6 +363,18 @@ impl<Inner: ReadExt> io::Read for TextReader<Inner> {
     }
 }
 
+impl<Inner: ReadExt> io::BufRead for TextReader<Inner> {
+    #[inline]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        TextReaderImpl::fill_buf(self)
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        TextReaderImpl::consume(self, amt)
+    }
+}
+
 #[cfg(test)]
 fn translate_via_std_reader(bytes: &[u8]) -> String {
     use std::io::Read;
@@ -109,11 +408,29 @@ fn translate_with_small_buffer(bytes: &[u8]) -> String {
     String::from_utf8(v).unwrap()
 }
 
+#[cfg(test)]
+fn translate_via_fill_buf(bytes: &[u8]) -> String {
+    use std::io::BufRead;
+    let mut reader = TextReader::new(io_ext::SliceReader::new(bytes));
+    let mut s = String::new();
+    loop {
+        let buf = reader.fill_buf().unwrap();
+        if buf.is_empty() {
+            break;
+        }
+        s.push_str(str::from_utf8(buf).unwrap());
+        let len = buf.len();
+        reader.consume(len);
+    }
+    s
+}
+
 #[cfg(test)]
 fn test(bytes: &[u8], s: &str) {
     assert_eq!(translate_via_std_reader(bytes), s);
     assert_eq!(translate_via_slice_reader(bytes), s);
     assert_eq!(translate_with_small_buffer(bytes), s);
+    assert_eq!(translate_via_fill_buf(bytes), s);
 }
 
 #[test]
@@ -127,6 +444,48 @@ fn test_nl() {
     test(b"\nhello\nworld\n", "\nhello\nworld\n");
 }
 
+#[test]
+fn test_buf_read() {
+    use std::io::{BufRead, Read};
+    let mut reader = TextReader::new(io_ext::SliceReader::new(b"hello\r\nA\xcc\x8a\x07\nlast"));
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "hello\n");
+
+    let lines = reader.lines().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(lines, ["\u{c5}\u{fffd}", "last"]);
+
+    // Mix buffered and unbuffered reads.
+    let mut reader = TextReader::new(io_ext::SliceReader::new(b"\xef\xbb\xbfhello\n"));
+    assert_eq!(reader.fill_buf().unwrap(), b"hello\n");
+    reader.consume(2);
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "llo\n");
+}
+
+#[test]
+fn test_buf_read_no_data_yet() {
+    use crate::test_streams::Trickle;
+    use std::io::BufRead;
+    let mut reader = TextReader::new(Trickle(b"\x1b[1mhi\r\n"));
+    let mut output = Vec::new();
+    loop {
+        let buf = reader.fill_buf().unwrap();
+        if buf.is_empty() {
+            break;
+        }
+        output.extend_from_slice(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    assert_eq!(output, b"hi\n");
+
+    // With no data available, `fill_buf` returns an empty buffer rather
+    // than waiting.
+    assert!(reader.fill_buf().unwrap().is_empty());
+}
+
 #[test]
 fn test_bom() {
     test("\u{feff}".as_bytes(), "");
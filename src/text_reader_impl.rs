@@ -1,23 +1,36 @@
 use crate::{
-    rc_char_queue::{RcCharQueue, RcCharQueueIter},
+    control_pictures::{is_control_picture, write_invalid, write_visible},
+    input_profile::{InputProfile, LineEndings},
+    line_editor::LineEditor,
+    offset_map::{OffsetMap, OffsetSpan},
+    read_buffer::{ReadBuffer, READ_BUFFER_SIZE},
     replace_selected::ReplaceSelected,
+    replacement_observer::{ReplacementCounts, ReplacementObserver, TerminalEvent, TextObserving},
+    text_profile::TextProfile,
+    text_reader_snapshot::TextReaderSnapshot,
+    text_transform::{TextTransform, TransformStage, Transforms},
+    transcript::Transcript,
     unicode::{
-        is_normalization_form_starter, BOM, CGJ, DEL, ESC, FF, MAX_UTF8_SIZE,
+        disallowed, is_assigned, is_normalization_form_starter, is_segment_start,
+        is_tag_spec_character, BOM, CANCEL_TAG, CGJ, DEL, ESC, FF, MAX_UTF8_SIZE,
         NORMALIZATION_BUFFER_LEN, NORMALIZATION_BUFFER_SIZE, REPL,
     },
+    utf8_reader_impl::{is_retryable, Utf8ReaderImpl},
+    validate::{Validator, Violation, ViolationKind},
     ReadStr, TextReader, TextReaderWriter, Utf8Reader, Utf8ReaderWriter,
 };
 use io_ext::{
     default_read, default_read_exact, default_read_to_end, default_read_to_string,
     default_read_vectored, ReadExt, ReadWriteExt, Status,
 };
-use std::{io, mem, str};
-use unicode_normalization::{Recompositions, Replacements, StreamSafe, UnicodeNormalization};
+use std::{collections::VecDeque, io, mem, ops::Range, str};
+use unicode_normalization::UnicodeNormalization;
 
 pub(crate) trait TextReaderInternals<Inner: ReadExt>: ReadExt {
     type Utf8Inner: ReadStr;
     fn impl_(&mut self) -> &mut TextReaderImpl;
     fn inner(&mut self) -> &mut Self::Utf8Inner;
+    fn utf8_impl(&mut self) -> &mut Utf8ReaderImpl;
 }
 
 impl<Inner: ReadExt> TextReaderInternals<Inner> for TextReader<Inner> {
@@ -30,6 +43,10 @@ impl<Inner: ReadExt> TextReaderInternals<Inner> for TextReader<Inner> {
     fn inner(&mut self) -> &mut Self::Utf8Inner {
         &mut self.inner
     }
+
+    fn utf8_impl(&mut self) -> &mut Utf8ReaderImpl {
+        &mut self.inner.impl_
+    }
 }
 
 impl<Inner: ReadWriteExt> TextReaderInternals<Inner> for TextReaderWriter<Inner> {
@@ -42,23 +59,28 @@ impl<Inner: ReadWriteExt> TextReaderInternals<Inner> for TextReaderWriter<Inner>
     fn inner(&mut self) -> &mut Self::Utf8Inner {
         &mut self.inner
     }
+
+    fn utf8_impl(&mut self) -> &mut Utf8ReaderImpl {
+        &mut self.inner.reader_impl
+    }
 }
 
 pub(crate) struct TextReaderImpl {
     /// Temporary storage for reading scalar values from the underlying stream.
     raw_string: String,
 
-    /// A queue of scalar values which have been translated but not written to
-    /// the output yet.
-    /// TODO: This is awkward; what we really want here is a streaming stream-safe
-    /// and NFC translator.
-    queue: RcCharQueue,
+    /// A queue of scalar values which have been translated but not
+    /// normalized yet. It's normalized in batches which end where
+    /// normalization can't combine anything with what precedes, so that the
+    /// normalizer doesn't need to hold on to it between reads.
+    queue: VecDeque<char>,
 
-    /// An iterator over the chars in `self.queue`.
-    queue_iter: Option<ReplaceSelected<Recompositions<StreamSafe<Replacements<RcCharQueueIter>>>>>,
+    /// Normalized output which hasn't been returned yet.
+    normalized: VecDeque<char>,
 
-    /// The number of '\n' and '\u34f`'s in the queue.
-    queued_nfc_resets: usize,
+    /// When the queue starts with a run too long to hold, the length up to
+    /// which it's been checked for a place to split it.
+    split_checked: usize,
 
     /// When we can't fit all the data from an underlying read in our buffer,
     /// we buffer it up. Remember the status value so we can replay that too.
@@ -73,22 +95,282 @@ pub(crate) struct TextReaderImpl {
 
     /// Control-code and escape-sequence state machine.
     state: State,
+
+    /// Output produced by `fill_buf` which hasn't been consumed yet.
+    read_buffer: ReadBuffer,
+
+    /// When enabled, unassigned codepoints are passed through rather than
+    /// being replaced.
+    preserve_unassigned: bool,
+
+    /// The version of the rules to apply.
+    profile: TextProfile,
+
+    /// What has been seen in the input, for `input_profile`.
+    observed: Observed,
+
+    /// The replacement observer, if one is set.
+    observing: Option<Box<TextObserving>>,
+
+    /// User transforms applied to the decoded input.
+    before: Transforms,
+
+    /// User transforms applied to the normalized output, if any have been
+    /// added.
+    after: Option<Box<Renormalize>>,
+
+    /// State for recording an `OffsetMap`, if enabled.
+    mapping: Option<Box<Mapping>>,
+
+    /// When enabled, control codes and invalid UTF-8 are shown in a visible
+    /// form rather than being replaced with U+FFFD.
+    control_pictures: bool,
+
+    /// The positions in `raw_string` of replaced invalid UTF-8 sequences,
+    /// and their original bytes, when needed by `mapping`, `observing`, or
+    /// `control_pictures`.
+    invalid: VecDeque<(usize, Vec<u8>)>,
+
+    /// State for strict mode, if enabled.
+    strict: Option<Box<Strict>>,
+
+    /// Interprets terminal controls in the input, if enabled.
+    terminal: Option<Box<Terminal>>,
+
+    /// State for taking snapshots, if enabled.
+    snapshots: Option<Box<Snapshots>>,
 }
 
 impl TextReaderImpl {
     /// Construct a new instance of `TextReaderImpl`.
     #[inline]
     pub fn new() -> Self {
-        let queue = RcCharQueue::new();
         Self {
             raw_string: String::new(),
-            queue,
-            queue_iter: None,
-            queued_nfc_resets: 0,
+            queue: VecDeque::new(),
+            normalized: VecDeque::new(),
+            split_checked: 0,
             pending_status: Status::active(),
             expect_starter: true,
             at_start: true,
             state: State::Ground(true),
+            read_buffer: ReadBuffer::new(),
+            preserve_unassigned: false,
+            profile: TextProfile::default(),
+            observed: Observed::default(),
+            observing: None,
+            before: Transforms::new(),
+            after: None,
+            mapping: None,
+            control_pictures: false,
+            invalid: VecDeque::new(),
+            strict: None,
+            terminal: None,
+            snapshots: None,
+        }
+    }
+
+    /// Like `new`, but applies the rules of the given version of the Text
+    /// format.
+    #[inline]
+    pub(crate) fn with_profile(profile: TextProfile) -> Self {
+        let mut impl_ = Self::new();
+        impl_.profile = profile;
+        impl_
+    }
+
+    /// Like `new`, but passes unassigned codepoints through rather than
+    /// replacing them with U+FFFD.
+    #[inline]
+    pub(crate) fn with_unassigned_preserved() -> Self {
+        let mut impl_ = Self::new();
+        impl_.preserve_unassigned = true;
+        impl_
+    }
+
+    /// Like `new`, but shows control codes in a visible form. The caller
+    /// must also enable `record_invalid` on the underlying `Utf8ReaderImpl`.
+    #[inline]
+    pub(crate) fn with_control_pictures() -> Self {
+        let mut impl_ = Self::new();
+        impl_.control_pictures = true;
+        impl_
+    }
+
+    /// Like `new`, but rather than repairing the input, reports the first
+    /// violation of the Text rules as an error. The caller must also enable
+    /// `record_invalid` on the underlying `Utf8ReaderImpl`.
+    #[inline]
+    pub(crate) fn with_strict_validation() -> Self {
+        let mut impl_ = Self::new();
+        impl_.strict = Some(Box::new(Strict::new(impl_.profile)));
+        impl_
+    }
+
+    /// Like `new`, but applies terminal line-editing controls to the input
+    /// before the Text rules.
+    #[inline]
+    pub(crate) fn with_line_editing() -> Self {
+        let mut impl_ = Self::new();
+        impl_.terminal = Some(Box::new(Terminal::LineEditing(LineEditor::new())));
+        impl_
+    }
+
+    /// Like `new`, but renders terminal output the way a terminal would
+    /// display it before the Text rules.
+    #[inline]
+    pub(crate) fn with_transcript() -> Self {
+        let mut impl_ = Self::new();
+        impl_.terminal = Some(Box::new(Terminal::Transcript(Transcript::new())));
+        impl_
+    }
+
+    /// Like `new`, but resumes reading from the state recorded in
+    /// `snapshot`, and records snapshots. The caller must also enable
+    /// `record_invalid` on the underlying `Utf8ReaderImpl`.
+    pub(crate) fn resume(snapshot: &TextReaderSnapshot) -> Self {
+        let mut impl_ = Self::with_profile(snapshot.profile);
+        impl_.preserve_unassigned = snapshot.preserve_unassigned;
+        impl_.control_pictures = snapshot.control_pictures;
+        if !snapshot.at_start {
+            // The snapshot is at the start of a line, as in `translate_chunk`.
+            impl_.at_start = false;
+            impl_.expect_starter = false;
+        }
+        impl_.snapshots = Some(Box::new(Snapshots::new(
+            snapshot.offset,
+            snapshot.at_start,
+            snapshot.skip,
+        )));
+        impl_
+    }
+
+    /// Test whether the positions of invalid UTF-8 sequences are needed.
+    fn records_invalid(&self) -> bool {
+        self.observing.is_some()
+            || self.mapping.is_some()
+            || self.control_pictures
+            || self.snapshots.is_some()
+    }
+
+    /// Summarize what has been seen in the input so far. `saw_invalid_utf8`
+    /// indicates whether the underlying `Utf8Reader` replaced anything.
+    pub(crate) fn input_profile(&self, saw_invalid_utf8: bool) -> InputProfile {
+        let observed = &self.observed;
+        let replacements = observed.replacements;
+        InputProfile {
+            bom: observed.bom,
+            line_endings: LineEndings::from_counts(observed.lf, observed.crlf, observed.lone_cr),
+            escape_sequences: observed.escape_sequences,
+            replacements: saw_invalid_utf8 || replacements != 0,
+        }
+    }
+
+    /// Set the observer to report replacements to. The caller must also
+    /// enable `record_invalid` on the underlying `Utf8ReaderImpl`.
+    pub(crate) fn set_replacement_observer(
+        &mut self,
+        observer: Box<dyn ReplacementObserver + Send>,
+    ) {
+        self.observing = Some(Box::new(TextObserving::new(observer, self.profile)));
+    }
+
+    /// The counts of replacements made while an observer was set.
+    #[inline]
+    pub(crate) fn replacement_counts(&self) -> ReplacementCounts {
+        self.observing
+            .as_ref()
+            .map(|observing| observing.counts().clone())
+            .unwrap_or_default()
+    }
+
+    /// Start recording an `OffsetMap`. The caller must also enable
+    /// `record_invalid` on the underlying `Utf8ReaderImpl`.
+    pub(crate) fn record_offset_map(&mut self) {
+        if self.mapping.is_none() {
+            self.mapping = Some(Box::new(Mapping::default()));
+        }
+    }
+
+    /// The `OffsetMap` recorded so far.
+    pub(crate) fn offset_map(&self) -> &OffsetMap {
+        static EMPTY: OffsetMap = OffsetMap::new();
+        self.mapping.as_ref().map_or(&EMPTY, |mapping| &mapping.map)
+    }
+
+    /// Take the `OffsetMap` recorded so far, leaving an empty one.
+    pub(crate) fn take_offset_map(&mut self) -> OffsetMap {
+        self.mapping
+            .as_mut()
+            .map(|mapping| mem::take(&mut mapping.map))
+            .unwrap_or_default()
+    }
+
+    /// Start recording the state needed for `snapshot`. The caller must also
+    /// enable `record_invalid` on the underlying `Utf8ReaderImpl`.
+    pub(crate) fn record_snapshots(&mut self) {
+        if self.snapshots.is_none() {
+            self.snapshots = Some(Box::new(Snapshots::new(0, true, 0)));
+        }
+    }
+
+    /// Take a snapshot of the state of the reader, from which reading can be
+    /// resumed with `resume`.
+    pub(crate) fn snapshot(&self) -> io::Result<TextReaderSnapshot> {
+        let snapshots = self.snapshots.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "snapshots are not being recorded",
+            )
+        })?;
+        if !self.before.is_empty()
+            || self.after.is_some()
+            || self.terminal.is_some()
+            || self.strict.is_some()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "snapshots aren't supported with transforms, terminal modes, or strict validation",
+            ));
+        }
+
+        // Output in `read_buffer` hasn't been returned yet, so it must be
+        // produced again when resuming, while output still to be skipped was
+        // returned before this reader was resumed.
+        let returned =
+            snapshots.output_offset + snapshots.skip - self.read_buffer.data().len() as u64;
+        let line = snapshots
+            .lines
+            .iter()
+            .rev()
+            .find(|line| line.output <= returned)
+            .unwrap();
+        Ok(TextReaderSnapshot {
+            offset: line.input,
+            skip: returned - line.output,
+            at_start: line.at_start,
+            profile: self.profile,
+            preserve_unassigned: self.preserve_unassigned,
+            control_pictures: self.control_pictures,
+        })
+    }
+
+    /// Add a user transform at the given stage of the pipeline.
+    pub(crate) fn add_transform(
+        &mut self,
+        stage: TransformStage,
+        transform: Box<dyn TextTransform + Send>,
+    ) {
+        match stage {
+            TransformStage::BeforeNormalization => self.before.push(transform),
+            TransformStage::AfterNormalization => {
+                let profile = self.profile;
+                let preserve_unassigned = self.preserve_unassigned;
+                self.after
+                    .get_or_insert_with(|| Box::new(Renormalize::new(profile, preserve_unassigned)))
+                    .transforms
+                    .push(transform)
+            }
         }
     }
 
@@ -112,95 +394,317 @@ impl TextReaderImpl {
         unsafe { Self::read_exact(internals, buf.as_bytes_mut()) }
     }
 
+    /// Test whether the queue is normalized one segment at a time, so that
+    /// each segment's output can be attributed to its input.
+    fn segmented(&self) -> bool {
+        self.mapping.is_some() || self.observing.is_some()
+    }
+
     fn queue_next(&mut self, sequence_end: bool) -> Option<char> {
-        if !sequence_end
-            && self.queued_nfc_resets == 0
-            && self.queue.len() < NORMALIZATION_BUFFER_LEN
-        {
-            return None;
+        loop {
+            let c = self.normalized_queue_next(sequence_end)?;
+            if let Some(snapshots) = &mut self.snapshots {
+                if !snapshots.output(c) {
+                    continue;
+                }
+            }
+            return Some(c);
         }
-        if self.queue_iter.is_none() {
-            if self.queue.is_empty() {
+    }
+
+    /// Return the next normalized `char` from the queue, if one is ready.
+    fn normalized_queue_next(&mut self, sequence_end: bool) -> Option<char> {
+        loop {
+            if let Some(c) = self.normalized.pop_front() {
+                return Some(c);
+            }
+            let ready = if self.segmented() {
+                self.normalize_segment(sequence_end)
+            } else {
+                self.normalize_batch(sequence_end)
+            };
+            if !ready {
                 return None;
             }
-            self.queue_iter = Some(ReplaceSelected::new(
-                self.queue.iter().svar().stream_safe().nfc(),
-            ));
         }
-        if let Some(c) = self.queue_iter.as_mut().unwrap().next() {
-            if c == '\n' || c == CGJ {
-                self.queued_nfc_resets -= 1;
+    }
+
+    /// Normalize as much of the queue as can be without knowing what
+    /// follows it. Return false if none of it can be.
+    fn normalize_batch(&mut self, sequence_end: bool) -> bool {
+        let after_reset = self
+            .queue
+            .iter()
+            .rposition(|c| *c == '\n' || *c == CGJ)
+            .map_or(0, |index| index + 1);
+        // Hold short runs until the end of a line, so that lines are
+        // produced whole where possible.
+        let (len, cgj) = if sequence_end {
+            (self.queue.len(), false)
+        } else if self.queue.len() < NORMALIZATION_BUFFER_LEN {
+            (after_reset, false)
+        } else {
+            let last_start = self
+                .queue
+                .iter()
+                .skip(1)
+                .rposition(|c| is_segment_start(*c));
+            match last_start {
+                Some(index) => (after_reset.max(index + 1), false),
+                None if after_reset != 0 => (after_reset, false),
+                None => self.next_segment(false),
             }
-            return Some(c);
+        };
+        if len == 0 {
+            return false;
+        }
+        self.split_checked = 0;
+
+        let mut normalized = ReplaceSelected::new(
+            self.queue.drain(..len).svar().stream_safe().nfc(),
+            self.profile,
+        );
+        self.normalized.extend(&mut normalized);
+        self.observed.replacements += normalized.replacements();
+        if cgj {
+            self.normalized.push_back(CGJ);
+        }
+        true
+    }
+
+    /// Normalize the next segment of the queue, which ends before a
+    /// `char` which normalization can't combine with anything before it,
+    /// and record its span and report its replacements. Return false if
+    /// there's no complete segment yet.
+    fn normalize_segment(&mut self, sequence_end: bool) -> bool {
+        let (len, cgj) = self.next_segment(sequence_end);
+        if len == 0 {
+            return false;
+        }
+        self.split_checked = 0;
+
+        let segment = self.queue.drain(..len).collect::<Vec<_>>();
+
+        let mut nfc = normalize(&segment);
+        // If a long run was split where the normalizer would insert a CGJ,
+        // the CGJ goes at the end of the first part.
+        if cgj {
+            nfc.push(CGJ);
+        }
+        let mut normalized = ReplaceSelected::new(nfc.into_iter(), self.profile);
+        let mut output = String::new();
+        output.extend(&mut normalized);
+        self.observed.replacements += normalized.replacements();
+
+        if let Some(mapping) = &mut self.mapping {
+            let mut end = mapping.segment_start;
+            let mut verbatim = true;
+            for (source_end, source_verbatim) in mapping.sources.drain(..len) {
+                end = source_end;
+                verbatim &= source_verbatim;
+            }
+            let input = mapping.segment_start..end;
+            let segment_len = segment.iter().map(|c| c.len_utf8()).sum::<usize>();
+            let exact = verbatim
+                && output.chars().eq(segment.iter().copied())
+                && input.end - input.start == segment_len as u64;
+            mapping.push(output.len() as u64, input, exact);
+        }
+        self.normalized.extend(output.chars());
+        self.flush_events();
+        true
+    }
+
+    /// Return the length of the first segment in the queue, or 0 if it may
+    /// not be complete yet, and whether normalization inserts a CGJ after
+    /// it.
+    fn next_segment(&mut self, sequence_end: bool) -> (usize, bool) {
+        let (end, complete) = match self.queue.iter().skip(1).position(|c| is_segment_start(*c)) {
+            Some(index) => (index + 1, true),
+            None => (self.queue.len(), sequence_end),
+        };
+        // Split runs too long to hold at the first place possible, so that
+        // where they're split doesn't depend on how much of them was queued
+        // at once.
+        let split = if end >= NORMALIZATION_BUFFER_LEN {
+            let chars = self.queue.iter().take(end).copied().collect::<Vec<_>>();
+            let start = self.split_checked.max(NORMALIZATION_BUFFER_LEN);
+            self.split_checked = end + 1;
+            (start..=end).find_map(|len| split_overlong(&chars[..len]))
+        } else {
+            None
+        };
+        match split {
+            Some(split) => split,
+            None if complete => (end, false),
+            None => (0, false),
+        }
+    }
+
+    /// Report the replacements which can't be preceded by any not made yet.
+    fn flush_events(&mut self) {
+        if let Some(observing) = &mut self.observing {
+            let limit = match self.state {
+                State::Ground(_) => None,
+                _ => Some(observing.state_start_offset()),
+            };
+            observing.flush(limit);
         }
-        self.queue_iter = None;
-        None
+    }
+
+    /// Test whether there's output from the queue which hasn't been
+    /// returned yet.
+    fn output_pending(&self) -> bool {
+        !self.normalized.is_empty() || !self.queue.is_empty()
     }
 
     fn process_raw_string(&mut self) {
-        for c in self.raw_string.chars() {
+        let raw_string = mem::take(&mut self.raw_string);
+        for (index, c) in raw_string.char_indices() {
+            let invalid = match self.invalid.front() {
+                Some((at, _)) if *at == index => self.invalid.pop_front().map(|(_, bytes)| bytes),
+                _ => None,
+            };
+            let (start, end) = match &mut self.mapping {
+                Some(mapping) => mapping.advance(c, invalid.as_ref().map(Vec::len)),
+                None => (0, 0),
+            };
+            if let Some(snapshots) = &mut self.snapshots {
+                snapshots.input_offset += invalid.as_ref().map_or(c.len_utf8(), Vec::len) as u64;
+            }
+            if let Some(observing) = &mut self.observing {
+                observing.advance(c, invalid.as_ref().map_or(c.len_utf8(), Vec::len));
+                if !self.control_pictures {
+                    if let Some(invalid) = &invalid {
+                        observing.replaced_invalid(invalid.clone(), "\u{fffd}");
+                    }
+                }
+            }
             let at_start = mem::replace(&mut self.at_start, false);
             loop {
                 match (self.state, c) {
-                    (State::Ground(_), BOM) if at_start => (),
+                    (State::Ground(_), _) if self.control_pictures && invalid.is_some() => {
+                        self.observed.replacements += 1;
+                        let mut visible = String::new();
+                        write_invalid(invalid.as_ref().unwrap(), &mut visible).unwrap();
+                        if let Some(observing) = &mut self.observing {
+                            observing.replaced_invalid(invalid.clone().unwrap(), &visible);
+                        }
+                        for v in visible.chars() {
+                            self.push(v, end);
+                        }
+                        self.expect_starter = false;
+                        self.state = State::Ground(false)
+                    }
+                    (State::Ground(_), '\\') if self.control_pictures => {
+                        // Double backslashes so that they can't be mistaken
+                        // for the start of an escape.
+                        self.push('\\', end);
+                        self.push('\\', end);
+                        self.expect_starter = false;
+                        self.state = State::Ground(false)
+                    }
+                    (State::Ground(_), c) if self.control_pictures && is_control_picture(c) => {
+                        // Escape Control Pictures so that they can't be
+                        // mistaken for the control codes they depict.
+                        let mut visible = String::new();
+                        write_visible(c, &mut visible).unwrap();
+                        for v in visible.chars() {
+                            self.push(v, end);
+                        }
+                        self.expect_starter = false;
+                        self.state = State::Ground(false)
+                    }
+                    (State::Ground(_), BOM) if at_start => {
+                        self.observed.bom = true;
+                        self.replaced(ViolationKind::ByteOrderMark, c, "");
+                    }
                     (State::Ground(_), '\n') => {
-                        self.queue.push('\n');
-                        self.queued_nfc_resets += 1;
+                        self.observed.lf += 1;
+                        self.push_line_end(end);
                         self.expect_starter = false;
                         self.state = State::Ground(true)
                     }
                     (State::Ground(_), '\t') => {
-                        self.queue.push('\t');
+                        self.push('\t', end);
                         self.expect_starter = false;
                         self.state = State::Ground(false)
                     }
-                    (State::Ground(_), FF) => {
-                        self.queue.push(' ');
+                    (State::Ground(_), FF) if !self.control_pictures => {
+                        self.observed.replacements += 1;
+                        self.replaced(ViolationKind::ControlCode, c, " ");
+                        self.push(' ', end);
                         self.expect_starter = false;
                         self.state = State::Ground(false)
                     }
-                    (State::Ground(_), '\r') => self.state = State::Cr,
-                    (State::Ground(_), ESC) => self.state = State::Esc,
+                    (State::Ground(_), '\r') => {
+                        self.start_state();
+                        self.state = State::Cr
+                    }
+                    (State::Ground(_), ESC) if !self.control_pictures => {
+                        self.observed.escape_sequences = true;
+                        self.start_state();
+                        self.state = State::Esc
+                    }
                     (State::Ground(_), c) if c.is_control() => {
-                        self.queue.push(REPL);
+                        self.observed.replacements += 1;
+                        if self.control_pictures {
+                            self.push_visible(c, end);
+                        } else {
+                            self.replaced(ViolationKind::ControlCode, c, "\u{fffd}");
+                            self.push(REPL, end);
+                        }
                         self.expect_starter = false;
                         self.state = State::Ground(false);
                     }
                     (State::Ground(_), CGJ) => {
-                        self.queue.push(CGJ);
-                        self.queued_nfc_resets += 1;
+                        self.push(CGJ, end);
                         self.expect_starter = false;
                         self.state = State::Ground(false)
                     }
-                    (State::Ground(_), '\u{2329}') => {
+                    (State::Ground(_), '\u{2329}') | (State::Ground(_), '\u{232a}') => {
+                        self.observed.replacements += 1;
+                        self.replaced(ViolationKind::Deprecated, c, "\u{fffd}");
                         self.expect_starter = false;
-                        self.queue.push(REPL);
-                        self.state = State::Ground(false)
-                    }
-                    (State::Ground(_), '\u{232a}') => {
-                        self.expect_starter = false;
-                        self.queue.push(REPL);
+                        self.push(REPL, end);
                         self.state = State::Ground(false)
                     }
                     (State::Ground(_), mut c) => {
+                        if self.profile.disallows_unassigned()
+                            && !self.preserve_unassigned
+                            && !is_assigned(c)
+                        {
+                            // Noncharacters are also unassigned; report them
+                            // as noncharacters.
+                            let kind = disallowed(c).map_or(ViolationKind::Unassigned, Into::into);
+                            self.replaced(kind, c, "\u{fffd}");
+                            c = REPL;
+                        }
                         if self.expect_starter {
                             self.expect_starter = false;
                             if !is_normalization_form_starter(c) {
+                                self.replaced(ViolationKind::LeadingNonstarter, c, "\u{fffd}");
                                 c = REPL;
                             }
                         }
-                        self.queue.push(c);
+                        if c == REPL {
+                            self.observed.replacements += 1;
+                        }
+                        self.push(c, end);
                         self.state = State::Ground(false)
                     }
 
                     (State::Cr, '\n') => {
-                        self.queue.push('\n');
-                        self.queued_nfc_resets += 1;
+                        self.observed.crlf += 1;
+                        self.replaced_state_start(ViolationKind::CarriageReturn, '\r', "");
+                        self.push_line_end(end);
                         self.expect_starter = false;
                         self.state = State::Ground(true);
                     }
                     (State::Cr, _) => {
-                        self.queue.push(REPL);
+                        self.observed.lone_cr += 1;
+                        self.observed.replacements += 1;
+                        self.push_lone_cr(start);
                         self.expect_starter = false;
                         self.state = State::Ground(false);
                         continue;
@@ -208,11 +712,11 @@ impl TextReaderImpl {
 
                     (State::Esc, '[') => self.state = State::CsiStart,
                     (State::Esc, ']') => self.state = State::Osc,
-                    (State::Esc, c) if ('@'..='~').contains(&c) => {
-                        self.state = State::Ground(false)
-                    }
+                    (State::Esc, c) if ('@'..='~').contains(&c) => self.escape_sequence(),
                     (State::Esc, _) => {
-                        self.queue.push(REPL);
+                        self.observed.replacements += 1;
+                        self.replaced_state_start(ViolationKind::ControlCode, ESC, "\u{fffd}");
+                        self.push(REPL, start);
                         self.state = State::Ground(false);
                         continue;
                     }
@@ -222,27 +726,182 @@ impl TextReaderImpl {
                         self.state = State::Csi
                     }
                     (State::CsiStart, c) | (State::Csi, c) if ('@'..='~').contains(&c) => {
-                        self.state = State::Ground(false)
+                        self.escape_sequence()
                     }
                     (State::CsiStart, _) | (State::Csi, _) => {
-                        self.state = State::Ground(false);
+                        self.escape_sequence();
                         continue;
                     }
 
                     (State::Osc, c) if !c.is_control() || c == '\n' || c == '\t' => (),
-                    (State::Osc, _) => self.state = State::Ground(false),
+                    (State::Osc, _) => self.escape_sequence(),
 
-                    (State::Linux, c) if ('\0'..=DEL).contains(&c) => {
-                        self.state = State::Ground(false)
-                    }
+                    (State::Linux, c) if ('\0'..=DEL).contains(&c) => self.escape_sequence(),
                     (State::Linux, _) => {
-                        self.state = State::Ground(false);
+                        self.escape_sequence();
                         continue;
                     }
                 }
                 break;
             }
         }
+        self.raw_string = raw_string;
+        self.flush_events();
+    }
+
+    /// Report a replacement of the `char` being processed, if an observer
+    /// is set.
+    #[inline]
+    fn replaced(&mut self, kind: ViolationKind, c: char, replacement: &str) {
+        if let Some(observing) = &mut self.observing {
+            observing.replaced(kind, Some(c), replacement);
+        }
+    }
+
+    /// Report a replacement of the `char` which started the current state,
+    /// if an observer is set.
+    #[inline]
+    fn replaced_state_start(&mut self, kind: ViolationKind, c: char, replacement: &str) {
+        if let Some(observing) = &mut self.observing {
+            observing.replaced_state_start(kind, c, replacement);
+        }
+    }
+
+    /// Record that the `char` being processed starts a state.
+    #[inline]
+    fn start_state(&mut self) {
+        if let Some(observing) = &mut self.observing {
+            observing.start_state();
+        }
+    }
+
+    /// The end of an escape sequence, which is removed.
+    fn escape_sequence(&mut self) {
+        self.replaced_state_start(ViolationKind::EscapeSequence, ESC, "");
+        self.state = State::Ground(false);
+    }
+
+    /// Queue `c`, which was produced from input ending at `end`.
+    #[inline]
+    fn push(&mut self, c: char, end: u64) {
+        self.queue.push_back(c);
+        if let Some(observing) = &mut self.observing {
+            observing.queued(c);
+        }
+        if let Some(mapping) = &mut self.mapping {
+            let verbatim = mapping.current == Some(c) && end == mapping.input_offset;
+            mapping.sources.push_back((end, verbatim));
+        }
+    }
+
+    /// Queue a '\n' from the input which ends a line, so that reading can
+    /// be resumed after it.
+    fn push_line_end(&mut self, end: u64) {
+        self.push('\n', end);
+        if let Some(snapshots) = &mut self.snapshots {
+            snapshots.line_ends.push_back(snapshots.input_offset);
+        }
+    }
+
+    /// Queue the replacement for control code `c`, in control pictures mode.
+    fn push_visible(&mut self, c: char, end: u64) {
+        let mut visible = String::new();
+        write_visible(c, &mut visible).unwrap();
+        if c == '\r' {
+            self.replaced_state_start(ViolationKind::CarriageReturn, c, &visible);
+        } else {
+            self.replaced(ViolationKind::ControlCode, c, &visible);
+        }
+        for v in visible.chars() {
+            self.push(v, end);
+        }
+    }
+
+    /// Queue the replacement for a '\r' which isn't followed by a '\n'.
+    fn push_lone_cr(&mut self, end: u64) {
+        if self.control_pictures {
+            self.push_visible('\r', end);
+        } else {
+            self.replaced_state_start(ViolationKind::CarriageReturn, '\r', "\u{fffd}");
+            self.push(REPL, end);
+        }
+    }
+
+    /// At the end of a stream or a push, terminate any partial sequence, and
+    /// at the end of a stream, ensure there's a final newline.
+    fn end_segment(&mut self, status: Status) {
+        let end = self
+            .mapping
+            .as_ref()
+            .map_or(0, |mapping| mapping.input_offset);
+        if let Some(observing) = &mut self.observing {
+            observing.at_end();
+        }
+        match self.state {
+            State::Ground(_) => {}
+            State::Cr => {
+                self.observed.lone_cr += 1;
+                self.observed.replacements += 1;
+                self.push_lone_cr(end);
+                self.state = State::Ground(false);
+            }
+            State::Esc => {
+                self.observed.replacements += 1;
+                self.replaced_state_start(ViolationKind::ControlCode, ESC, "\u{fffd}");
+                self.push(REPL, end);
+                self.state = State::Ground(false);
+            }
+            State::CsiStart | State::Csi | State::Osc | State::Linux => self.escape_sequence(),
+        }
+
+        if status.is_end() && self.state != State::Ground(true) {
+            if let Some(observing) = &mut self.observing {
+                observing.replaced(ViolationKind::MissingFinalNewline, None, "\n");
+            }
+            self.push('\n', end);
+            self.state = State::Ground(true);
+        }
+        if let Some(observing) = &mut self.observing {
+            observing.end();
+        }
+
+        // Attribute anything left over, such as a trailing escape sequence,
+        // to the last queued `char`, or if there isn't one, record it now.
+        if let Some(mapping) = &mut self.mapping {
+            match mapping.sources.back_mut() {
+                Some(last) => last.0 = end,
+                None => mapping.skip_to(end),
+            }
+        }
+        self.flush_events();
+    }
+
+    /// Translate `s`, a chunk of a larger input which ends with a '\n', or
+    /// ends the input if `last` is true, appending the result to `output`.
+    /// Unless `first` is true, it's assumed to follow a '\n' which ended a
+    /// line. Returns false if the final '\n' didn't end a line, for example
+    /// because it was inside an escape sequence, in which case the following
+    /// chunk must be translated together with this one instead.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn translate_chunk(s: String, first: bool, last: bool, output: &mut String) -> bool {
+        let mut impl_ = Self::new();
+        if !first {
+            impl_.at_start = false;
+            impl_.expect_starter = false;
+        }
+
+        impl_.raw_string = s;
+        impl_.process_raw_string();
+        if last {
+            impl_.end_segment(Status::End);
+        } else if impl_.state != State::Ground(true) {
+            return false;
+        }
+
+        while let Some(c) = impl_.queue_next(true) {
+            output.push(c);
+        }
+        true
     }
 
     pub(crate) fn read_with_status<Inner: ReadExt>(
@@ -256,6 +915,103 @@ impl TextReaderImpl {
             ));
         }
 
+        if !internals.impl_().read_buffer.is_empty() {
+            return Ok(internals.impl_().read_buffer.drain(buf));
+        }
+
+        if internals.impl_().strict.is_some() {
+            return Self::read_strict(internals, buf);
+        }
+
+        match internals.impl_().after.take() {
+            None => Self::read_normalized(internals, buf),
+            Some(mut after) => {
+                let result = Self::read_renormalized(internals, &mut after, buf);
+                internals.impl_().after = Some(after);
+                result
+            }
+        }
+    }
+
+    /// Read and normalize input, and run any `AfterNormalization`
+    /// transforms on it and normalize the result.
+    fn read_renormalized<Inner: ReadExt>(
+        internals: &mut impl TextReaderInternals<Inner>,
+        after: &mut Renormalize,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, Status)> {
+        loop {
+            let (nread, status) = after.drain(buf);
+            if nread != 0 || status != Status::active() {
+                return Ok((nread, status));
+            }
+
+            let mut scratch = mem::take(&mut after.scratch);
+            scratch.resize(buf.len(), 0);
+            let result =
+                Self::read_normalized(internals, &mut scratch).and_then(|(size, status)| {
+                    after.feed(
+                        unsafe { str::from_utf8_unchecked(&scratch[..size]) },
+                        status,
+                    )?;
+                    Ok((size, status))
+                });
+            after.scratch = scratch;
+
+            // If the underlying stream produced nothing, return so that the
+            // caller can decide whether to wait.
+            if let (0, status) = result? {
+                if status == Status::active() {
+                    return Ok((0, status));
+                }
+            }
+        }
+    }
+
+    /// Read input in strict mode. Valid Text passes through the rules
+    /// unchanged, so rather than running the input through them, it's
+    /// checked with a `Validator` and returned as-is up to the first
+    /// violation.
+    fn read_strict<Inner: ReadExt>(
+        internals: &mut impl TextReaderInternals<Inner>,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, Status)> {
+        loop {
+            let strict = internals.impl_().strict.as_mut().unwrap();
+            if !strict.output.is_empty() {
+                return Ok(strict.output.drain(buf));
+            }
+            if let Some(violation) = &strict.violation {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    violation.clone(),
+                ));
+            }
+
+            let mut raw_bytes = mem::take(&mut internals.impl_().raw_string).into_bytes();
+            raw_bytes.resize(4096, 0_u8);
+            let (size, status) = internals.inner().read_with_status(&mut raw_bytes)?;
+            raw_bytes.resize(size, 0);
+            let raw_string = String::from_utf8(raw_bytes).unwrap();
+            let invalid = internals.utf8_impl().take_invalid();
+
+            let impl_ = internals.impl_();
+            let strict = impl_.strict.as_mut().unwrap();
+            strict.feed(&raw_string, invalid, status);
+            impl_.raw_string = raw_string;
+
+            if strict.violation.is_none() {
+                return Ok(strict.output.drain(buf));
+            }
+        }
+    }
+
+    /// Read and normalize input, without any `AfterNormalization`
+    /// transforms.
+    fn read_normalized<Inner: ReadExt>(
+        internals: &mut impl TextReaderInternals<Inner>,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, Status)> {
         let mut nread = 0;
 
         loop {
@@ -276,29 +1032,67 @@ impl TextReaderImpl {
         let mut raw_bytes =
             mem::replace(&mut internals.impl_().raw_string, String::new()).into_bytes();
         raw_bytes.resize(4096, 0_u8);
-        let (size, status) = internals.inner().read_with_status(&mut raw_bytes)?;
+        let (size, status) = match internals.inner().read_with_status(&mut raw_bytes) {
+            Ok(size_and_status) => size_and_status,
+            Err(e) => {
+                raw_bytes.clear();
+                internals.impl_().raw_string = String::from_utf8(raw_bytes).unwrap();
+                // Chars already written to `buf` have been taken from the
+                // queue, so return them, and let the next read retry.
+                if nread != 0 && is_retryable(&e) {
+                    return Ok((nread, Status::active()));
+                }
+                return Err(e);
+            }
+        };
         raw_bytes.resize(size, 0);
         internals.impl_().raw_string = String::from_utf8(raw_bytes).unwrap();
 
-        internals.impl_().process_raw_string();
+        let mut invalid = if internals.impl_().records_invalid() {
+            internals.utf8_impl().take_invalid()
+        } else {
+            VecDeque::new()
+        };
+        let impl_ = internals.impl_();
 
-        if status != Status::active() {
-            match internals.impl_().state {
-                State::Ground(_) => {}
-                State::Cr | State::Esc => {
-                    internals.impl_().queue.push(REPL);
-                    internals.impl_().state = State::Ground(false);
-                }
-                State::CsiStart | State::Csi | State::Osc | State::Linux => {
-                    internals.impl_().state = State::Ground(false);
-                }
+        // If there are `BeforeNormalization` transforms or terminal controls
+        // are interpreted, the Text rules don't see the input itself, so
+        // invalid UTF-8 is just U+FFFD to them, and it's reported here.
+        let transformed = !impl_.before.is_empty() || impl_.terminal.is_some();
+        let mut events = Vec::new();
+        if let Some(terminal) = &mut impl_.terminal {
+            let raw_string = mem::take(&mut impl_.raw_string);
+            terminal.apply(
+                &raw_string,
+                status.is_end(),
+                &mut impl_.raw_string,
+                &mut events,
+            );
+            if let Some(observing) = &mut impl_.observing {
+                observing.raw_input(&raw_string, &mut invalid, &events);
             }
-
-            if status.is_end() && internals.impl_().state != State::Ground(true) {
-                internals.impl_().queue.push('\n');
-                internals.impl_().queued_nfc_resets += 1;
-                internals.impl_().state = State::Ground(true);
+        } else if transformed {
+            if let Some(observing) = &mut impl_.observing {
+                observing.raw_input(&impl_.raw_string, &mut invalid, &events);
             }
+        } else {
+            impl_.invalid = invalid;
+        }
+
+        if !internals.impl_().before.is_empty() {
+            let impl_ = internals.impl_();
+            let raw_string = mem::take(&mut impl_.raw_string);
+            impl_.before.apply(
+                &raw_string,
+                status != Status::active(),
+                &mut impl_.raw_string,
+            )?;
+        }
+
+        internals.impl_().process_raw_string();
+
+        if status != Status::active() {
+            internals.impl_().end_segment(status);
         }
 
         loop {
@@ -313,7 +1107,7 @@ impl TextReaderImpl {
 
         Ok((
             nread,
-            if internals.impl_().queue_iter.is_none() {
+            if !internals.impl_().output_pending() {
                 if status != Status::active() {
                     internals.impl_().expect_starter = true;
                 }
@@ -325,6 +1119,42 @@ impl TextReaderImpl {
         ))
     }
 
+    /// Fill the internal buffer, if it's empty, and return its contents,
+    /// which always end on a boundary after which normalization can't
+    /// change the preceding scalar values. This is empty at the end of the
+    /// stream, or if the underlying stream has no data available yet.
+    pub(crate) fn fill_buf<Inner: ReadExt>(
+        internals: &mut impl TextReaderInternals<Inner>,
+    ) -> io::Result<&[u8]> {
+        if internals.impl_().read_buffer.is_empty() {
+            let mut buf = internals.impl_().read_buffer.take_storage();
+            let result = loop {
+                match Self::read_with_status(internals, &mut buf) {
+                    // Input can be consumed without producing output yet,
+                    // so keep reading, unless the underlying stream has
+                    // nothing more for now.
+                    Ok((0, status)) if !status.is_end() && !internals.utf8_impl().stalled() => {
+                        continue
+                    }
+                    result => break result,
+                }
+            };
+            let (size, status) = *result.as_ref().unwrap_or(&(0, Status::active()));
+            internals.impl_().read_buffer.fill(buf, size, status);
+            result?;
+        }
+
+        Ok(internals.impl_().read_buffer.data())
+    }
+
+    #[inline]
+    pub(crate) fn consume<Inner: ReadExt>(
+        internals: &mut impl TextReaderInternals<Inner>,
+        amt: usize,
+    ) {
+        internals.impl_().read_buffer.consume(amt)
+    }
+
     #[inline]
     pub(crate) fn read<Inner: ReadExt>(
         internals: &mut impl TextReaderInternals<Inner>,
@@ -374,8 +1204,359 @@ impl TextReaderImpl {
     }
 }
 
+/// Normalize `chars` on their own.
+fn normalize(chars: &[char]) -> Vec<char> {
+    chars.iter().copied().svar().stream_safe().nfc().collect()
+}
+
+/// Find where to split `chars`, the start of a run with no segment starts, so that normalizing the parts separately gives the same
+/// output as normalizing it whole, whatever follows it. Returns the length
+/// of the first part and whether the normalizer inserts a CGJ after it.
+fn split_overlong(chars: &[char]) -> Option<(usize, bool)> {
+    let mut whole = None;
+    (1..chars.len()).rev().find_map(|index| {
+        let c = chars[index];
+        if is_tag_spec_character(c) || c == CANCEL_TAG {
+            return None;
+        }
+        let whole = whole.get_or_insert_with(|| normalize(chars));
+        let (first, rest) = (normalize(&chars[..index]), normalize(&chars[index..]));
+        let tail = whole.strip_prefix(&first[..])?;
+        // What follows can't affect a split before a starter, or one where
+        // the normalizer inserts a CGJ.
+        if is_normalization_form_starter(c) && tail == rest {
+            Some((index, false))
+        } else if tail.first() == Some(&CGJ) && tail[1..] == rest[..] {
+            Some((index, true))
+        } else {
+            None
+        }
+    })
+}
+
+/// Counts of what has been seen in the input, for `input_profile`.
+#[derive(Default)]
+struct Observed {
+    bom: bool,
+    lf: u64,
+    crlf: u64,
+    lone_cr: u64,
+    escape_sequences: bool,
+    replacements: u64,
+}
+
+/// State for recording an `OffsetMap`.
+#[derive(Default)]
+struct Mapping {
+    /// For each `char` in the queue, the input offset of the end of the
+    /// input which produced it, and whether it's a copy of that input.
+    sources: VecDeque<(u64, bool)>,
+
+    /// The `char` being processed, or `None` if it's a replacement for
+    /// invalid UTF-8.
+    current: Option<char>,
+
+    /// The input offset of the end of the input processed so far.
+    input_offset: u64,
+
+    /// The input offset where the next segment's input begins.
+    segment_start: u64,
+
+    /// The output offset of the end of the spans recorded so far.
+    output_offset: u64,
+
+    map: OffsetMap,
+}
+
+impl Mapping {
+    /// Advance past `c`, and return the range of input it came from. If
+    /// it's a replacement for invalid UTF-8, `invalid` is the length of the
+    /// original sequence.
+    fn advance(&mut self, c: char, invalid: Option<usize>) -> (u64, u64) {
+        let len = match invalid {
+            Some(len) => {
+                self.current = None;
+                len
+            }
+            None => {
+                self.current = Some(c);
+                c.len_utf8()
+            }
+        };
+        let start = self.input_offset;
+        self.input_offset += len as u64;
+        (start, self.input_offset)
+    }
+
+    /// Record a span for a segment which produced `len` bytes of output.
+    fn push(&mut self, len: u64, input: Range<u64>, exact: bool) {
+        let output = self.output_offset..self.output_offset + len;
+        self.output_offset = output.end;
+        self.segment_start = input.end;
+        self.map.push(OffsetSpan {
+            output,
+            input,
+            exact,
+        });
+    }
+
+    /// Record any input up to `end` which produced no output.
+    fn skip_to(&mut self, end: u64) {
+        if self.segment_start < end {
+            self.push(0, self.segment_start..end, false);
+        }
+    }
+}
+
+/// The ways of interpreting terminal controls in the input.
+enum Terminal {
+    LineEditing(LineEditor),
+    Transcript(Transcript),
+}
+
+impl Terminal {
+    /// Interpret the controls in `input`, appending the resulting lines to
+    /// `output`, and recording what's discarded in `events`. If `end` is
+    /// true, the input has ended.
+    fn apply(
+        &mut self,
+        input: &str,
+        end: bool,
+        output: &mut String,
+        events: &mut Vec<(usize, TerminalEvent)>,
+    ) {
+        match self {
+            Self::LineEditing(line_editor) => line_editor.apply(input, end, output, events),
+            Self::Transcript(transcript) => transcript.apply(input, end, output, events),
+        }
+    }
+}
+
+/// State for strict mode.
+struct Strict {
+    /// Checks the input, stopping at the first violation.
+    validator: Validator,
+
+    /// Input which has been checked, but which may still be followed by a
+    /// violation which prevents it from being returned as-is, such as a
+    /// combining mark which would compose with it.
+    unconfirmed: String,
+
+    /// The input offset of the start of `unconfirmed`.
+    unconfirmed_start: u64,
+
+    /// Input which is known to be valid and hasn't been returned yet.
+    output: ReadBuffer,
+
+    /// The first violation, once it has been found, which is reported
+    /// once `output` has been drained.
+    violation: Option<Violation>,
+}
+
+impl Strict {
+    fn new(profile: TextProfile) -> Self {
+        Self {
+            validator: Validator::new(profile, 1),
+            unconfirmed: String::new(),
+            unconfirmed_start: 0,
+            output: ReadBuffer::new(),
+            violation: None,
+        }
+    }
+
+    /// Check `s`, the result of a read with status `status`, in which the
+    /// invalid UTF-8 sequences listed in `invalid` have been replaced with
+    /// U+FFFD, and move the input which is known to be valid to `output`.
+    fn feed(&mut self, s: &str, mut invalid: VecDeque<(usize, Vec<u8>)>, status: Status) {
+        for (index, c) in s.char_indices() {
+            match invalid.front() {
+                Some((at, _)) if *at == index => {
+                    let (_, bytes) = invalid.pop_front().unwrap();
+                    self.validator.invalid_utf8(bytes.len());
+                }
+                _ => self.validator.char(c),
+            }
+        }
+        self.unconfirmed.push_str(s);
+        if status != Status::active() {
+            self.validator.sequence_end(status);
+        }
+
+        // Offsets in `unconfirmed` only line up with input offsets up to the
+        // first invalid UTF-8 sequence, but that's always a violation.
+        let confirmed = match self.validator.take_violations().into_iter().next() {
+            Some(violation) => {
+                let offset = violation.offset;
+                self.violation = Some(violation);
+                offset
+            }
+            None if status != Status::active() => {
+                self.unconfirmed_start + self.unconfirmed.len() as u64
+            }
+            None => self.validator.confirmed_offset(),
+        };
+        let len = (confirmed - self.unconfirmed_start) as usize;
+        let valid = self.unconfirmed.drain(..len).collect::<String>();
+        self.unconfirmed_start = confirmed;
+        if self.violation.is_some() {
+            self.unconfirmed.clear();
+        }
+        let len = valid.len();
+        self.output.fill(
+            valid.into_bytes(),
+            len,
+            if self.violation.is_some() {
+                Status::active()
+            } else {
+                status
+            },
+        );
+    }
+}
+
+/// State for taking snapshots.
+struct Snapshots {
+    /// The input offset of the end of the input processed so far.
+    input_offset: u64,
+
+    /// For each '\n' in the queue which ends a line, the input offset after
+    /// it.
+    line_ends: VecDeque<u64>,
+
+    /// The output offset of the end of the output produced so far,
+    /// including any skipped when resuming.
+    output_offset: u64,
+
+    /// The number of bytes of output still to be skipped when resuming.
+    skip: u64,
+
+    /// The starts of the most recent lines which may still contain output
+    /// which hasn't been returned.
+    lines: VecDeque<LineStart>,
+}
+
+/// The start of a line, from which reading can be resumed.
+struct LineStart {
+    output: u64,
+    input: u64,
+
+    /// True if this is the start of the stream, rather than following a
+    /// '\n'.
+    at_start: bool,
+}
+
+impl Snapshots {
+    fn new(input_offset: u64, at_start: bool, skip: u64) -> Self {
+        let mut lines = VecDeque::new();
+        lines.push_back(LineStart {
+            output: 0,
+            input: input_offset,
+            at_start,
+        });
+        Self {
+            input_offset,
+            line_ends: VecDeque::new(),
+            output_offset: 0,
+            skip,
+            lines,
+        }
+    }
+
+    /// Account for output of `c`, and return false if it's to be skipped.
+    fn output(&mut self, c: char) -> bool {
+        let len = c.len_utf8() as u64;
+        self.output_offset += len;
+
+        // A '\n' which isn't from the input is the final newline appended
+        // at the end of the stream.
+        if c == '\n' {
+            if let Some(input) = self.line_ends.pop_front() {
+                self.lines.push_back(LineStart {
+                    output: self.output_offset,
+                    input,
+                    at_start: false,
+                });
+            }
+        }
+
+        // At most a `ReadBuffer`'s worth of output can be waiting to be
+        // returned, so older line starts are no longer needed.
+        while self.lines.len() > 1
+            && self.lines[1].output + READ_BUFFER_SIZE as u64 <= self.output_offset
+        {
+            self.lines.pop_front();
+        }
+
+        if self.skip == 0 {
+            return true;
+        }
+        self.skip -= len;
+        false
+    }
+}
+
+/// State for applying `AfterNormalization` transforms to the output, and
+/// running the result through the Text rules and normalization again.
+struct Renormalize {
+    transforms: Transforms,
+
+    /// The pipeline the output of `transforms` is fed through.
+    impl_: TextReaderImpl,
+
+    /// The status of the last read fed in, reported once its output has
+    /// been drained.
+    status: Status,
+
+    /// Storage for reading normalized input.
+    scratch: Vec<u8>,
+}
+
+impl Renormalize {
+    fn new(profile: TextProfile, preserve_unassigned: bool) -> Self {
+        let mut impl_ = TextReaderImpl::with_profile(profile);
+        impl_.preserve_unassigned = preserve_unassigned;
+        impl_.at_start = false;
+        Self {
+            transforms: Transforms::new(),
+            impl_,
+            status: Status::active(),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Apply the transforms to `s` and queue the result for normalization.
+    fn feed(&mut self, s: &str, status: Status) -> io::Result<()> {
+        let end = status != Status::active();
+        self.impl_.raw_string.clear();
+        self.transforms.apply(s, end, &mut self.impl_.raw_string)?;
+        self.impl_.process_raw_string();
+        if end {
+            self.impl_.end_segment(status);
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    /// Copy as much normalized output into `buf` as is available.
+    fn drain(&mut self, buf: &mut [u8]) -> (usize, Status) {
+        let end = self.status != Status::active();
+        let mut nread = 0;
+        while buf.len() - nread >= MAX_UTF8_SIZE {
+            match self.impl_.queue_next(end) {
+                Some(c) => nread += c.encode_utf8(&mut buf[nread..]).len(),
+                None if end => {
+                    self.impl_.expect_starter = true;
+                    return (nread, mem::replace(&mut self.status, Status::active()));
+                }
+                None => break,
+            }
+        }
+        (nread, Status::active())
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum State {
+pub(crate) enum State {
     // Default state. Boolean is true iff we just saw a '\n'.
     Ground(bool),
 
@@ -0,0 +1,38 @@
+//! Defines `TextReaderSnapshot`.
+
+use crate::TextProfile;
+
+/// The state of a `TextReader`, from which reading can be resumed later,
+/// for example by another process, with [`TextReader::resume`].
+///
+/// Rather than the reader's internal buffers, a snapshot records the offset
+/// in the underlying stream of the start of the line containing the next
+/// output, and how much of that line's output has already been returned.
+/// Resuming reads the line again and skips what was already returned, so
+/// the output is the same as if reading had continued uninterrupted, even
+/// if the snapshot was taken in the middle of an escape sequence or a UTF-8
+/// sequence.
+///
+/// With the `serde` feature, this implements `Serialize` and `Deserialize`.
+///
+/// [`TextReader::resume`]: crate::TextReader::resume
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct TextReaderSnapshot {
+    pub(crate) offset: u64,
+    pub(crate) skip: u64,
+    pub(crate) at_start: bool,
+    pub(crate) profile: TextProfile,
+    pub(crate) preserve_unassigned: bool,
+    pub(crate) control_pictures: bool,
+}
+
+impl TextReaderSnapshot {
+    /// The byte offset in the underlying stream, relative to where the
+    /// reader started, that the stream passed to `TextReader::resume` must
+    /// start at.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    text_reader_impl::TextReaderImpl, text_writer_impl::TextWriterImpl, ReadStr, Utf8ReaderWriter,
-    WriteWrapper,
+    text_reader_impl::TextReaderImpl, text_writer_impl::TextWriterImpl, InputProfile, ReadStr,
+    TextProfile, Utf8ReaderWriter, WriteWrapper,
 };
 use io_ext::{
     default_read, default_read_exact, default_read_to_end, default_read_to_string,
@@ -28,6 +28,17 @@ impl<Inner: ReadWriteExt> TextReaderWriter<Inner> {
         }
     }
 
+    /// Like `new`, but applies the rules of the given version of the Text
+    /// format, rather than [`TextProfile::TextLatest`].
+    #[inline]
+    pub fn with_profile(inner: Inner, profile: TextProfile) -> Self {
+        Self {
+            inner: Utf8ReaderWriter::new(inner),
+            reader_impl: TextReaderImpl::with_profile(profile),
+            writer_impl: TextWriterImpl::with_profile(profile),
+        }
+    }
+
     /// Like `new`, but writes a U+FEFF (BOM) to the beginning of the output
     /// stream for compatibility with consumers that require that to determine
     /// the text encoding.
@@ -61,6 +72,16 @@ impl<Inner: ReadWriteExt> TextReaderWriter<Inner> {
         }
     }
 
+    /// Return a summary of what has been seen in the input so far. See
+    /// [`TextReader::input_profile`].
+    ///
+    /// [`TextReader::input_profile`]: crate::TextReader::input_profile
+    #[inline]
+    pub fn input_profile(&self) -> InputProfile {
+        self.reader_impl
+            .input_profile(self.inner.reader_impl.saw_invalid())
+    }
+
     /// Flush and close the underlying stream and return the underlying
     /// stream object.
     #[inline]
@@ -0,0 +1,495 @@
+//! Defines `TextStr` and `TextString`, and their single-line counterparts
+//! `TextLineStr` and `TextLineString`.
+
+use crate::{
+    to_text::{is_plain_ascii, is_plain_ascii_text, to_text},
+    unicode::needs_cgj,
+    validate::first_violation,
+    TextProfile, Violation, ViolationKind,
+};
+use std::{
+    borrow::{Borrow, Cow},
+    convert::TryFrom,
+    fmt,
+    ops::{Add, AddAssign, Deref},
+};
+
+/// A string slice which is a complete, valid [Text] document: it's in NFC
+/// and Stream-Safe, contains no disallowed codepoints, begins with a
+/// normalization-form starter, and is either empty or ends with a newline.
+///
+/// This is to `TextString` as `str` is to `String`. Validity is determined
+/// by the rules of [`TextProfile::TextLatest`].
+///
+/// [Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/text.md
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct TextStr(str);
+
+impl TextStr {
+    /// Convert `s` into a `TextStr` without checking that it's valid Text.
+    ///
+    /// # Safety
+    ///
+    /// `s` must be valid Text.
+    #[inline]
+    pub(crate) unsafe fn from_str_unchecked(s: &str) -> &Self {
+        &*(s as *const str as *const Self)
+    }
+
+    /// Return the contents as a `str`.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> TryFrom<&'a str> for &'a TextStr {
+    type Error = Violation;
+
+    /// Check that `s` is valid Text, and fail with the first violation if
+    /// it isn't.
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        if !is_plain_ascii_text(s.as_bytes()) {
+            if let Some(violation) = first_violation(s, TextProfile::default()) {
+                return Err(violation);
+            }
+        }
+        Ok(unsafe { TextStr::from_str_unchecked(s) })
+    }
+}
+
+impl Deref for TextStr {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for TextStr {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ToOwned for TextStr {
+    type Owned = TextString;
+
+    #[inline]
+    fn to_owned(&self) -> TextString {
+        TextString(self.0.to_owned())
+    }
+}
+
+impl fmt::Debug for TextStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for TextStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// An owned string which is a complete, valid [Text] document.
+///
+/// This is to `TextStr` as `String` is to `str`.
+///
+/// [Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/text.md
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextString(String);
+
+impl TextString {
+    /// Construct a new empty `TextString`.
+    #[inline]
+    pub fn new() -> Self {
+        Self(String::new())
+    }
+
+    /// Construct a `TextString` from arbitrary bytes, replacing anything
+    /// which isn't valid Text, following the same rules as `TextReader`.
+    ///
+    /// Some replacements can form new sequences which need normalizing, so
+    /// the rules are reapplied until the result is valid Text.
+    pub fn from_lossy(bytes: &[u8]) -> Self {
+        let mut s = to_text(bytes).into_owned();
+        loop {
+            match to_text(s.as_bytes()) {
+                Cow::Borrowed(_) => return Self(s),
+                Cow::Owned(text) => s = text,
+            }
+        }
+    }
+
+    /// Return the contents as a `TextStr`.
+    #[inline]
+    pub fn as_text_str(&self) -> &TextStr {
+        unsafe { TextStr::from_str_unchecked(&self.0) }
+    }
+
+    /// Return the contents as a `String`.
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Append `other` to the end of this string.
+    ///
+    /// Text documents end with a newline and begin with a normalization-form
+    /// starter, so the result is always valid Text, without needing the CGJ
+    /// insertion that concatenating arbitrary Text streams may require.
+    #[inline]
+    pub fn push_text(&mut self, other: &TextStr) {
+        if let Some(first) = other.chars().next() {
+            debug_assert!(!needs_cgj(self.0.chars().next_back(), first));
+        }
+        self.0.push_str(other);
+    }
+
+    /// Append `line` and a newline to the end of this string.
+    #[inline]
+    pub fn push_line(&mut self, line: &TextLineStr) {
+        if let Some(first) = line.chars().next() {
+            debug_assert!(!needs_cgj(self.0.chars().next_back(), first));
+        }
+        self.0.push_str(line);
+        self.0.push('\n');
+    }
+}
+
+impl TryFrom<String> for TextString {
+    type Error = Violation;
+
+    /// Check that `s` is valid Text, and fail with the first violation if
+    /// it isn't.
+    #[inline]
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        <&TextStr>::try_from(s.as_str())?;
+        Ok(Self(s))
+    }
+}
+
+impl TryFrom<&str> for TextString {
+    type Error = Violation;
+
+    /// Check that `s` is valid Text, and fail with the first violation if
+    /// it isn't.
+    #[inline]
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        <&TextStr>::try_from(s).map(ToOwned::to_owned)
+    }
+}
+
+impl From<&TextStr> for TextString {
+    #[inline]
+    fn from(s: &TextStr) -> Self {
+        s.to_owned()
+    }
+}
+
+impl From<TextString> for String {
+    #[inline]
+    fn from(s: TextString) -> Self {
+        s.0
+    }
+}
+
+impl Deref for TextString {
+    type Target = TextStr;
+
+    #[inline]
+    fn deref(&self) -> &TextStr {
+        self.as_text_str()
+    }
+}
+
+impl Borrow<TextStr> for TextString {
+    #[inline]
+    fn borrow(&self) -> &TextStr {
+        self.as_text_str()
+    }
+}
+
+impl AsRef<TextStr> for TextString {
+    #[inline]
+    fn as_ref(&self) -> &TextStr {
+        self.as_text_str()
+    }
+}
+
+impl AsRef<str> for TextString {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Add<&TextStr> for TextString {
+    type Output = Self;
+
+    #[inline]
+    fn add(mut self, other: &TextStr) -> Self {
+        self.push_text(other);
+        self
+    }
+}
+
+impl AddAssign<&TextStr> for TextString {
+    #[inline]
+    fn add_assign(&mut self, other: &TextStr) {
+        self.push_text(other);
+    }
+}
+
+impl fmt::Debug for TextString {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for TextString {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// A string slice which is a single line of valid [Text], without its
+/// newline: it's in NFC and Stream-Safe, contains no disallowed codepoints
+/// and no U+000A (newline), and begins with a normalization-form starter.
+///
+/// Any `TextLineStr` followed by a newline is a valid `TextStr`, so lines
+/// can be appended to a `TextString` with [`TextString::push_line`].
+///
+/// [Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/text.md
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct TextLineStr(str);
+
+impl TextLineStr {
+    /// Convert `s` into a `TextLineStr` without checking that it's a valid
+    /// line of Text.
+    ///
+    /// # Safety
+    ///
+    /// `s` must be a valid line of Text.
+    #[inline]
+    pub(crate) unsafe fn from_str_unchecked(s: &str) -> &Self {
+        &*(s as *const str as *const Self)
+    }
+
+    /// Return the contents as a `str`.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> TryFrom<&'a str> for &'a TextLineStr {
+    type Error = Violation;
+
+    /// Check that `s` is a valid line of Text, and fail with the first
+    /// violation if it isn't.
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        if let Some(offset) = s.find('\n') {
+            // Report any violation before the newline first.
+            <&TextLineStr>::try_from(&s[..offset])?;
+            return Err(Violation {
+                kind: ViolationKind::Newline,
+                codepoint: Some('\n'),
+                offset: offset as u64,
+                line: 1,
+                column: s[..offset].chars().count() as u64 + 1,
+                replacement: " ".to_owned(),
+            });
+        }
+        if !is_plain_ascii(s.as_bytes()) {
+            match first_violation(s, TextProfile::default()) {
+                Some(violation) if violation.kind != ViolationKind::MissingFinalNewline => {
+                    return Err(violation)
+                }
+                _ => (),
+            }
+        }
+        Ok(unsafe { TextLineStr::from_str_unchecked(s) })
+    }
+}
+
+impl Deref for TextLineStr {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for TextLineStr {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ToOwned for TextLineStr {
+    type Owned = TextLineString;
+
+    #[inline]
+    fn to_owned(&self) -> TextLineString {
+        TextLineString(self.0.to_owned())
+    }
+}
+
+impl fmt::Debug for TextLineStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for TextLineStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// An owned string which is a single line of valid [Text], without its
+/// newline.
+///
+/// This is to `TextLineStr` as `String` is to `str`.
+///
+/// [Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/text.md
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextLineString(String);
+
+impl TextLineString {
+    /// Construct a new empty `TextLineString`.
+    #[inline]
+    pub fn new() -> Self {
+        Self(String::new())
+    }
+
+    /// Construct a `TextLineString` from arbitrary bytes, replacing anything
+    /// which isn't valid Text, following the same rules as `TextReader`, and
+    /// replacing newlines within the line with U+0020 (SPACE).
+    pub fn from_lossy(bytes: &[u8]) -> Self {
+        let mut s = TextString::from_lossy(bytes).into_string();
+        s.pop();
+        // Both are starters which never compose, so this keeps `s` in NFC.
+        let s = s.replace('\n', " ");
+        debug_assert!(<&TextLineStr>::try_from(s.as_str()).is_ok());
+        Self(s)
+    }
+
+    /// Return the contents as a `TextLineStr`.
+    #[inline]
+    pub fn as_text_line_str(&self) -> &TextLineStr {
+        unsafe { TextLineStr::from_str_unchecked(&self.0) }
+    }
+
+    /// Return the contents as a `String`.
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl TryFrom<String> for TextLineString {
+    type Error = Violation;
+
+    /// Check that `s` is a valid line of Text, and fail with the first
+    /// violation if it isn't.
+    #[inline]
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        <&TextLineStr>::try_from(s.as_str())?;
+        Ok(Self(s))
+    }
+}
+
+impl TryFrom<&str> for TextLineString {
+    type Error = Violation;
+
+    /// Check that `s` is a valid line of Text, and fail with the first
+    /// violation if it isn't.
+    #[inline]
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        <&TextLineStr>::try_from(s).map(ToOwned::to_owned)
+    }
+}
+
+impl From<&TextLineStr> for TextLineString {
+    #[inline]
+    fn from(s: &TextLineStr) -> Self {
+        s.to_owned()
+    }
+}
+
+impl From<TextLineString> for String {
+    #[inline]
+    fn from(s: TextLineString) -> Self {
+        s.0
+    }
+}
+
+impl From<&TextLineStr> for TextString {
+    /// Construct a one-line document from `line`.
+    #[inline]
+    fn from(line: &TextLineStr) -> Self {
+        let mut s = Self::new();
+        s.push_line(line);
+        s
+    }
+}
+
+impl Deref for TextLineString {
+    type Target = TextLineStr;
+
+    #[inline]
+    fn deref(&self) -> &TextLineStr {
+        self.as_text_line_str()
+    }
+}
+
+impl Borrow<TextLineStr> for TextLineString {
+    #[inline]
+    fn borrow(&self) -> &TextLineStr {
+        self.as_text_line_str()
+    }
+}
+
+impl AsRef<TextLineStr> for TextLineString {
+    #[inline]
+    fn as_ref(&self) -> &TextLineStr {
+        self.as_text_line_str()
+    }
+}
+
+impl AsRef<str> for TextLineString {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for TextLineString {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for TextLineString {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
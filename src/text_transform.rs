@@ -0,0 +1,109 @@
+//! Defines `TextTransform` and the pipeline stages it plugs into.
+
+use std::{borrow::Cow, collections::VecDeque, io, sync::Mutex};
+
+/// A user-defined stage in the `TextReader` or `TextWriter` pipeline, for
+/// rules such as mapping smart quotes to ASCII, stripping zero-width spaces,
+/// or rejecting a blocklist of codepoints.
+///
+/// Transforms are stateful and streaming: `transform` is called once for
+/// each `char`, in order, and may emit any number of `char`s in its place.
+/// A transform which needs to see what follows a `char` before deciding
+/// what to emit declares how far ahead it needs to look with `lookahead`,
+/// and the pipeline holds back that many `char`s before calling it.
+///
+/// The output of a transform isn't trusted: whichever stage a transform is
+/// registered at, its output is run through the Text rules and normalized
+/// again, so the stream remains in NFC and Stream-Safe and free of
+/// disallowed codepoints.
+pub trait TextTransform {
+    /// The number of `char`s following the current one which `transform`
+    /// needs to see. The default is 0.
+    #[inline]
+    fn lookahead(&self) -> usize {
+        0
+    }
+
+    /// Transform `c`, appending the result to `output`.
+    ///
+    /// `lookahead` holds the `char`s which follow `c`; it's as long as
+    /// `self.lookahead()`, except at the end of a stream or before a push,
+    /// where it may be shorter. Return an error to reject the input.
+    fn transform(&mut self, c: char, lookahead: &[char], output: &mut String) -> io::Result<()>;
+}
+
+/// Where in the pipeline a `TextTransform` is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransformStage {
+    /// Apply the transform before the Text rules and normalization. In a
+    /// `TextReader`, this sees the input after UTF-8 decoding, but before
+    /// control codes and escape sequences are handled.
+    BeforeNormalization,
+
+    /// Apply the transform to the normalized output. The result is then
+    /// checked and normalized again.
+    AfterNormalization,
+}
+
+/// A sequence of `TextTransform`s, applied in the order they were added.
+pub(crate) struct Transforms {
+    stages: Vec<Stage>,
+}
+
+struct Stage {
+    /// The transform. It's only accessed through `&mut self`, using
+    /// `Mutex::get_mut`, so it's never locked; the `Mutex` is there so that
+    /// streams holding transforms can be `Sync`.
+    transform: Mutex<Box<dyn TextTransform + Send>>,
+
+    /// `char`s held back to provide lookahead.
+    pending: VecDeque<char>,
+}
+
+impl Transforms {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn push(&mut self, transform: Box<dyn TextTransform + Send>) {
+        self.stages.push(Stage {
+            transform: Mutex::new(transform),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Run `input` through each transform, appending the result to `output`.
+    /// If `end` is true, the stream is ending or being pushed, so `char`s
+    /// held back for lookahead are processed too.
+    pub(crate) fn apply(&mut self, input: &str, end: bool, output: &mut String) -> io::Result<()> {
+        let mut input = Cow::Borrowed(input);
+        for stage in &mut self.stages {
+            let mut next = String::new();
+            stage.apply(&input, end, &mut next)?;
+            input = Cow::Owned(next);
+        }
+        output.push_str(&input);
+        Ok(())
+    }
+}
+
+impl Stage {
+    fn apply(&mut self, input: &str, end: bool, output: &mut String) -> io::Result<()> {
+        self.pending.extend(input.chars());
+        let transform = self.transform.get_mut().unwrap();
+        let lookahead = transform.lookahead();
+        while self.pending.len() > lookahead || (end && !self.pending.is_empty()) {
+            let c = self.pending.pop_front().unwrap();
+            let len = lookahead.min(self.pending.len());
+            transform.transform(c, &self.pending.make_contiguous()[..len], output)?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{text_writer_impl::TextWriterImpl, Utf8Writer, WriteWrapper};
+use crate::{
+    text_writer_impl::TextWriterImpl, InputProfile, TextProfile, TextTransform, TransformStage,
+    Utf8Writer, WriteWrapper,
+};
 use io_ext::{Status, WriteExt};
 use std::{io, str};
 
@@ -7,6 +10,11 @@ use std::{io, str};
 ///
 /// `write` is not guaranteed to perform a single operation, because short
 /// writes could produce invalid UTF-8, so `write` will retry as needed.
+///
+/// `WouldBlock` from the underlying stream is handled as it is by
+/// [`Utf8Writer`].
+///
+/// [`Utf8Writer`]: crate::Utf8Writer
 pub struct TextWriter<Inner: WriteExt> {
     /// The wrapped byte stream.
     pub(crate) inner: Utf8Writer<Inner>,
@@ -25,6 +33,16 @@ impl<Inner: WriteExt> TextWriter<Inner> {
         }
     }
 
+    /// Like `new`, but applies the rules of the given version of the Text
+    /// format, rather than [`TextProfile::TextLatest`].
+    #[inline]
+    pub fn with_profile(inner: Inner, profile: TextProfile) -> Self {
+        Self {
+            inner: Utf8Writer::new(inner),
+            impl_: TextWriterImpl::with_profile(profile),
+        }
+    }
+
     /// Like `new`, but writes a U+FEFF (BOM) to the beginning of the output
     /// stream for compatibility with consumers that require that to determine
     /// the text encoding.
@@ -37,6 +55,40 @@ impl<Inner: WriteExt> TextWriter<Inner> {
         })
     }
 
+    /// Like `new`, but enables append mode, for appending to existing Text
+    /// content, such as a file opened for appending.
+    ///
+    /// NFC isn't closed under concatenation, so in append mode, wherever
+    /// the beginning of a write could combine or reorder with what precedes
+    /// it, a U+034F (COMBINING GRAPHEME JOINER) is inserted to keep the output
+    /// in NFC and Stream-Safe. This also means writes may begin with
+    /// normalization-form non-starters, rather than failing.
+    ///
+    /// The last `char` of the existing content isn't known, so a U+034F is
+    /// inserted before the first write if it begins with anything which
+    /// could compose with a preceding `char`, such as a Hangul vowel jamo.
+    /// Use [`with_append_mode_after`] to avoid this when it's known.
+    ///
+    /// [`with_append_mode_after`]: Self::with_append_mode_after
+    #[inline]
+    pub fn with_append_mode(inner: Inner) -> Self {
+        Self {
+            inner: Utf8Writer::new(inner),
+            impl_: TextWriterImpl::with_append_mode(),
+        }
+    }
+
+    /// Like `with_append_mode`, but with `last`, the last `char` of the
+    /// existing content, or `None` if it's empty, so that a U+034F is only
+    /// inserted before the first write where it's needed.
+    #[inline]
+    pub fn with_append_mode_after(inner: Inner, last: Option<char>) -> Self {
+        Self {
+            inner: Utf8Writer::new(inner),
+            impl_: TextWriterImpl::with_append_mode_after(last),
+        }
+    }
+
     /// Like `new`, but enables CRLF output mode, which translates "\n" to
     /// "\r\n" for compatibility with consumers that need that.
     ///
@@ -55,6 +107,39 @@ impl<Inner: WriteExt> TextWriter<Inner> {
         }
     }
 
+    /// Like `new`, but follows the conventions recorded in `profile`, as
+    /// returned by [`TextReader::input_profile`], so that content read from
+    /// a stream can be written back in the same form.
+    ///
+    /// If the input began with a BOM, one is written, as in
+    /// `with_bom_compatibility`, and if its line endings were all CRLF, "\n"
+    /// is translated to "\r\n", as in `with_crlf_compatibility`. Otherwise,
+    /// line endings are written as "\n".
+    ///
+    /// [`TextReader::input_profile`]: crate::TextReader::input_profile
+    #[inline]
+    pub fn with_input_profile(mut inner: Inner, profile: &InputProfile) -> io::Result<Self> {
+        let impl_ = TextWriterImpl::with_input_profile(&mut inner, profile)?;
+        Ok(Self {
+            inner: Utf8Writer::new(inner),
+            impl_,
+        })
+    }
+
+    /// Add a user-defined transform at the given stage of the pipeline.
+    /// Transforms at the same stage are applied in the order they're added.
+    ///
+    /// `char`s a transform holds back for lookahead are written when the
+    /// stream is pushed or closed.
+    #[inline]
+    pub fn add_transform<T: TextTransform + Send + 'static>(
+        &mut self,
+        stage: TransformStage,
+        transform: T,
+    ) {
+        self.impl_.add_transform(stage, Box::new(transform))
+    }
+
     /// Flush and close the underlying stream and return the underlying
     /// stream object.
     #[inline]
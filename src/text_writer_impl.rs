@@ -1,14 +1,26 @@
 use crate::{
     categorize::Categorize,
-    unicode::{is_normalization_form_starter, BOM, MAX_UTF8_SIZE, SUB},
+    input_profile::{InputProfile, LineEndings},
+    text_profile::TextProfile,
+    text_transform::{TextTransform, TransformStage, Transforms},
+    unicode::{
+        is_normalization_form_starter, is_tag_spec_character, needs_cgj, needs_cgj_after_unknown,
+        BOM, CGJ, MAX_TAG_SPEC_LEN, MAX_UTF8_SIZE, WAVING_BLACK_FLAG,
+    },
+    utf8_writer_impl::{Utf8WriterImpl, Utf8WriterInternals},
     TextReaderWriter, TextWriter, Utf8ReaderWriter, Utf8Writer, WriteWrapper,
 };
 use io_ext::{default_flush, ReadWriteExt, Status, WriteExt};
-use std::{cell::RefCell, io, mem, mem::replace, rc::Rc, str};
+use std::{
+    borrow::Cow,
+    io,
+    mem::{replace, take},
+    str,
+};
 use unicode_normalization::UnicodeNormalization;
 
 pub(crate) trait TextWriterInternals<Inner: WriteExt>: WriteExt {
-    type Utf8Inner: io::Write + WriteExt + WriteWrapper<Inner>;
+    type Utf8Inner: io::Write + WriteExt + WriteWrapper<Inner> + Utf8WriterInternals<Inner>;
     fn impl_(&mut self) -> &mut TextWriterImpl;
     fn utf8_inner(&mut self) -> &mut Self::Utf8Inner;
     fn into_utf8_inner(self) -> Self::Utf8Inner;
@@ -59,6 +71,31 @@ pub(crate) struct TextWriterImpl {
     /// At the beginning of a stream or after a push, expect a
     /// normalization-form starter.
     expect_starter: bool,
+
+    /// The version of the rules to apply.
+    profile: TextProfile,
+
+    /// When enabled, a CGJ is inserted at the beginning of a write wherever
+    /// it's needed to keep the concatenation with what precedes it in NFC
+    /// and Stream-Safe.
+    append: bool,
+
+    /// The last `char` written, if any.
+    last: Option<char>,
+
+    /// In append mode, true if nothing has been written yet and the last
+    /// `char` of the content being appended to isn't known.
+    after_unknown: bool,
+
+    /// A possible start of an emoji tag sequence at the end of the last
+    /// write, held until the next write, a flush, or close.
+    tag_sequence: String,
+
+    /// User transforms applied to the input.
+    before: Transforms,
+
+    /// User transforms applied to the normalized input.
+    after: Transforms,
 }
 
 impl TextWriterImpl {
@@ -70,9 +107,45 @@ impl TextWriterImpl {
             nl: NlGuard(true),
             crlf_compatibility: false,
             expect_starter: true,
+            profile: TextProfile::default(),
+            append: false,
+            last: None,
+            after_unknown: false,
+            tag_sequence: String::new(),
+            before: Transforms::new(),
+            after: Transforms::new(),
         }
     }
 
+    /// Like `new`, but applies the rules of the given version of the Text
+    /// format.
+    #[inline]
+    pub(crate) fn with_profile(profile: TextProfile) -> Self {
+        let mut impl_ = Self::new();
+        impl_.profile = profile;
+        impl_
+    }
+
+    /// Like `new`, but enables append mode, in which output may be appended
+    /// to existing Text content, and writes may begin with non-starters.
+    #[inline]
+    pub(crate) fn with_append_mode() -> Self {
+        let mut impl_ = Self::new();
+        impl_.append = true;
+        impl_.after_unknown = true;
+        impl_
+    }
+
+    /// Like `with_append_mode`, but with the last `char` of the existing
+    /// content, or `None` if it's empty.
+    #[inline]
+    pub(crate) fn with_append_mode_after(last: Option<char>) -> Self {
+        let mut impl_ = Self::new();
+        impl_.append = true;
+        impl_.last = last;
+        impl_
+    }
+
     /// Like `new`, but enables CRLF output mode, which translates "\n" to
     /// "\r\n" for compatibility with consumers that need that.
     ///
@@ -85,11 +158,36 @@ impl TextWriterImpl {
     /// [RFC-5198]: https://tools.ietf.org/html/rfc5198#appendix-C
     #[inline]
     pub(crate) fn with_crlf_compatibility() -> Self {
-        Self {
-            buffer: String::new(),
-            nl: NlGuard(true),
-            crlf_compatibility: true,
-            expect_starter: true,
+        let mut impl_ = Self::new();
+        impl_.crlf_compatibility = true;
+        impl_
+    }
+
+    /// Like `new`, but follows the BOM and line-ending conventions recorded
+    /// in `profile`.
+    #[inline]
+    pub(crate) fn with_input_profile<Inner: WriteExt>(
+        internals: &mut Inner,
+        profile: &InputProfile,
+    ) -> io::Result<Self> {
+        let mut impl_ = if profile.bom {
+            Self::with_bom_compatibility(internals)?
+        } else {
+            Self::new()
+        };
+        impl_.crlf_compatibility = profile.line_endings == LineEndings::Crlf;
+        Ok(impl_)
+    }
+
+    /// Add a user transform at the given stage of the pipeline.
+    pub(crate) fn add_transform(
+        &mut self,
+        stage: TransformStage,
+        transform: Box<dyn TextTransform + Send>,
+    ) {
+        match stage {
+            TransformStage::BeforeNormalization => self.before.push(transform),
+            TransformStage::AfterNormalization => self.after.push(transform),
         }
     }
 
@@ -113,6 +211,8 @@ impl TextWriterImpl {
     pub(crate) fn close_into_inner<Inner: WriteExt>(
         mut internals: impl TextWriterInternals<Inner>,
     ) -> io::Result<Inner> {
+        Self::finish_tag_sequence(&mut internals)?;
+        Self::finish_transforms(&mut internals)?;
         Self::check_nl(&mut internals, Status::End)?;
         internals.into_utf8_inner().close_into_inner()
     }
@@ -125,63 +225,49 @@ impl TextWriterImpl {
         internals.into_utf8_inner().abandon_into_inner()
     }
 
-    fn normal_write_str<Inner: WriteExt>(
+    /// Write out any held start of an emoji tag sequence, which fails if
+    /// it's incomplete.
+    fn finish_tag_sequence<Inner: WriteExt>(
         internals: &mut impl TextWriterInternals<Inner>,
-        s: &str,
     ) -> io::Result<()> {
-        let error = Rc::new(RefCell::new(None));
-        for c in Categorize::new(s.chars(), Rc::clone(&error))
-            .svar()
-            .stream_safe()
-            .nfc()
-        {
-            // SUB indicates an error sent through the NFC iterator chain, and
-            // the Rc<RefCell<Option<io::Error>>> holds the actual error.
-            if c == SUB {
-                return Err(mem::replace(&mut *error.borrow_mut(), None).unwrap());
-            }
-            internals.impl_().buffer.push(c);
+        let impl_ = internals.impl_();
+        if impl_.tag_sequence.is_empty() {
+            return Ok(());
         }
-
-        // Write to the underlying stream.
+        let tag_sequence = take(&mut impl_.tag_sequence);
+        impl_.push_text(&tag_sequence, false)?;
         Self::write_buffer(internals)
     }
 
-    fn crlf_write_str<Inner: WriteExt>(
+    /// Write out any `char`s held back by user transforms.
+    fn finish_transforms<Inner: WriteExt>(
         internals: &mut impl TextWriterInternals<Inner>,
-        s: &str,
     ) -> io::Result<()> {
-        // Translate "\n" into "\r\n".
-        let mut first = true;
-        let error = Rc::new(RefCell::new(None));
-        for slice in s.split('\n') {
-            if first {
-                first = false;
-            } else {
-                internals.impl_().buffer.push_str("\r\n");
-            }
-
-            for c in Categorize::new(slice.chars(), Rc::clone(&error))
-                .svar()
-                .stream_safe()
-                .nfc()
-            {
-                // SUB indicates an error sent through the NFC iterator chain, and
-                // the Rc<RefCell<Option<io::Error>>> holds the actual error.
-                if c == SUB {
-                    return Err(mem::replace(&mut *error.borrow_mut(), None).unwrap());
-                }
-                internals.impl_().buffer.push(c);
-            }
+        let impl_ = internals.impl_();
+        if impl_.before.is_empty() && impl_.after.is_empty() {
+            return Ok(());
         }
-
-        // Write to the underlying stream.
+        impl_.push_text("", true)?;
         Self::write_buffer(internals)
     }
 
     fn write_buffer<Inner: WriteExt>(
         internals: &mut impl TextWriterInternals<Inner>,
     ) -> io::Result<()> {
+        if internals.impl_().append {
+            let impl_ = internals.impl_();
+            if let Some(c) = impl_.buffer.chars().next() {
+                let needs = if impl_.after_unknown {
+                    needs_cgj_after_unknown(c)
+                } else {
+                    needs_cgj(impl_.last, c)
+                };
+                if needs {
+                    impl_.buffer.insert(0, CGJ);
+                }
+            }
+        }
+
         if internals.impl_().expect_starter {
             internals.impl_().expect_starter = false;
             if let Some(c) = internals.impl_().buffer.chars().next() {
@@ -205,8 +291,10 @@ impl TextWriterImpl {
         }
         internals.impl_().buffer = buffer;
 
-        if let Some(last) = internals.impl_().buffer.as_bytes().last().copied() {
-            Self::newline(internals, last == b'\n');
+        if let Some(last) = internals.impl_().buffer.chars().next_back() {
+            internals.impl_().last = Some(last);
+            internals.impl_().after_unknown = false;
+            Self::newline(internals, last == '\n');
         }
 
         // Reset the temporary buffer.
@@ -238,7 +326,13 @@ impl TextWriterImpl {
         internals: &mut impl TextWriterInternals<Inner>,
         status: Status,
     ) -> io::Result<()> {
+        // If the underlying stream can't accept output held from an earlier
+        // write, nothing has changed yet, so this can be retried.
+        Utf8WriterImpl::write_pending(internals.utf8_inner())?;
+
+        Self::finish_tag_sequence(internals)?;
         if status != Status::active() {
+            Self::finish_transforms(internals)?;
             internals.impl_().expect_starter = true;
         }
         Self::check_nl(internals, status)?;
@@ -247,6 +341,7 @@ impl TextWriterImpl {
 
     pub(crate) fn abandon<Inner: WriteExt>(internals: &mut impl TextWriterInternals<Inner>) {
         internals.utf8_inner().abandon();
+        internals.impl_().tag_sequence.clear();
 
         // Don't enforce a trailing newline.
         internals.impl_().nl.0 = true;
@@ -256,11 +351,17 @@ impl TextWriterImpl {
         internals: &mut impl TextWriterInternals<Inner>,
         s: &str,
     ) -> io::Result<()> {
-        if internals.impl_().crlf_compatibility {
-            Self::crlf_write_str(internals, s)
-        } else {
-            Self::normal_write_str(internals, s)
-        }
+        // If the underlying stream can't accept output held from an earlier
+        // write, fail before accepting `s`, so that the write can be retried.
+        Utf8WriterImpl::write_pending(internals.utf8_inner())?;
+
+        let impl_ = internals.impl_();
+        let (text, tag_sequence) = impl_.split_tag_sequence(s);
+        impl_.push_text(&text, false)?;
+        impl_.tag_sequence = tag_sequence;
+
+        // Write to the underlying stream.
+        Self::write_buffer(internals)
     }
 
     pub(crate) fn write<Inner: WriteExt>(
@@ -296,6 +397,89 @@ impl TextWriterImpl {
     }
 }
 
+impl TextWriterImpl {
+    /// Prepend any held start of an emoji tag sequence to `s`, and split off
+    /// any possible start of one at the end, so that a sequence split
+    /// across writes is checked as a whole.
+    fn split_tag_sequence<'a>(&self, s: &'a str) -> (Cow<'a, str>, String) {
+        if !self.profile.permits_emoji_tag_sequences() {
+            return (Cow::Borrowed(s), String::new());
+        }
+
+        let text = if self.tag_sequence.is_empty() {
+            Cow::Borrowed(s)
+        } else {
+            Cow::Owned(self.tag_sequence.clone() + s)
+        };
+        let start = match text.rfind(WAVING_BLACK_FLAG) {
+            Some(start) => start,
+            None => return (text, String::new()),
+        };
+        let mut tags = text[start + WAVING_BLACK_FLAG.len_utf8()..].chars();
+        if tags.clone().count() > MAX_TAG_SPEC_LEN || !tags.all(is_tag_spec_character) {
+            return (text, String::new());
+        }
+
+        let tag_sequence = text[start..].to_owned();
+        let text = match text {
+            Cow::Borrowed(text) => Cow::Borrowed(&text[..start]),
+            Cow::Owned(mut text) => {
+                text.truncate(start);
+                Cow::Owned(text)
+            }
+        };
+        (text, tag_sequence)
+    }
+
+    /// Check, normalize, and transform `s`, and append the result to the
+    /// buffer. On failure, nothing is appended. If `end` is true, `char`s
+    /// held back by transforms are processed too.
+    fn push_text(&mut self, s: &str, end: bool) -> io::Result<()> {
+        let start = self.buffer.len();
+        let result = self.transform_and_normalize(s, end);
+        if result.is_err() {
+            self.buffer.truncate(start);
+        } else if self.crlf_compatibility {
+            // Translate "\n" into "\r\n".
+            let text = self.buffer.split_off(start);
+            self.buffer.push_str(&text.replace('\n', "\r\n"));
+        }
+        result
+    }
+
+    fn transform_and_normalize(&mut self, s: &str, end: bool) -> io::Result<()> {
+        if self.before.is_empty() && self.after.is_empty() {
+            return normalize(s, self.profile, &mut self.buffer);
+        }
+
+        let mut transformed = String::new();
+        self.before.apply(s, end, &mut transformed)?;
+        if self.after.is_empty() {
+            return normalize(&transformed, self.profile, &mut self.buffer);
+        }
+
+        let mut normalized = String::new();
+        normalize(&transformed, self.profile, &mut normalized)?;
+        transformed.clear();
+        self.after.apply(&normalized, end, &mut transformed)?;
+        normalize(&transformed, self.profile, &mut self.buffer)
+    }
+}
+
+/// Check `s` against the rules for Text output, and append its normalized
+/// form to `output`.
+fn normalize(s: &str, profile: TextProfile, output: &mut String) -> io::Result<()> {
+    let mut error = None;
+    let chars =
+        Categorize::new(s.chars(), profile).map_while(|c| c.map_err(|err| error = Some(err)).ok());
+    output.extend(chars.svar().stream_safe().nfc());
+
+    match error {
+        Some(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+        None => Ok(()),
+    }
+}
+
 struct NlGuard(bool);
 
 impl Drop for NlGuard {
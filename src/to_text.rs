@@ -0,0 +1,45 @@
+//! Defines `to_text` and `is_text`, for sanitizing data which is already in
+//! memory without setting up a `TextReader` in the common case where it's
+//! already valid.
+
+use crate::{validate::first_violation, TextProfile, TextReader};
+use io_ext::SliceReader;
+use std::{borrow::Cow, io::Read, str};
+
+/// Translate `bytes` into valid Text, applying the same rules as
+/// `TextReader`. If `bytes` is already valid Text, it's borrowed rather than
+/// copied.
+pub fn to_text(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(s) = str::from_utf8(bytes) {
+        if is_text(s) {
+            return Cow::Borrowed(s);
+        }
+    }
+
+    let mut reader = TextReader::new(SliceReader::new(bytes));
+    let mut s = String::new();
+    reader
+        .read_to_string(&mut s)
+        .expect("reading from a slice can't fail");
+    Cow::Owned(s)
+}
+
+/// Test whether `s` is valid Text, such that `TextReader` would pass it
+/// through unmodified.
+pub fn is_text(s: &str) -> bool {
+    is_plain_ascii_text(s.as_bytes()) || first_violation(s, TextProfile::default()).is_none()
+}
+
+/// A fast path for the common case: printable ASCII, tabs, and newlines are
+/// always valid, as long as the stream ends with a newline.
+pub(crate) fn is_plain_ascii_text(bytes: &[u8]) -> bool {
+    matches!(bytes.last(), None | Some(b'\n')) && is_plain_ascii(bytes)
+}
+
+/// Test whether `bytes` consists only of printable ASCII, tabs, and
+/// newlines.
+pub(crate) fn is_plain_ascii(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .all(|b| matches!(*b, b' '..=b'~' | b'\t' | b'\n'))
+}
@@ -0,0 +1,98 @@
+//! Defines `to_text_parallel`, for sanitizing large inputs which are already
+//! in memory, such as memory-mapped files, using multiple threads. Enabled
+//! with the `rayon` feature.
+
+use crate::{text_reader_impl::TextReaderImpl, Utf8Reader};
+use io_ext::SliceReader;
+use rayon::prelude::*;
+use std::{io::Read, ops::Range};
+
+/// The default size of the chunks `to_text_parallel` splits its input into.
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Translate `bytes` into valid Text, like [`to_text`], splitting the work
+/// across rayon's thread pool. The output is exactly the same as that of
+/// `TextReader`.
+///
+/// The input is split into chunks after newlines, which normalization
+/// doesn't reach across. Each chunk is translated assuming the newline
+/// before it ended a line; when that isn't so, for example when the newline
+/// is inside an escape sequence, the chunks on either side of it are
+/// translated together instead.
+///
+/// [`to_text`]: crate::to_text
+pub fn to_text_parallel(bytes: &[u8]) -> String {
+    to_text_parallel_with_chunk_size(bytes, DEFAULT_CHUNK_SIZE)
+}
+
+/// Like [`to_text_parallel`], but splits the input into chunks of about
+/// `chunk_size` bytes, extended to the next newline.
+pub fn to_text_parallel_with_chunk_size(bytes: &[u8], chunk_size: usize) -> String {
+    let chunks = split(bytes, chunk_size.max(1));
+    let last = chunks.len() - 1;
+    let translated = chunks
+        .par_iter()
+        .enumerate()
+        .map(|(index, chunk)| translate(&bytes[chunk.clone()], index == 0, index == last))
+        .collect::<Vec<_>>();
+
+    let mut output = String::with_capacity(bytes.len());
+    let mut index = 0;
+    while index <= last {
+        if let Some(s) = &translated[index] {
+            output.push_str(s);
+            index += 1;
+            continue;
+        }
+
+        // The chunk's final newline didn't end a line, so the chunks after
+        // it didn't start where they assumed. Translate it together with
+        // them, taking twice as many each time it still doesn't end a line.
+        let start = chunks[index].start;
+        let mut width = 1;
+        loop {
+            let end = (index + width).min(last);
+            if let Some(s) = translate(&bytes[start..chunks[end].end], index == 0, end == last) {
+                output.push_str(&s);
+                index = end + 1;
+                break;
+            }
+            width *= 2;
+        }
+    }
+    output
+}
+
+/// Split `bytes` into ranges of about `chunk_size` bytes, each ending just
+/// after a newline, except for the last one. There's always at least one.
+fn split(bytes: &[u8], chunk_size: usize) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while bytes.len() - start > chunk_size {
+        match bytes[start + chunk_size..].iter().position(|b| *b == b'\n') {
+            Some(newline) => {
+                let end = start + chunk_size + newline + 1;
+                chunks.push(start..end);
+                start = end;
+            }
+            None => break,
+        }
+    }
+    chunks.push(start..bytes.len());
+    chunks
+}
+
+/// Translate one chunk. See `TextReaderImpl::translate_chunk`.
+fn translate(bytes: &[u8], first: bool, last: bool) -> Option<String> {
+    let mut s = String::new();
+    Utf8Reader::new(SliceReader::new(bytes))
+        .read_to_string(&mut s)
+        .expect("reading from a slice can't fail");
+
+    let mut output = String::with_capacity(s.len());
+    if TextReaderImpl::translate_chunk(s, first, last, &mut output) {
+        Some(output)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,213 @@
+//! Defines `Transcript`, which renders terminal output the way a terminal
+//! would display it, for `TextReader::with_transcript`.
+
+use crate::{
+    replacement_observer::TerminalEvent,
+    unicode::{is_normalization_form_starter, DEL, ESC},
+};
+use std::mem;
+
+/// ASCII BS, which moves the cursor back one column.
+const BS: char = '\u{8}';
+
+/// ASCII BEL, which terminates OSC sequences.
+const BEL: char = '\u{7}';
+
+/// The width of the emulated terminal. Cursor movement stops at its last
+/// column, or at the end of a line which is already longer, so that a
+/// sequence with a huge count can't make a line huge.
+const COLUMNS: usize = 4096;
+
+/// Emulates a single line of a terminal, producing the final visible text
+/// of each line.
+pub(crate) struct Transcript {
+    /// The cells of the current line, each holding a char and any combining
+    /// marks which followed it.
+    cells: Vec<String>,
+
+    /// The column of the cursor, which may be past the end of `cells`.
+    cursor: usize,
+
+    /// Escape-sequence state machine.
+    state: State,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum State {
+    // Default state.
+    Ground,
+
+    // After a '\x1b'.
+    Esc,
+
+    // Within a sequence started by "\x1b[", with the parameter bytes so far.
+    Csi(String),
+
+    // Within a sequence started by "\x1b]".
+    Osc,
+
+    // After a '\x1b' within an OSC sequence.
+    OscEsc,
+}
+
+impl Transcript {
+    pub(crate) fn new() -> Self {
+        Self {
+            cells: Vec::new(),
+            cursor: 0,
+            state: State::Ground,
+        }
+    }
+
+    /// Render the terminal output in `input`, appending each line which is
+    /// ended to `output`. If `end` is true, the input has ended, and a
+    /// partial line is appended too. What's discarded without being
+    /// interpreted is recorded in `events`, with its position in `input`.
+    pub(crate) fn apply(
+        &mut self,
+        input: &str,
+        end: bool,
+        output: &mut String,
+        events: &mut Vec<(usize, TerminalEvent)>,
+    ) {
+        for (index, c) in input.char_indices() {
+            self.char(c, output, &mut |event| events.push((index, event)));
+        }
+        if end {
+            output.extend(self.cells.drain(..));
+            self.cursor = 0;
+            if mem::replace(&mut self.state, State::Ground) != State::Ground {
+                events.push((input.len(), TerminalEvent::IgnoredSequence));
+            }
+        }
+    }
+
+    fn char(&mut self, c: char, output: &mut String, event: &mut dyn FnMut(TerminalEvent)) {
+        match mem::replace(&mut self.state, State::Ground) {
+            State::Ground => {}
+            State::Esc => {
+                match c {
+                    '[' => self.state = State::Csi(String::new()),
+                    ']' => self.state = State::Osc,
+                    // Ignore other sequences, such as character set
+                    // selection.
+                    c if !c.is_control() => event(TerminalEvent::IgnoredSequence),
+                    c => {
+                        event(TerminalEvent::IgnoredSequence);
+                        self.char(c, output, event)
+                    }
+                }
+                return;
+            }
+            State::Csi(mut params) => {
+                match c {
+                    '\u{20}'..='\u{3f}' => {
+                        params.push(c);
+                        self.state = State::Csi(params);
+                    }
+                    '\u{40}'..='\u{7e}' => {
+                        if !self.csi(&params, c) {
+                            event(TerminalEvent::IgnoredSequence)
+                        }
+                    }
+                    // An interrupted sequence is ignored.
+                    c => {
+                        event(TerminalEvent::IgnoredSequence);
+                        self.char(c, output, event)
+                    }
+                }
+                return;
+            }
+            State::Osc => {
+                match c {
+                    BEL => event(TerminalEvent::IgnoredSequence),
+                    ESC => self.state = State::OscEsc,
+                    _ => self.state = State::Osc,
+                }
+                return;
+            }
+            State::OscEsc => {
+                if c == '\\' {
+                    event(TerminalEvent::IgnoredSequence)
+                } else {
+                    self.state = State::Osc;
+                }
+                return;
+            }
+        }
+
+        match c {
+            '\n' => {
+                output.extend(self.cells.drain(..));
+                output.push('\n');
+                self.cursor = 0;
+            }
+            '\r' => self.cursor = 0,
+            BS => self.cursor = self.cursor.min(self.cells.len()).saturating_sub(1),
+            ESC => {
+                event(TerminalEvent::Escape);
+                self.state = State::Esc
+            }
+            '\t' => self.put('\t'),
+            c if c <= '\u{1f}' || c == DEL => event(TerminalEvent::IgnoredControl(c)),
+            c if !is_normalization_form_starter(c) && self.cursor != 0 => {
+                // Combining marks join the preceding cell, which is blank if
+                // the cursor is past the end of the row.
+                if self.cells.len() < self.cursor {
+                    self.cells.resize(self.cursor, " ".to_owned());
+                }
+                self.cells[self.cursor - 1].push(c);
+            }
+            c => self.put(c),
+        }
+    }
+
+    /// Write `c` at the cursor and advance it.
+    fn put(&mut self, c: char) {
+        if self.cursor < self.cells.len() {
+            let cell = &mut self.cells[self.cursor];
+            // Overstriking with '_' underlines, as in nroff output, so the
+            // visible char is the one underneath.
+            if c != '_' || cell.starts_with(' ') {
+                cell.clear();
+                cell.push(c);
+            }
+        } else {
+            self.cells.resize(self.cursor, " ".to_owned());
+            self.cells.push(c.to_string());
+        }
+        self.cursor += 1;
+    }
+
+    /// Move the cursor to `column`, or as far towards it as it can go.
+    fn move_to(&mut self, column: usize) {
+        self.cursor = column.min(self.cells.len().max(COLUMNS - 1));
+    }
+
+    /// Handle a CSI sequence with parameters `params` and final byte
+    /// `final_`, and return false if it's ignored.
+    fn csi(&mut self, params: &str, final_: char) -> bool {
+        let first = params.split(';').next().unwrap_or("");
+        let first = first.parse::<usize>().ok();
+        let count = first.unwrap_or(1).max(1);
+        match final_ {
+            'C' => self.move_to(self.cursor.saturating_add(count)),
+            'D' => self.cursor = self.cursor.min(self.cells.len()).saturating_sub(count),
+            'G' => self.move_to(count - 1),
+            'K' => match first.unwrap_or(0) {
+                0 => self.cells.truncate(self.cursor),
+                1 => {
+                    let end = self.cells.len().min(self.cursor + 1);
+                    for cell in &mut self.cells[..end] {
+                        *cell = " ".to_owned();
+                    }
+                }
+                2 => self.cells.clear(),
+                _ => return false,
+            },
+            // Ignore other sequences, such as colors and vertical movement.
+            _ => return false,
+        }
+        true
+    }
+}
@@ -1,4 +1,9 @@
-use unicode_normalization::char::canonical_combining_class;
+use crate::tables::{ASSIGNED, DISALLOWED};
+use std::{cmp::Ordering, iter};
+use unicode_normalization::{
+    char::{canonical_combining_class, compose},
+    is_nfc_quick, IsNormalized,
+};
 
 /// The size of the longest UTF-8 scalar value encoding. Note that even though
 /// RFC-2279 allowed longer encodings, it's obsoleted by RFC-3629 which doesn't.
@@ -27,9 +32,6 @@ pub(crate) const FF: char = '\u{c}';
 /// ASCII ESC, known as '\e' in some contexts.
 pub(crate) const ESC: char = '\u{1b}';
 
-/// ASCII SUB.
-pub(crate) const SUB: char = '\u{1a}';
-
 /// ASCII DEL, which is not what's generated by the "delete" key on the keyboard
 pub(crate) const DEL: char = '\u{7f}';
 
@@ -45,7 +47,93 @@ pub(crate) const WJ: char = '\u{2060}';
 /// REPLACEMENT CHARACTER
 pub(crate) const REPL: char = '\u{fffd}';
 
+/// WAVING BLACK FLAG, the base of the RGI emoji tag sequences
+pub(crate) const WAVING_BLACK_FLAG: char = '\u{1f3f4}';
+
+/// CANCEL TAG, which ends an emoji tag sequence
+pub(crate) const CANCEL_TAG: char = '\u{e007f}';
+
+/// The longest tag spec in an emoji tag sequence. Subdivision ids are a
+/// region subtag of up to 3 characters followed by a suffix of up to 4.
+pub(crate) const MAX_TAG_SPEC_LEN: usize = 7;
+
+/// Test whether `c` is a tag character which may appear in the tag spec of
+/// an emoji tag sequence: TAG DIGIT ZERO through TAG DIGIT NINE and TAG LATIN
+/// SMALL LETTER A through TAG LATIN SMALL LETTER Z.
+pub(crate) fn is_tag_spec_character(c: char) -> bool {
+    matches!(c, '\u{e0030}'..='\u{e0039}' | '\u{e0061}'..='\u{e007a}')
+}
+
+/// The categories of *disallowed codepoints*, other than control codes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Disallowed {
+    /// Deprecated characters, other than Deprecated Format Characters.
+    Deprecated,
+    /// Deprecated characters which are also Default Ignorable.
+    DeprecatedFormat,
+    /// Tag Characters.
+    Tag,
+    /// Noncharacters.
+    Noncharacter,
+    /// Private-Use Characters.
+    PrivateUse,
+}
+
+/// Test whether `c` is a *disallowed codepoint*, other than a control code,
+/// and if so, which kind.
+pub(crate) fn disallowed(c: char) -> Option<Disallowed> {
+    DISALLOWED
+        .binary_search_by(|&(lo, hi, _)| compare_range(lo, hi, c))
+        .ok()
+        .map(|index| DISALLOWED[index].2)
+}
+
+/// Test whether `c` is assigned in [`UNICODE_VERSION`]. Noncharacters are
+/// not considered assigned here; they're handled separately.
+///
+/// [`UNICODE_VERSION`]: crate::UNICODE_VERSION
+pub(crate) fn is_assigned(c: char) -> bool {
+    ASSIGNED
+        .binary_search_by(|&(lo, hi)| compare_range(lo, hi, c))
+        .is_ok()
+}
+
+fn compare_range(lo: char, hi: char, c: char) -> Ordering {
+    if hi < c {
+        Ordering::Less
+    } else if lo > c {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
 // TODO: include ZWJ, WJ, ZWNJ, CGJ as non-starters?
 pub(crate) fn is_normalization_form_starter(c: char) -> bool {
     canonical_combining_class(c) == 0
 }
+
+/// Test whether `c` begins a segment which can be normalized independently
+/// of what precedes it: it's a starter which can't compose with anything
+/// before it. Tag characters are excluded so that emoji tag sequences stay
+/// within one segment.
+pub(crate) fn is_segment_start(c: char) -> bool {
+    is_normalization_form_starter(c)
+        && is_nfc_quick(iter::once(c)) == IsNormalized::Yes
+        && !is_tag_spec_character(c)
+        && c != CANCEL_TAG
+}
+
+/// Test whether a U+034F (COMBINING GRAPHEME JOINER) is needed between
+/// `prev`, the last codepoint of some normalized text, or `None` at the
+/// beginning of a stream, and `next`, the first codepoint of some normalized
+/// text which follows it, to keep the concatenation in NFC and Stream-Safe.
+pub(crate) fn needs_cgj(prev: Option<char>, next: char) -> bool {
+    !is_normalization_form_starter(next) || prev.and_then(|prev| compose(prev, next)).is_some()
+}
+
+/// Like `needs_cgj`, but for when what precedes `next` isn't known, so a
+/// U+034F is needed if `next` could compose with anything before it.
+pub(crate) fn needs_cgj_after_unknown(next: char) -> bool {
+    !is_normalization_form_starter(next) || is_nfc_quick(iter::once(next)) != IsNormalized::Yes
+}
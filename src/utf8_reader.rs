@@ -1,4 +1,4 @@
-use crate::utf8_reader_impl::Utf8ReaderImpl;
+use crate::{utf8_reader_impl::Utf8ReaderImpl, ReplacementCounts, ReplacementObserver};
 use io_ext::{ReadExt, Status};
 use std::{io, str};
 
@@ -18,6 +18,13 @@ pub trait ReadStr: ReadExt {
 /// sequences replaced by U+FFFD (REPLACEMENT CHARACTER) in the manner of
 /// `String::from_utf8_lossy`, where scalar value encodings never straddle `read`
 /// calls (callers can do `str::from_utf8` and it will always succeed).
+///
+/// `Utf8Reader` also implements `BufRead`, returning the bytes it reads from
+/// the underlying stream in place, with any invalid sequences replaced, so
+/// there's no need to wrap it in a `BufReader`. The buffers returned by
+/// `fill_buf` likewise always end on a scalar value boundary. If the
+/// underlying stream has no data available yet, `fill_buf` returns an empty
+/// buffer rather than waiting.
 pub struct Utf8Reader<Inner: ReadExt> {
     /// The wrapped byte stream.
     pub(crate) inner: Inner,
@@ -34,6 +41,33 @@ impl<Inner: ReadExt> Utf8Reader<Inner> {
             impl_: Utf8ReaderImpl::new(),
         }
     }
+
+    /// Set an observer to be called for each invalid UTF-8 sequence replaced
+    /// by U+FFFD (REPLACEMENT CHARACTER), with its offset in the input.
+    ///
+    /// The observer should be set before reading begins.
+    #[inline]
+    pub fn set_replacement_observer<O: ReplacementObserver + Send + 'static>(
+        &mut self,
+        observer: O,
+    ) {
+        self.impl_.set_replacement_observer(Box::new(observer))
+    }
+
+    /// Return the number of replacements made while an observer was set.
+    #[inline]
+    pub fn replacement_counts(&self) -> ReplacementCounts {
+        self.impl_.replacement_counts()
+    }
+
+    /// Enable or disable follow mode, for reading a file which is still
+    /// being written, as `tail -f` does. See [`TextReader::set_follow`].
+    ///
+    /// [`TextReader::set_follow`]: crate::TextReader::set_follow
+    #[inline]
+    pub fn set_follow(&mut self, follow: bool) {
+        self.impl_.set_follow(follow)
+    }
 }
 
 impl<Inner: ReadExt> ReadExt for Utf8Reader<Inner> {
@@ -88,6 +122,18 @@ impl<Inner: ReadExt> io::Read for Utf8Reader<Inner> {
     }
 }
 
+impl<Inner: ReadExt> io::BufRead for Utf8Reader<Inner> {
+    #[inline]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Utf8ReaderImpl::fill_buf(self)
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        Utf8ReaderImpl::consume(self, amt)
+    }
+}
+
 #[cfg(test)]
 fn translate_via_std_reader(bytes: &[u8]) -> String {
     use std::io::Read;
@@ -121,11 +167,29 @@ fn translate_with_small_buffer(bytes: &[u8]) -> String {
     String::from_utf8(v).unwrap()
 }
 
+#[cfg(test)]
+fn translate_via_fill_buf(bytes: &[u8]) -> String {
+    use std::io::BufRead;
+    let mut reader = Utf8Reader::new(io_ext::SliceReader::new(bytes));
+    let mut s = String::new();
+    loop {
+        let buf = reader.fill_buf().unwrap();
+        if buf.is_empty() {
+            break;
+        }
+        s.push_str(str::from_utf8(buf).unwrap());
+        let len = buf.len();
+        reader.consume(len);
+    }
+    s
+}
+
 #[cfg(test)]
 fn test(bytes: &[u8], s: &str) {
     assert_eq!(translate_via_std_reader(bytes), s);
     assert_eq!(translate_via_slice_reader(bytes), s);
     assert_eq!(translate_with_small_buffer(bytes), s);
+    assert_eq!(translate_via_fill_buf(bytes), s);
 
     for i in 1..4 {
         let mut v = vec![0u8; i + bytes.len()];
@@ -165,6 +229,50 @@ fn test_invalid_bytes() {
     test(b"\xff\xff\xff", "���");
 }
 
+#[test]
+fn test_buf_read() {
+    use std::io::{BufRead, Read};
+    let mut reader = Utf8Reader::new(io_ext::SliceReader::new(b"hello\xffworld\nline two\n"));
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "hello\u{fffd}world\n");
+
+    // Mix buffered and unbuffered reads.
+    reader.consume(1);
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "ine two\n");
+
+    let reader = Utf8Reader::new(io_ext::SliceReader::new(b"a\xef\xbf\xbdb\xffc"));
+    let parts = reader
+        .split(b'b')
+        .map(|part| String::from_utf8(part.unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(parts, ["a\u{fffd}", "\u{fffd}c"]);
+}
+
+#[test]
+fn test_buf_read_no_data_yet() {
+    use crate::test_streams::Trickle;
+    use std::io::BufRead;
+    let mut reader = Utf8Reader::new(Trickle(b"h\xc3\xa9\xff\n"));
+    let mut output = Vec::new();
+    loop {
+        let buf = reader.fill_buf().unwrap();
+        if buf.is_empty() {
+            break;
+        }
+        output.extend_from_slice(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    assert_eq!(output, "h\u{e9}\u{fffd}\n".as_bytes());
+
+    // With no data available, `fill_buf` returns an empty buffer rather
+    // than waiting.
+    assert!(reader.fill_buf().unwrap().is_empty());
+}
+
 #[test]
 fn test_some_ascii_printable() {
     test(
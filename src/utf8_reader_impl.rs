@@ -1,9 +1,14 @@
-use crate::{unicode::REPL, Utf8Reader, Utf8ReaderWriter};
+use crate::{
+    read_buffer::READ_BUFFER_SIZE,
+    replacement_observer::{ReplacementCounts, ReplacementObserver, Utf8Observing},
+    unicode::REPL,
+    Utf8Reader, Utf8ReaderWriter,
+};
 use io_ext::{
     default_read, default_read_exact, default_read_to_end, default_read_to_string,
     default_read_vectored, ReadExt, ReadWriteExt, Status,
 };
-use std::{cmp::min, io, str};
+use std::{cmp::min, collections::VecDeque, io, mem, str};
 
 pub(crate) trait Utf8ReaderInternals<Inner: ReadExt>: ReadExt {
     fn impl_(&mut self) -> &mut Utf8ReaderImpl;
@@ -32,8 +37,41 @@ impl<Inner: ReadWriteExt> Utf8ReaderInternals<Inner> for Utf8ReaderWriter<Inner>
 
 pub(crate) struct Utf8ReaderImpl {
     /// A queue of bytes which have not been read but which have not been
-    /// translated into the output yet.
+    /// translated into the output yet. `fill_buf` reads into this directly
+    /// and translates it in place, so its front may hold output.
     overflow: Vec<u8>,
+
+    /// The end of the output `fill_buf` has translated at the front of
+    /// `overflow`.
+    translated: usize,
+
+    /// The number of bytes of translated output which have been consumed.
+    consumed: usize,
+
+    /// The status to report once the translated output has been consumed.
+    translated_status: Status,
+
+    /// True if the last read from the underlying stream produced nothing
+    /// without reaching the end, so there's no point retrying it now.
+    stalled: bool,
+
+    /// Whether any invalid UTF-8 has been replaced.
+    saw_invalid: bool,
+
+    /// The positions in the output of the current read of invalid UTF-8
+    /// sequences which were replaced, paired with their original bytes.
+    invalid: VecDeque<(usize, Vec<u8>)>,
+
+    /// When enabled, `invalid` is kept after a read for a `TextReader` to
+    /// take.
+    record_invalid: bool,
+
+    /// The replacement observer, if one is set.
+    observing: Option<Box<Utf8Observing>>,
+
+    /// When enabled, the end of the underlying stream is treated as the
+    /// current end of a stream which may grow, rather than as final.
+    follow: bool,
 }
 
 impl Utf8ReaderImpl {
@@ -42,9 +80,70 @@ impl Utf8ReaderImpl {
     pub(crate) fn new() -> Self {
         Self {
             overflow: Vec::new(),
+            translated: 0,
+            consumed: 0,
+            translated_status: Status::active(),
+            stalled: false,
+            saw_invalid: false,
+            invalid: VecDeque::new(),
+            record_invalid: false,
+            observing: None,
+            follow: false,
         }
     }
 
+    /// Set the observer to report replacements to.
+    #[inline]
+    pub(crate) fn set_replacement_observer(
+        &mut self,
+        observer: Box<dyn ReplacementObserver + Send>,
+    ) {
+        self.observing = Some(Box::new(Utf8Observing::new(observer)));
+    }
+
+    /// The counts of replacements made while an observer was set.
+    #[inline]
+    pub(crate) fn replacement_counts(&self) -> ReplacementCounts {
+        self.observing
+            .as_ref()
+            .map(|observing| observing.counts().clone())
+            .unwrap_or_default()
+    }
+
+    /// Keep the positions of replaced invalid UTF-8 sequences, for
+    /// `take_invalid`.
+    #[inline]
+    pub(crate) fn record_invalid(&mut self) {
+        self.record_invalid = true;
+    }
+
+    /// Take the positions in the output of the last read of the invalid
+    /// UTF-8 sequences which were replaced, paired with their original bytes.
+    #[inline]
+    pub(crate) fn take_invalid(&mut self) -> VecDeque<(usize, Vec<u8>)> {
+        mem::take(&mut self.invalid)
+    }
+
+    /// Enable or disable follow mode.
+    #[inline]
+    pub(crate) fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+    }
+
+    /// Test whether the last read from the underlying stream produced
+    /// nothing without reaching the end, as a nonblocking stream or a
+    /// stream in follow mode does when no more data is available yet.
+    #[inline]
+    pub(crate) fn stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Test whether any invalid UTF-8 has been replaced.
+    #[inline]
+    pub(crate) fn saw_invalid(&self) -> bool {
+        self.saw_invalid
+    }
+
     /// Like `read_with_status` but produces the result in a `str`. Be sure to
     /// check the `size` field of the return value to see how many bytes were
     /// written.
@@ -80,21 +179,72 @@ impl Utf8ReaderImpl {
             ));
         }
 
+        let impl_ = internals.impl_();
+        if impl_.consumed != impl_.translated {
+            return Ok(impl_.drain_translated(buf));
+        }
+        impl_.compact();
+
+        internals.impl_().invalid.clear();
+        let (nread, status) = Self::read_and_replace(internals, buf)?;
+
+        let impl_ = internals.impl_();
+        if let Some(observing) = &mut impl_.observing {
+            observing.output(
+                unsafe { str::from_utf8_unchecked(&buf[..nread]) },
+                &mut impl_.invalid,
+            );
+        }
+
+        Ok((nread, status))
+    }
+
+    /// Read from the underlying stream, replacing invalid UTF-8.
+    fn read_and_replace<Inner: ReadExt>(
+        internals: &mut impl Utf8ReaderInternals<Inner>,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, Status)> {
         let mut nread = 0;
+        internals.impl_().stalled = false;
 
         if !internals.impl_().overflow.is_empty() {
             nread += internals
                 .impl_()
-                .process_overflow(&mut buf[nread..], IncompleteHow::Include)
+                .process_overflow(&mut buf[nread..], 0, IncompleteHow::Include)
                 .unwrap();
             if !internals.impl_().overflow.is_empty() {
                 return Ok((nread, Status::active()));
             }
         }
 
-        let (size, status) = internals.inner().read_with_status(&mut buf[nread..])?;
+        let (size, mut status) = match internals.inner().read_with_status(&mut buf[nread..]) {
+            Ok(size_and_status) => size_and_status,
+            // Bytes already written to `buf` have been taken from `overflow`,
+            // so return the complete scalar values, put any incomplete
+            // sequence back, and let the next read retry.
+            Err(e) if nread != 0 && is_retryable(&e) => {
+                let valid_up_to = match str::from_utf8(&buf[..nread]) {
+                    Ok(_) => nread,
+                    Err(error) => error.valid_up_to(),
+                };
+                let impl_ = internals.impl_();
+                impl_.overflow.extend_from_slice(&buf[valid_up_to..nread]);
+                if valid_up_to == 0 {
+                    return Err(e);
+                }
+                return Ok((valid_up_to, Status::active()));
+            }
+            Err(e) => return Err(e),
+        };
         nread += size;
 
+        // In follow mode, the end is just the end of what's there so far, so
+        // hold any incomplete sequence until more data arrives.
+        if status.is_end() && internals.impl_().follow {
+            status = Status::active();
+        }
+        internals.impl_().stalled = size == 0 && !status.is_end();
+
         match str::from_utf8(&buf[..nread]) {
             Ok(_) => Ok((nread, status)),
             Err(error) => {
@@ -111,7 +261,7 @@ impl Utf8ReaderImpl {
                 };
                 nread += internals
                     .impl_()
-                    .process_overflow(&mut buf[nread..], incomplete_how)
+                    .process_overflow(&mut buf[nread..], nread, incomplete_how)
                     .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid UTF-8"))?;
                 if internals.impl_().overflow.is_empty() {
                     Ok((nread, status))
@@ -122,14 +272,157 @@ impl Utf8ReaderImpl {
         }
     }
 
+    /// Read from the underlying stream into `overflow`, if there's no
+    /// translated output left, and return the translated output, which
+    /// always ends on a scalar value boundary. This is empty at the end of
+    /// the stream, or if the underlying stream has no data available yet.
+    pub(crate) fn fill_buf<Inner: ReadExt>(
+        internals: &mut impl Utf8ReaderInternals<Inner>,
+    ) -> io::Result<&[u8]> {
+        if internals.impl_().consumed == internals.impl_().translated {
+            internals.impl_().compact();
+            internals.impl_().invalid.clear();
+            internals.impl_().translate(false);
+
+            while internals.impl_().translated == 0 {
+                let impl_ = internals.impl_();
+                let mut overflow = mem::take(&mut impl_.overflow);
+                let len = overflow.len();
+                overflow.resize(len + READ_BUFFER_SIZE, 0);
+                let result = internals.inner().read_with_status(&mut overflow[len..]);
+                let size = *result.as_ref().map(|(size, _)| size).unwrap_or(&0);
+                overflow.truncate(len + size);
+                let impl_ = internals.impl_();
+                impl_.overflow = overflow;
+                let (size, status) = result?;
+
+                // In follow mode, the end is just the end of what's there so
+                // far, as in `read_and_replace`.
+                let end = status.is_end() && !impl_.follow;
+                impl_.stalled = size == 0 && !end;
+                impl_.translate(end);
+                impl_.translated_status = if end { Status::End } else { status };
+                if end || impl_.stalled {
+                    break;
+                }
+            }
+
+            let impl_ = internals.impl_();
+            if let Some(observing) = &mut impl_.observing {
+                observing.output(
+                    unsafe { str::from_utf8_unchecked(&impl_.overflow[..impl_.translated]) },
+                    &mut impl_.invalid,
+                );
+            }
+        }
+
+        let impl_ = internals.impl_();
+        Ok(&impl_.overflow[impl_.consumed..impl_.translated])
+    }
+
+    #[inline]
+    pub(crate) fn consume<Inner: ReadExt>(
+        internals: &mut impl Utf8ReaderInternals<Inner>,
+        amt: usize,
+    ) {
+        let impl_ = internals.impl_();
+        impl_.consumed = impl_.translated.min(impl_.consumed + amt);
+    }
+
+    /// Copy as much of the translated output into `buf` as will fit without
+    /// splitting a scalar value. `buf` must be at least 4 bytes long.
+    fn drain_translated(&mut self, buf: &mut [u8]) -> (usize, Status) {
+        let data = &self.overflow[self.consumed..self.translated];
+        let mut size = buf.len().min(data.len());
+        while size < data.len() && (data[size] & 0xc0) == 0x80 {
+            size -= 1;
+        }
+        buf[..size].copy_from_slice(&data[..size]);
+        self.consumed += size;
+
+        if self.consumed == self.translated {
+            self.compact();
+            (
+                size,
+                mem::replace(&mut self.translated_status, Status::active()),
+            )
+        } else {
+            (size, Status::active())
+        }
+    }
+
+    /// Remove consumed output from the front of `overflow`.
+    fn compact(&mut self) {
+        debug_assert_eq!(self.consumed, self.translated);
+        self.overflow.drain(..self.translated);
+        self.consumed = 0;
+        self.translated = 0;
+    }
+
+    /// Translate `overflow` in place, replacing invalid sequences, up to any
+    /// incomplete sequence at the end, which is replaced too if `end` is
+    /// true.
+    fn translate(&mut self, end: bool) {
+        debug_assert_eq!(self.translated, 0);
+        match str::from_utf8(&self.overflow) {
+            Ok(_) => self.translated = self.overflow.len(),
+            Err(error) if error.error_len().is_none() && !end => {
+                self.translated = error.valid_up_to()
+            }
+            Err(_) => self.translate_invalid(end),
+        }
+    }
+
+    #[cold]
+    fn translate_invalid(&mut self, end: bool) {
+        let overflow = mem::take(&mut self.overflow);
+        let mut output = Vec::with_capacity(overflow.len() + REPL.len_utf8());
+        let mut rest = &overflow[..];
+        while !rest.is_empty() {
+            let error = match str::from_utf8(rest) {
+                Ok(_) => {
+                    output.extend_from_slice(rest);
+                    rest = &[];
+                    break;
+                }
+                Err(error) => error,
+            };
+            output.extend_from_slice(&rest[..error.valid_up_to()]);
+            rest = &rest[error.valid_up_to()..];
+
+            let len = match error.error_len() {
+                Some(len) => len,
+                None if end => rest.len(),
+                None => break,
+            };
+            self.saw_invalid = true;
+            if self.record_invalid || self.observing.is_some() {
+                self.invalid.push_back((output.len(), rest[..len].to_vec()));
+            }
+            let mut repl = [0_u8; 4];
+            output.extend_from_slice(REPL.encode_utf8(&mut repl).as_bytes());
+            rest = &rest[len..];
+        }
+        self.translated = output.len();
+        output.extend_from_slice(rest);
+        self.overflow = output;
+    }
+
     /// If normal reading encounters invalid bytes, the data is copied into
     /// `internals.impl_().overflow` as it may need to expand to make room for
     /// the U+FFFD's, and we may need to hold on to some of it until the next
     /// `read` call.
     ///
+    /// `base` is the position of `buf` in the output of the current read.
+    ///
     /// TODO: This code could be significantly optimized.
     #[cold]
-    fn process_overflow(&mut self, buf: &mut [u8], incomplete_how: IncompleteHow) -> Option<usize> {
+    fn process_overflow(
+        &mut self,
+        buf: &mut [u8],
+        base: usize,
+        incomplete_how: IncompleteHow,
+    ) -> Option<usize> {
         let mut nread = 0;
 
         loop {
@@ -152,6 +445,7 @@ impl Utf8ReaderImpl {
 
                     if let Some(invalid_sequence_length) = error.error_len() {
                         if REPL.len_utf8() <= buf[nread..].len() {
+                            self.invalid(base + nread, invalid_sequence_length);
                             nread += REPL.encode_utf8(&mut buf[nread..]).len();
                             self.overflow.copy_within(invalid_sequence_length.., 0);
                             self.overflow
@@ -162,6 +456,7 @@ impl Utf8ReaderImpl {
                         match incomplete_how {
                             IncompleteHow::Replace => {
                                 if REPL.len_utf8() <= buf[nread..].len() {
+                                    self.invalid(base + nread, self.overflow.len());
                                     nread += REPL.encode_utf8(&mut buf[nread..]).len();
                                     self.overflow.clear();
                                 } else if self.overflow.is_empty() {
@@ -188,6 +483,15 @@ impl Utf8ReaderImpl {
         Some(nread)
     }
 
+    /// Note that the first `len` bytes of `overflow` are being replaced at
+    /// position `at` in the output of the current read.
+    fn invalid(&mut self, at: usize, len: usize) {
+        self.saw_invalid = true;
+        if self.record_invalid || self.observing.is_some() {
+            self.invalid.push_back((at, self.overflow[..len].to_vec()));
+        }
+    }
+
     #[inline]
     pub(crate) fn read<Inner: ReadExt>(
         internals: &mut impl Utf8ReaderInternals<Inner>,
@@ -247,3 +551,12 @@ enum IncompleteHow {
     /// Replace the incomplete sequence with U+FFFD.
     Replace,
 }
+
+/// Test whether `error` is one which a later read can be expected to
+/// recover from, such as from a nonblocking stream.
+pub(crate) fn is_retryable(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    )
+}
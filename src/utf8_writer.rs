@@ -21,6 +21,12 @@ pub trait WriteWrapper<Inner: WriteExt>: WriteExt {
 ///
 /// `write` is not guaranteed to perform a single operation, because short
 /// writes could produce invalid UTF-8, so `write` will retry as needed.
+///
+/// If the underlying stream reports `WouldBlock` partway through, the rest
+/// of the output is held and written first by the next write or flush,
+/// which fail with the same error, without accepting anything new, until
+/// it's written. With a nonblocking stream, call `flush_with_status` with
+/// `Status::End` until it succeeds before calling `close_into_inner`.
 pub struct Utf8Writer<Inner: WriteExt> {
     /// The wrapped byte stream.
     pub(crate) inner: Inner,
@@ -1,6 +1,6 @@
 use crate::{Utf8ReaderWriter, Utf8Writer, WriteWrapper};
 use io_ext::{ReadWriteExt, Status, WriteExt};
-use std::{io, str};
+use std::{io, mem, str};
 
 pub(crate) trait Utf8WriterInternals<Inner: WriteExt>:
     WriteExt + WriteWrapper<Inner>
@@ -38,13 +38,46 @@ impl<Inner: ReadWriteExt> Utf8WriterInternals<Inner> for Utf8ReaderWriter<Inner>
     }
 }
 
-pub(crate) struct Utf8WriterImpl {}
+pub(crate) struct Utf8WriterImpl {
+    /// Output which has been accepted, but which the underlying stream
+    /// hasn't accepted yet because it reported `WouldBlock`.
+    pending: Vec<u8>,
+}
 
 impl Utf8WriterImpl {
     /// Construct a new instance of `Utf8WriterImpl`.
     #[inline]
     pub(crate) fn new() -> Self {
-        Self {}
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    /// Write out any output held from an earlier write. On failure,
+    /// including `WouldBlock` and `Interrupted`, whatever wasn't written
+    /// remains pending, so this can be retried.
+    pub(crate) fn write_pending<Inner: WriteExt>(
+        internals: &mut impl Utf8WriterInternals<Inner>,
+    ) -> io::Result<()> {
+        if internals.impl_().pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut pending = mem::take(&mut internals.impl_().pending);
+        let mut written = 0;
+        let result = loop {
+            if written == pending.len() {
+                break Ok(());
+            }
+            match internals.inner().write(&pending[written..]) {
+                Ok(0) => break Err(write_zero()),
+                Ok(size) => written += size,
+                Err(e) => break Err(e),
+            }
+        };
+        pending.drain(..written);
+        internals.impl_().pending = pending;
+        result
     }
 
     /// Flush and close the underlying stream and return the underlying
@@ -71,20 +104,40 @@ impl Utf8WriterImpl {
         internals: &mut impl Utf8WriterInternals<Inner>,
         status: Status,
     ) -> io::Result<()> {
+        Self::write_pending(internals)?;
         internals.inner().flush_with_status(status)
     }
 
     #[inline]
     pub(crate) fn abandon<Inner: WriteExt>(internals: &mut impl Utf8WriterInternals<Inner>) {
+        internals.impl_().pending.clear();
         internals.inner().abandon()
     }
 
-    #[inline]
+    /// Write `s`. If the underlying stream reports `WouldBlock` partway
+    /// through, `s` is still accepted, and the rest is held until the next
+    /// write or flush, which report `WouldBlock` without accepting anything
+    /// new until it's written.
     pub(crate) fn write_str<Inner: WriteExt>(
         internals: &mut impl Utf8WriterInternals<Inner>,
         s: &str,
     ) -> io::Result<()> {
-        internals.inner().write_str(s)
+        Self::write_pending(internals)?;
+
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            match internals.inner().write(bytes) {
+                Ok(0) => return Err(write_zero()),
+                Ok(size) => bytes = &bytes[size..],
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    internals.impl_().pending.extend_from_slice(bytes);
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     }
 
     pub(crate) fn write<Inner: WriteExt>(
@@ -93,10 +146,10 @@ impl Utf8WriterImpl {
     ) -> io::Result<usize> {
         match str::from_utf8(buf) {
             Ok(s) => Self::write_str(internals, s).map(|_| buf.len()),
-            Err(error) if error.valid_up_to() != 0 => internals
-                .inner()
-                .write_all(&buf[..error.valid_up_to()])
-                .map(|_| error.valid_up_to()),
+            Err(error) if error.valid_up_to() != 0 => Self::write_str(internals, unsafe {
+                str::from_utf8_unchecked(&buf[..error.valid_up_to()])
+            })
+            .map(|_| error.valid_up_to()),
             Err(error) => {
                 internals.inner().abandon();
                 Err(io::Error::new(io::ErrorKind::Other, error))
@@ -108,6 +161,11 @@ impl Utf8WriterImpl {
     pub(crate) fn flush<Inner: WriteExt>(
         internals: &mut impl Utf8WriterInternals<Inner>,
     ) -> io::Result<()> {
+        Self::write_pending(internals)?;
         internals.inner().flush()
     }
 }
+
+fn write_zero() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")
+}
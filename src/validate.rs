@@ -0,0 +1,724 @@
+//! Defines `validate`, which reports every place where a byte stream differs
+//! from the Text format, rather than sanitizing or rejecting it.
+
+use crate::{
+    read_buffer::READ_BUFFER_SIZE,
+    replace_selected::ReplaceSelected,
+    text_reader_impl::State,
+    unicode::{
+        disallowed, is_assigned, is_normalization_form_starter, is_tag_spec_character, Disallowed,
+        BOM, CANCEL_TAG, DEL, ESC, FF, MAX_TAG_SPEC_LEN, REPL, WAVING_BLACK_FLAG,
+    },
+    TextProfile,
+};
+use io_ext::{ReadExt, Status};
+use std::{fmt, io, iter, mem, str};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// The kinds of violations of the [Text] format.
+///
+/// [Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/text.md
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// A byte sequence which isn't valid UTF-8.
+    InvalidUtf8,
+    /// A U+FEFF (BOM), at the start of the stream or elsewhere.
+    ByteOrderMark,
+    /// A U+000D (CR), either before a U+000A (newline) or on its own.
+    CarriageReturn,
+    /// A control code, including a U+001B (ESC) which doesn't begin an
+    /// escape sequence.
+    ControlCode,
+    /// An escape sequence.
+    EscapeSequence,
+    /// A sequence which isn't in NFC or isn't Stream-Safe.
+    NotNormalized,
+    /// U+2126 (OHM SIGN), U+212A (KELVIN SIGN), or U+212B (ANGSTROM SIGN).
+    ObsoleteCompatibility,
+    /// A normalization-form non-starter at the beginning of the stream or
+    /// after a push.
+    LeadingNonstarter,
+    /// A deprecated character.
+    Deprecated,
+    /// A deprecated format character.
+    DeprecatedFormatCharacter,
+    /// A tag character which isn't part of an emoji tag sequence.
+    TagCharacter,
+    /// A noncharacter.
+    Noncharacter,
+    /// A private-use character.
+    PrivateUse,
+    /// A codepoint which is unassigned in [`UNICODE_VERSION`].
+    ///
+    /// [`UNICODE_VERSION`]: crate::UNICODE_VERSION
+    Unassigned,
+    /// A non-empty stream which doesn't end with a U+000A (newline).
+    MissingFinalNewline,
+    /// A U+000A (newline) in a string which is required to be a single line,
+    /// such as a [`TextLineStr`].
+    ///
+    /// [`TextLineStr`]: crate::TextLineStr
+    Newline,
+    /// A bidirectional embedding, override, or isolate control. The Text
+    /// format permits these, so this is only reported by the helpers in
+    /// [`text_streams::serde`], which restrict them.
+    ///
+    /// [`text_streams::serde`]: https://docs.rs/text-streams/latest/text_streams/serde/index.html
+    BidiControl,
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidUtf8 => "invalid UTF-8",
+            Self::ByteOrderMark => "byte-order mark",
+            Self::CarriageReturn => "carriage return",
+            Self::ControlCode => "control code",
+            Self::EscapeSequence => "escape sequence",
+            Self::NotNormalized => "not in NFC",
+            Self::ObsoleteCompatibility => "obsolete compatibility",
+            Self::LeadingNonstarter => "leading non-starter",
+            Self::Deprecated => "deprecated character",
+            Self::DeprecatedFormatCharacter => "deprecated format character",
+            Self::TagCharacter => "tag character",
+            Self::Noncharacter => "noncharacter",
+            Self::PrivateUse => "private use character",
+            Self::Unassigned => "unassigned codepoint",
+            Self::MissingFinalNewline => "missing final newline",
+            Self::Newline => "newline within a line",
+            Self::BidiControl => "bidirectional control",
+        })
+    }
+}
+
+impl From<Disallowed> for ViolationKind {
+    fn from(disallowed: Disallowed) -> Self {
+        match disallowed {
+            Disallowed::Deprecated => Self::Deprecated,
+            Disallowed::DeprecatedFormat => Self::DeprecatedFormatCharacter,
+            Disallowed::Tag => Self::TagCharacter,
+            Disallowed::Noncharacter => Self::Noncharacter,
+            Disallowed::PrivateUse => Self::PrivateUse,
+        }
+    }
+}
+
+/// A single place where the input differs from the Text format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// What kind of violation this is.
+    pub kind: ViolationKind,
+
+    /// The offending codepoint, or `None` for invalid UTF-8 and a missing
+    /// final newline. For an escape sequence, this is the U+001B (ESC).
+    pub codepoint: Option<char>,
+
+    /// The byte offset in the input of the start of the violation.
+    pub offset: u64,
+
+    /// The 1-based line number of the start of the violation.
+    pub line: u64,
+
+    /// The 1-based column, in codepoints, of the start of the violation.
+    pub column: u64,
+
+    /// What `TextReader` produces in its place. For `NotNormalized` and
+    /// `ObsoleteCompatibility`, this replaces the sequence starting at
+    /// `codepoint` up to the next normalization boundary, and for
+    /// `EscapeSequence`, it replaces the whole escape sequence.
+    pub replacement: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)?;
+        if let Some(c) = self.codepoint {
+            write!(f, " U+{:04X}", c as u32)?;
+        }
+        write!(f, " at byte {}", self.offset)?;
+        if self.replacement.is_empty() {
+            write!(f, ", removed")
+        } else {
+            write!(f, ", replaced with {:?}", self.replacement)
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+/// The result of `validate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The violations found, in order of their offsets.
+    pub violations: Vec<Violation>,
+
+    /// True if validation stopped early because the maximum number of
+    /// violations was reached.
+    pub truncated: bool,
+}
+
+impl ValidationReport {
+    /// Test whether the input was valid Text, such that `TextReader` would
+    /// pass it through unmodified.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Read all of `inner` and report each place where it violates the rules of
+/// the [Text] format, along with what `TextReader` would replace it with.
+///
+/// At most `max_violations` violations are reported; if more are found,
+/// validation stops early and the report is marked as truncated.
+///
+/// [Text]: https://github.com/sunfishcode/text-streams/blob/main/docs/text.md
+#[inline]
+pub fn validate<Inner: ReadExt>(
+    inner: Inner,
+    max_violations: usize,
+) -> io::Result<ValidationReport> {
+    validate_with_profile(inner, TextProfile::default(), max_violations)
+}
+
+/// Like `validate`, but applies the rules of the given version of the Text
+/// format, rather than [`TextProfile::TextLatest`].
+pub fn validate_with_profile<Inner: ReadExt>(
+    mut inner: Inner,
+    profile: TextProfile,
+    max_violations: usize,
+) -> io::Result<ValidationReport> {
+    let mut validator = Validator::new(profile, max_violations);
+    let mut buf = vec![0_u8; READ_BUFFER_SIZE];
+
+    loop {
+        let (size, status) = inner.read_with_status(&mut buf)?;
+        validator.bytes(&buf[..size], status != Status::active());
+        if status != Status::active() {
+            validator.sequence_end(status);
+        }
+        if status.is_end() || validator.report.truncated {
+            return Ok(validator.report);
+        }
+    }
+}
+
+/// Find the first violation in `s`, if any, without going through a
+/// `ReadExt`.
+pub(crate) fn first_violation(s: &str, profile: TextProfile) -> Option<Violation> {
+    let mut validator = Validator::new(profile, 1);
+    validator.bytes(s.as_bytes(), true);
+    validator.sequence_end(Status::End);
+    validator.report.violations.pop()
+}
+
+/// A position in the input.
+#[derive(Clone, Copy)]
+pub(crate) struct Position {
+    pub(crate) offset: u64,
+    line: u64,
+    column: u64,
+}
+
+impl Position {
+    pub(crate) fn start() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Advance past `c`, which came from `len` bytes of input.
+    pub(crate) fn advance(&mut self, c: char, len: usize) {
+        self.offset += len as u64;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+
+    pub(crate) fn violation(
+        self,
+        kind: ViolationKind,
+        codepoint: Option<char>,
+        replacement: impl Into<String>,
+    ) -> Violation {
+        Violation {
+            kind,
+            codepoint,
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+            replacement: replacement.into(),
+        }
+    }
+}
+
+/// Mirrors the rules applied by `TextReaderImpl`, recording a `Violation`
+/// wherever they change the input.
+pub(crate) struct Validator {
+    /// The version of the rules to apply.
+    profile: TextProfile,
+
+    max_violations: usize,
+    report: ValidationReport,
+
+    /// The position of the next codepoint in the input.
+    position: Position,
+
+    /// Bytes of an incomplete UTF-8 sequence at the end of the previous read.
+    partial: Vec<u8>,
+
+    /// Control-code and escape-sequence state machine.
+    state: State,
+
+    /// The position of the U+000D or U+001B which started the current state.
+    state_start: Position,
+
+    /// At the beginning of a stream or after a push, expect a
+    /// normalization-form starter.
+    expect_starter: bool,
+
+    /// For detecting a BOM at the start of a stream.
+    at_start: bool,
+
+    /// The rules applied to what's queued for normalization.
+    queue_rules: QueueRules,
+
+    /// Violations which may still be preceded by violations not found yet.
+    pending: Vec<Violation>,
+}
+
+impl Validator {
+    pub(crate) fn new(profile: TextProfile, max_violations: usize) -> Self {
+        Self {
+            profile,
+            max_violations,
+            report: ValidationReport::default(),
+            position: Position::start(),
+            partial: Vec::new(),
+            state: State::Ground(true),
+            state_start: Position::start(),
+            expect_starter: true,
+            at_start: true,
+            queue_rules: QueueRules::new(profile),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Decode `bytes` as UTF-8, treating an incomplete sequence at the end
+    /// as invalid if `sequence_end` is true.
+    fn bytes(&mut self, bytes: &[u8], sequence_end: bool) {
+        let mut partial = mem::take(&mut self.partial);
+        partial.extend_from_slice(bytes);
+        let mut bytes = &partial[..];
+
+        loop {
+            match str::from_utf8(bytes) {
+                Ok(s) => {
+                    s.chars().for_each(|c| self.char(c));
+                    return;
+                }
+                Err(error) => {
+                    let (valid, after_valid) = bytes.split_at(error.valid_up_to());
+                    unsafe { str::from_utf8_unchecked(valid) }
+                        .chars()
+                        .for_each(|c| self.char(c));
+                    match error.error_len() {
+                        Some(len) => {
+                            self.invalid_utf8(len);
+                            bytes = &after_valid[len..];
+                        }
+                        None if sequence_end => {
+                            self.invalid_utf8(after_valid.len());
+                            return;
+                        }
+                        None => {
+                            self.partial = after_valid.to_vec();
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The offset in the input before which no more violations can be
+    /// reported. Violations at or after it may still be found when more
+    /// input arrives.
+    pub(crate) fn confirmed_offset(&self) -> u64 {
+        let mut offset = self.position.offset;
+        if !matches!(self.state, State::Ground(_)) {
+            offset = offset.min(self.state_start.offset);
+        }
+        if let Some(unsettled) = self.queue_rules.unsettled_offset() {
+            offset = offset.min(unsettled);
+        }
+        for violation in &self.pending {
+            offset = offset.min(violation.offset);
+        }
+        offset
+    }
+
+    /// Take the violations reported so far.
+    pub(crate) fn take_violations(&mut self) -> Vec<Violation> {
+        mem::take(&mut self.report.violations)
+    }
+
+    pub(crate) fn char(&mut self, c: char) {
+        let position = self.position;
+        self.position.advance(c, c.len_utf8());
+        self.raw(c, position);
+    }
+
+    pub(crate) fn invalid_utf8(&mut self, len: usize) {
+        let position = self.position;
+        self.position.advance(REPL, len);
+        self.violation(ViolationKind::InvalidUtf8, None, position, REPL);
+        self.raw(REPL, position);
+    }
+
+    /// The equivalent of `TextReaderImpl::process_raw_string`.
+    fn raw(&mut self, c: char, position: Position) {
+        let at_start = mem::replace(&mut self.at_start, false);
+        loop {
+            match (self.state, c) {
+                (State::Ground(_), BOM) if at_start => {
+                    self.violation(ViolationKind::ByteOrderMark, Some(c), position, "")
+                }
+                (State::Ground(_), '\n') => {
+                    self.queue('\n', position);
+                    self.expect_starter = false;
+                    self.state = State::Ground(true)
+                }
+                (State::Ground(_), '\t') => {
+                    self.queue('\t', position);
+                    self.expect_starter = false;
+                    self.state = State::Ground(false)
+                }
+                (State::Ground(_), FF) => {
+                    self.violation(ViolationKind::ControlCode, Some(c), position, ' ');
+                    self.queue(' ', position);
+                    self.expect_starter = false;
+                    self.state = State::Ground(false)
+                }
+                (State::Ground(_), '\r') | (State::Ground(_), ESC) => {
+                    self.state_start = position;
+                    self.state = if c == ESC { State::Esc } else { State::Cr }
+                }
+                (State::Ground(_), c) if c.is_control() => {
+                    self.violation(ViolationKind::ControlCode, Some(c), position, REPL);
+                    self.queue(REPL, position);
+                    self.expect_starter = false;
+                    self.state = State::Ground(false);
+                }
+                (State::Ground(_), '\u{2329}') | (State::Ground(_), '\u{232a}') => {
+                    self.violation(ViolationKind::Deprecated, Some(c), position, REPL);
+                    self.queue(REPL, position);
+                    self.expect_starter = false;
+                    self.state = State::Ground(false)
+                }
+                (State::Ground(_), mut c) => {
+                    // Noncharacters are also unassigned; report them as noncharacters.
+                    if self.profile.disallows_unassigned()
+                        && !is_assigned(c)
+                        && disallowed(c).is_none()
+                    {
+                        self.violation(ViolationKind::Unassigned, Some(c), position, REPL);
+                        c = REPL;
+                    }
+                    if self.expect_starter {
+                        self.expect_starter = false;
+                        if !is_normalization_form_starter(c) {
+                            self.violation(
+                                ViolationKind::LeadingNonstarter,
+                                Some(c),
+                                position,
+                                REPL,
+                            );
+                            c = REPL;
+                        }
+                    }
+                    self.queue(c, position);
+                    self.state = State::Ground(false)
+                }
+
+                (State::Cr, '\n') => {
+                    self.violation(
+                        ViolationKind::CarriageReturn,
+                        Some('\r'),
+                        self.state_start,
+                        "",
+                    );
+                    self.queue('\n', position);
+                    self.expect_starter = false;
+                    self.state = State::Ground(true);
+                }
+                (State::Cr, _) => {
+                    self.cr_or_esc();
+                    continue;
+                }
+
+                (State::Esc, '[') => self.state = State::CsiStart,
+                (State::Esc, ']') => self.state = State::Osc,
+                (State::Esc, c) if ('@'..='~').contains(&c) => self.escape_sequence(),
+                (State::Esc, _) => {
+                    self.cr_or_esc();
+                    continue;
+                }
+
+                (State::CsiStart, '[') => self.state = State::Linux,
+                (State::CsiStart, c) | (State::Csi, c) if (' '..='?').contains(&c) => {
+                    self.state = State::Csi
+                }
+                (State::CsiStart, c) | (State::Csi, c) if ('@'..='~').contains(&c) => {
+                    self.escape_sequence()
+                }
+                (State::CsiStart, _) | (State::Csi, _) => {
+                    self.escape_sequence();
+                    continue;
+                }
+
+                (State::Osc, c) if !c.is_control() || c == '\n' || c == '\t' => (),
+                (State::Osc, _) => self.escape_sequence(),
+
+                (State::Linux, c) if ('\0'..=DEL).contains(&c) => self.escape_sequence(),
+                (State::Linux, _) => {
+                    self.escape_sequence();
+                    continue;
+                }
+            }
+            break;
+        }
+    }
+
+    /// A U+000D or U+001B which doesn't begin a CRLF or an escape sequence.
+    fn cr_or_esc(&mut self) {
+        // As in the reader, a lone ESC leaves `expect_starter` as it was, so
+        // a nonstarter after it is still replaced.
+        let (kind, c) = match self.state {
+            State::Cr => {
+                self.expect_starter = false;
+                (ViolationKind::CarriageReturn, '\r')
+            }
+            _ => (ViolationKind::ControlCode, ESC),
+        };
+        self.violation(kind, Some(c), self.state_start, REPL);
+        self.queue(REPL, self.state_start);
+        self.state = State::Ground(false);
+    }
+
+    fn escape_sequence(&mut self) {
+        self.violation(
+            ViolationKind::EscapeSequence,
+            Some(ESC),
+            self.state_start,
+            "",
+        );
+        self.state = State::Ground(false);
+    }
+
+    /// Handle the end of the stream or a push, following the rules in
+    /// `TextReaderImpl::read_with_status`.
+    pub(crate) fn sequence_end(&mut self, status: Status) {
+        match self.state {
+            State::Ground(_) => {}
+            State::Cr | State::Esc => self.cr_or_esc(),
+            State::CsiStart | State::Csi | State::Osc | State::Linux => self.escape_sequence(),
+        }
+
+        if status.is_end() && self.state != State::Ground(true) {
+            self.violation(
+                ViolationKind::MissingFinalNewline,
+                None,
+                self.position,
+                '\n',
+            );
+            self.queue('\n', self.position);
+            self.state = State::Ground(true);
+        }
+
+        self.queue_rules.end(&mut self.pending);
+        self.flush_pending();
+        self.expect_starter = true;
+    }
+
+    fn queue(&mut self, c: char, position: Position) {
+        if self.queue_rules.queue(c, position, &mut self.pending) {
+            self.flush_pending();
+        }
+    }
+
+    fn violation(
+        &mut self,
+        kind: ViolationKind,
+        codepoint: Option<char>,
+        position: Position,
+        replacement: impl Into<String>,
+    ) {
+        self.pending
+            .push(position.violation(kind, codepoint, replacement));
+    }
+
+    /// Move the pending violations into the report, in order.
+    fn flush_pending(&mut self) {
+        self.pending.sort_by_key(|violation| violation.offset);
+        for violation in self.pending.drain(..) {
+            if self.report.violations.len() == self.max_violations {
+                self.report.truncated = true;
+                break;
+            }
+            self.report.violations.push(violation);
+        }
+        self.pending.clear();
+    }
+}
+
+/// The rules `TextReaderImpl` applies to the codepoints it queues for
+/// normalization: those of `ReplaceSelected`, and normalization itself. Each
+/// violation is attributed to the codepoint it replaces, or for
+/// normalization, to the first codepoint it changes. This is shared by
+/// `Validator` and the replacement observer for `TextReader`, so that they
+/// report the same things.
+pub(crate) struct QueueRules {
+    /// The version of the rules to apply.
+    profile: TextProfile,
+
+    /// The codepoints since the last normalization boundary.
+    segment: Vec<(char, Position)>,
+
+    /// Tag characters following a U+1F3F4 (WAVING BLACK FLAG) which aren't
+    /// yet known to be part of a well-formed emoji tag sequence.
+    tags: Option<Vec<(char, Position)>>,
+}
+
+impl QueueRules {
+    pub(crate) fn new(profile: TextProfile) -> Self {
+        Self {
+            profile,
+            segment: Vec::new(),
+            tags: None,
+        }
+    }
+
+    /// The equivalent of `ReplaceSelected`, applied to codepoints as they
+    /// enter the normalization queue. Violations found are appended to
+    /// `violations`. Returns true if no more violations can be found before
+    /// `c`.
+    pub(crate) fn queue(
+        &mut self,
+        c: char,
+        position: Position,
+        violations: &mut Vec<Violation>,
+    ) -> bool {
+        if let Some(tags) = &mut self.tags {
+            if is_tag_spec_character(c) && tags.len() < MAX_TAG_SPEC_LEN {
+                tags.push((c, position));
+                return self.segment(c, position, violations);
+            }
+            if c == CANCEL_TAG && !tags.is_empty() {
+                self.tags = None;
+                return self.segment(c, position, violations);
+            }
+            self.end_tags(violations);
+        }
+
+        match c {
+            BOM => violations.push(position.violation(
+                ViolationKind::ByteOrderMark,
+                Some(c),
+                replace(c, self.profile),
+            )),
+            WAVING_BLACK_FLAG if self.profile.permits_emoji_tag_sequences() => {
+                self.tags = Some(Vec::new())
+            }
+            c => {
+                if let Some(disallowed) = disallowed(c) {
+                    violations.push(position.violation(
+                        disallowed.into(),
+                        Some(c),
+                        replace(c, self.profile),
+                    ));
+                }
+            }
+        }
+
+        self.segment(c, position, violations)
+    }
+
+    /// Check what's left at the end of the stream or a push.
+    pub(crate) fn end(&mut self, violations: &mut Vec<Violation>) {
+        self.end_tags(violations);
+        self.flush_segment(violations);
+    }
+
+    /// The offset of the first codepoint which may still be found to be a
+    /// violation, if any.
+    pub(crate) fn unsettled_offset(&self) -> Option<u64> {
+        let unsettled = self.segment.iter().chain(self.tags.iter().flatten());
+        unsettled.map(|(_, position)| position.offset).min()
+    }
+
+    /// Report any tag characters which didn't end up in a well-formed emoji
+    /// tag sequence.
+    fn end_tags(&mut self, violations: &mut Vec<Violation>) {
+        for (c, position) in self.tags.take().unwrap_or_default() {
+            violations.push(position.violation(ViolationKind::TagCharacter, Some(c), REPL));
+        }
+    }
+
+    /// Add `c` to the current normalization segment, starting a new one if
+    /// nothing before `c` can interact with it or anything after it, and
+    /// return true if nothing before `c` remains to be checked.
+    fn segment(&mut self, c: char, position: Position, violations: &mut Vec<Violation>) -> bool {
+        let boundary =
+            is_normalization_form_starter(c) && is_nfc_quick(iter::once(c)) == IsNormalized::Yes;
+        if boundary {
+            self.flush_segment(violations);
+        }
+        self.segment.push((c, position));
+        boundary && self.tags.is_none()
+    }
+
+    fn flush_segment(&mut self, violations: &mut Vec<Violation>) {
+        let segment = mem::take(&mut self.segment);
+        let normalized = segment
+            .iter()
+            .map(|(c, _)| *c)
+            .svar()
+            .stream_safe()
+            .nfc()
+            .collect::<Vec<_>>();
+        let differs = segment
+            .iter()
+            .zip(&normalized)
+            .position(|((c, _), n)| c != n)
+            .or_else(|| {
+                if segment.len() != normalized.len() {
+                    Some(segment.len().min(normalized.len()))
+                } else {
+                    None
+                }
+            });
+        if let Some(index) = differs {
+            let (c, position) = segment[index.min(segment.len() - 1)];
+            let kind = match c {
+                '\u{2126}' | '\u{212a}' | '\u{212b}' => ViolationKind::ObsoleteCompatibility,
+                _ => ViolationKind::NotNormalized,
+            };
+            let replacement = normalized[index.min(normalized.len())..]
+                .iter()
+                .collect::<String>();
+            violations.push(position.violation(kind, Some(c), replacement));
+        }
+    }
+}
+
+/// What `ReplaceSelected` replaces `c` with.
+fn replace(c: char, profile: TextProfile) -> String {
+    ReplaceSelected::new(iter::once(c), profile).collect()
+}
@@ -0,0 +1,19 @@
+//! Helpers shared between the integration tests.
+
+use std::io::{self, Read};
+
+/// An `io::Read` which produces one byte per read.
+pub struct OneByte<'a>(pub &'a [u8]);
+
+impl Read for OneByte<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.split_first() {
+            Some((first, rest)) if !buf.is_empty() => {
+                buf[0] = *first;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
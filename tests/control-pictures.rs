@@ -0,0 +1,137 @@
+use io_ext_adapters::StdReader;
+use std::io::Read;
+use text_streams::{is_text, ControlPictures, TextReader};
+
+fn read(input: &[u8]) -> String {
+    let mut reader = TextReader::with_control_pictures(StdReader::generic(input));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert!(is_text(&s), "output {:?}", s);
+    s
+}
+
+#[test]
+fn test_c0_and_del() {
+    assert_eq!(read(b"a\0b\x07c\x7fd\n"), "a\u{2400}b\u{2407}c\u{2421}d\n");
+    assert_eq!(read(b"form\x0cfeed\n"), "form\u{240c}feed\n");
+    assert_eq!(read(b"tab\tand\nnewline\n"), "tab\tand\nnewline\n");
+}
+
+#[test]
+fn test_line_endings() {
+    assert_eq!(read(b"crlf\r\nline\r\n"), "crlf\nline\n");
+    assert_eq!(read(b"lone\rcr\n"), "lone\u{240d}cr\n");
+    assert_eq!(read(b"trailing cr\r"), "trailing cr\u{240d}\n");
+}
+
+#[test]
+fn test_escape_sequences() {
+    assert_eq!(
+        read(b"\x1b[31mred\x1b[0m\n"),
+        "\u{241b}[31mred\u{241b}[0m\n"
+    );
+    assert_eq!(read(b"\x1b]0;title\x07\n"), "\u{241b}]0;title\u{2407}\n");
+}
+
+#[test]
+fn test_c1_and_invalid_utf8() {
+    assert_eq!(read("next\u{85}line\n".as_bytes()), "next\\u{85}line\n");
+    assert_eq!(read(b"bad\xff\xfebytes\n"), "bad\\xff\\xfebytes\n");
+    assert_eq!(read(b"short\xe2\x82\n"), "short\\xe2\\x82\n");
+    assert_eq!(read(b"cr\r\xff\n"), "cr\u{240d}\\xff\n");
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(
+        ControlPictures("a\0b\r\nc\td\x7f\u{9b}\n").to_string(),
+        "a\u{2400}b\u{240d}\nc\td\u{2421}\\u{9b}\n"
+    );
+    assert_eq!(ControlPictures("plain text").to_string(), "plain text");
+}
+
+/// Undo the mapping.
+fn decode(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next().unwrap() {
+                '\\' => bytes.push(b'\\'),
+                'x' => {
+                    let hex = chars.by_ref().take(2).collect::<String>();
+                    bytes.push(u8::from_str_radix(&hex, 16).unwrap());
+                }
+                'u' => {
+                    let hex = chars
+                        .by_ref()
+                        .skip(1)
+                        .take_while(|c| *c != '}')
+                        .collect::<String>();
+                    let c = char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap();
+                    bytes.extend_from_slice(c.to_string().as_bytes());
+                }
+                other => panic!("unexpected escape {:?}", other),
+            },
+            '\u{2400}'..='\u{241f}' => bytes.push(c as u32 as u8),
+            '\u{2421}' => bytes.push(0x7f),
+            c => bytes.extend_from_slice(c.to_string().as_bytes()),
+        }
+    }
+    bytes
+}
+
+#[test]
+fn test_backslashes() {
+    assert_eq!(read(b"C:\\dir\\file\n"), "C:\\\\dir\\\\file\n");
+    assert_eq!(read(b"\\xff\xff\n"), "\\\\xff\\xff\n");
+    assert_eq!(
+        ControlPictures("\\u{85}\u{85}").to_string(),
+        "\\\\u{85}\\u{85}"
+    );
+}
+
+#[test]
+fn test_literal_control_pictures() {
+    assert_eq!(read("\u{241b}\x1b\n".as_bytes()), "\\u{241b}\u{241b}\n");
+    assert_eq!(read("\u{240d}\r\n".as_bytes()), "\\u{240d}\n");
+    assert_eq!(
+        read("\u{2424}\u{2426}\u{2440}\n".as_bytes()),
+        "\\u{2424}\\u{2426}\u{2440}\n"
+    );
+    assert_ne!(
+        ControlPictures("\x07").to_string(),
+        ControlPictures("\u{2407}").to_string()
+    );
+    assert_eq!(
+        ControlPictures("\u{2421}\x7f").to_string(),
+        "\\u{2421}\u{2421}"
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    let inputs: &[&[u8]] = &[
+        b"plain\n",
+        b"a\0b\x07c\x7fd\x1b[0m\n",
+        b"literal \\x1b and real \x1b\n",
+        b"literal \\xff and real \xff\n",
+        "literal \\u{85} and real \u{85}\n".as_bytes(),
+        b"\\\\ \\\\\\ \\\n",
+        "picture \u{241b} and real \x1b\n".as_bytes(),
+        "picture \u{240d} and real \rx\n".as_bytes(),
+        "pictures \u{2400}\u{2421}\u{2424}\u{2426} and reals \0\x7f\n".as_bytes(),
+    ];
+    for input in inputs {
+        assert_eq!(decode(&read(input)), *input, "input {:?}", input);
+        let s = String::from_utf8_lossy(input);
+        if !s.contains('\u{fffd}') {
+            assert_eq!(
+                decode(&ControlPictures(&s).to_string()),
+                *input,
+                "input {:?}",
+                input
+            );
+        }
+    }
+}
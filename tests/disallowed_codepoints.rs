@@ -1,8 +1,13 @@
+// DO NOT EDIT THIS FILE. IT WAS AUTOMATICALLY GENERATED BY:
+//
+//   python3 scripts/unicode.py <ucd-dir>
+//
+// Unicode version: 13.0.0.
+
 pub const DISALLOWED_CODEPOINTS: [char; 267] = [
     // All C0, U+007F, and C1 control codes other than U+000A (newline),
-    // U+0009 (horizontal tab), U+000C (form feed), U+0007 (alert), and
-    // U+001b (escape).
-    '\0',
+    // U+0009 (horizontal tab), U+000C (form feed), and U+001B (escape).
+    '\u{0}',
     '\u{1}',
     '\u{2}',
     '\u{3}',
@@ -12,7 +17,7 @@ pub const DISALLOWED_CODEPOINTS: [char; 267] = [
     '\u{7}',
     '\u{8}',
     '\u{b}',
-    '\r',
+    '\u{d}',
     '\u{e}',
     '\u{f}',
     '\u{10}',
@@ -70,9 +75,9 @@ pub const DISALLOWED_CODEPOINTS: [char; 267] = [
     '\u{206d}',
     '\u{206e}',
     '\u{206f}',
+    '\u{e0001}',
     // Tag Characters
     '\u{e0000}',
-    '\u{e0001}',
     '\u{e0002}',
     '\u{e0003}',
     '\u{e0004}',
@@ -200,6 +205,38 @@ pub const DISALLOWED_CODEPOINTS: [char; 267] = [
     '\u{e007e}',
     '\u{e007f}',
     // Noncharacters
+    '\u{fdd0}',
+    '\u{fdd1}',
+    '\u{fdd2}',
+    '\u{fdd3}',
+    '\u{fdd4}',
+    '\u{fdd5}',
+    '\u{fdd6}',
+    '\u{fdd7}',
+    '\u{fdd8}',
+    '\u{fdd9}',
+    '\u{fdda}',
+    '\u{fddb}',
+    '\u{fddc}',
+    '\u{fddd}',
+    '\u{fdde}',
+    '\u{fddf}',
+    '\u{fde0}',
+    '\u{fde1}',
+    '\u{fde2}',
+    '\u{fde3}',
+    '\u{fde4}',
+    '\u{fde5}',
+    '\u{fde6}',
+    '\u{fde7}',
+    '\u{fde8}',
+    '\u{fde9}',
+    '\u{fdea}',
+    '\u{fdeb}',
+    '\u{fdec}',
+    '\u{fded}',
+    '\u{fdee}',
+    '\u{fdef}',
     '\u{fffe}',
     '\u{ffff}',
     '\u{1fffe}',
@@ -234,40 +271,8 @@ pub const DISALLOWED_CODEPOINTS: [char; 267] = [
     '\u{fffff}',
     '\u{10fffe}',
     '\u{10ffff}',
-    '\u{fdd0}',
-    '\u{fdd1}',
-    '\u{fdd2}',
-    '\u{fdd3}',
-    '\u{fdd4}',
-    '\u{fdd5}',
-    '\u{fdd6}',
-    '\u{fdd7}',
-    '\u{fdd8}',
-    '\u{fdd9}',
-    '\u{fdda}',
-    '\u{fddb}',
-    '\u{fddc}',
-    '\u{fddd}',
-    '\u{fdde}',
-    '\u{fddf}',
-    '\u{fde0}',
-    '\u{fde1}',
-    '\u{fde2}',
-    '\u{fde3}',
-    '\u{fde4}',
-    '\u{fde5}',
-    '\u{fde6}',
-    '\u{fde7}',
-    '\u{fde8}',
-    '\u{fde9}',
-    '\u{fdea}',
-    '\u{fdeb}',
-    '\u{fdec}',
-    '\u{fded}',
-    '\u{fdee}',
-    '\u{fdef}',
     // Private-Use Characters
-    // TODO: These are just the limits of the ranges; the ranges are big.
+    // These are just the limits of the ranges; the ranges are big.
     '\u{e000}',
     '\u{f8ff}',
     '\u{f0000}',
@@ -0,0 +1,189 @@
+use io_ext::{ReadExt, Status};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+use text_streams::{to_text, TextReader, Utf8Reader, NORMALIZATION_BUFFER_SIZE};
+
+/// A `ReadExt` for a file which reports its current end without assuming
+/// it's final, so reads after more data is appended see the new data.
+struct Tail(File);
+
+impl Read for Tail {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl ReadExt for Tail {
+    fn read_with_status(&mut self, buf: &mut [u8]) -> io::Result<(usize, Status)> {
+        match self.0.read(buf)? {
+            0 => Ok((0, Status::End)),
+            size => Ok((size, Status::active())),
+        }
+    }
+}
+
+/// A temporary file, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "text-streams-follow-{}-{}",
+            std::process::id(),
+            name
+        ));
+        File::create(&path).unwrap();
+        Self(path)
+    }
+
+    fn append(&self, data: &[u8]) {
+        let mut file = OpenOptions::new().append(true).open(&self.0).unwrap();
+        file.write_all(data).unwrap();
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Read everything which is currently available, returning the output
+/// and whether the end of the stream was reached. A read can consume input
+/// without producing output, so stop after two reads produce nothing.
+fn poll(reader: &mut impl ReadExt) -> (String, bool) {
+    let mut output = Vec::new();
+    let mut buf = [0_u8; NORMALIZATION_BUFFER_SIZE];
+    let mut idle = 0;
+    while idle < 2 {
+        let (size, status) = reader.read_with_status(&mut buf).unwrap();
+        output.extend_from_slice(&buf[..size]);
+        if status.is_end() {
+            return (String::from_utf8(output).unwrap(), true);
+        }
+        idle = if size == 0 { idle + 1 } else { 0 };
+    }
+    (String::from_utf8(output).unwrap(), false)
+}
+
+#[test]
+fn test_follow() {
+    let file = TempFile::new("test_follow");
+    let mut reader = TextReader::new(Tail(File::open(&file.0).unwrap()));
+    reader.set_follow(true);
+
+    // Each chunk ends in the middle of something which would be replaced
+    // or completed at the end of a stream.
+    let chunks: &[&[u8]] = &[
+        b"first line\n",
+        b"partial",
+        b" line\r",
+        b"\ncaf\xc3",
+        b"\xa9\n\x1b",
+        b"[1mbold\x1b[0m\n",
+        b"no newline yet",
+    ];
+    let mut all = Vec::new();
+    let mut output = String::new();
+    let expected_so_far = [
+        "first line\n",
+        "first line\n",
+        "first line\n",
+        "first line\npartial line\n",
+        "first line\npartial line\ncaf\u{e9}\n",
+        "first line\npartial line\ncaf\u{e9}\nbold\n",
+        "first line\npartial line\ncaf\u{e9}\nbold\n",
+    ];
+    for (chunk, expected) in chunks.iter().zip(&expected_so_far) {
+        file.append(chunk);
+        all.extend_from_slice(chunk);
+        let (more, ended) = poll(&mut reader);
+        assert!(!ended);
+        output.push_str(&more);
+
+        // Lines are produced as they're completed, and nothing is replaced
+        // or appended at the current end of the file.
+        assert_eq!(output, *expected, "after {:?}", chunk);
+        assert!(!output.contains('\u{fffd}'));
+    }
+
+    // Polling again with nothing new produces nothing.
+    assert_eq!(poll(&mut reader), (String::new(), false));
+
+    // Closing applies the end-of-stream rules.
+    reader.set_follow(false);
+    let (more, ended) = poll(&mut reader);
+    assert!(ended);
+    output.push_str(&more);
+    assert_eq!(output, to_text(&all));
+    assert!(output.ends_with("no newline yet\n"));
+}
+
+#[test]
+fn test_close_mid_sequence() {
+    let file = TempFile::new("test_close_mid_sequence");
+    let mut reader = TextReader::new(Tail(File::open(&file.0).unwrap()));
+    reader.set_follow(true);
+
+    file.append(b"line\ntruncated \xe2\x82");
+    assert_eq!(poll(&mut reader), ("line\n".to_owned(), false));
+    file.append(b"\xac\n\r");
+    assert_eq!(
+        poll(&mut reader),
+        ("truncated \u{20ac}\n".to_owned(), false)
+    );
+
+    reader.set_follow(false);
+    file.append(b"\xe2");
+    assert_eq!(poll(&mut reader), ("\u{fffd}\u{fffd}\n".to_owned(), true));
+}
+
+#[test]
+fn test_fill_buf_at_end() {
+    use std::io::BufRead;
+
+    let file = TempFile::new("test_fill_buf_at_end");
+    let mut reader = TextReader::new(Tail(File::open(&file.0).unwrap()));
+    reader.set_follow(true);
+
+    // With nothing available yet, `fill_buf` returns an empty buffer
+    // instead of waiting.
+    assert!(reader.fill_buf().unwrap().is_empty());
+
+    file.append(b"line\npartial\xe2\x82");
+    let mut output = String::new();
+    loop {
+        let buf = reader.fill_buf().unwrap();
+        if buf.is_empty() {
+            break;
+        }
+        output.push_str(std::str::from_utf8(buf).unwrap());
+        let len = buf.len();
+        reader.consume(len);
+    }
+    assert_eq!(output, "line\n");
+    assert!(reader.fill_buf().unwrap().is_empty());
+
+    file.append(b"\xac\n\x1b[");
+    assert_eq!(reader.fill_buf().unwrap(), "partial\u{20ac}\n".as_bytes());
+    reader.consume(12);
+    assert!(reader.fill_buf().unwrap().is_empty());
+
+    let mut reader = TextReader::with_line_editing(Tail(File::open(&file.0).unwrap()));
+    reader.set_follow(true);
+    assert_eq!(
+        reader.fill_buf().unwrap(),
+        "line\npartial\u{20ac}\n".as_bytes()
+    );
+    reader.consume(17);
+    assert!(reader.fill_buf().unwrap().is_empty());
+
+    let mut reader = Utf8Reader::new(Tail(File::open(&file.0).unwrap()));
+    reader.set_follow(true);
+    let len = reader.fill_buf().unwrap().len();
+    reader.consume(len);
+    assert!(reader.fill_buf().unwrap().is_empty());
+}
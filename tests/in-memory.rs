@@ -0,0 +1,54 @@
+use std::{borrow::Cow, io::Read};
+use text_streams::{is_text, to_text, TextReader};
+
+fn via_reader(bytes: &[u8]) -> String {
+    let mut reader = TextReader::new(io_ext::SliceReader::new(bytes));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    s
+}
+
+#[test]
+fn test_borrowed() {
+    for input in &[
+        "",
+        "\n",
+        "hello world\n",
+        "\ttab\n",
+        "\u{c5}ngstr\u{f6}m\n",
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0077}\u{e006c}\u{e0073}\u{e007f}\n",
+    ] {
+        assert!(is_text(input), "input {:?}", input);
+        assert!(
+            matches!(to_text(input.as_bytes()), Cow::Borrowed(s) if s == *input),
+            "input {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_owned() {
+    for input in &[
+        &b"hello"[..],
+        b"hello\r\n",
+        b"\xff\n",
+        b"\x07\n",
+        b"\x1b[31mred\x1b[0m\n",
+        "\u{feff}hello\n".as_bytes(),
+        "A\u{30a}\n".as_bytes(),
+        "\u{212b}\n".as_bytes(),
+        "\u{30a}\n".as_bytes(),
+        "\u{fdd0}\n".as_bytes(),
+        "\u{378}\n".as_bytes(),
+        "\u{e0065}\n".as_bytes(),
+    ] {
+        if let Ok(s) = std::str::from_utf8(input) {
+            assert!(!is_text(s), "input {:?}", input);
+        }
+        let text = to_text(input);
+        assert!(matches!(text, Cow::Owned(_)), "input {:?}", input);
+        assert_eq!(text, via_reader(input), "input {:?}", input);
+        assert!(is_text(&text), "input {:?}", input);
+    }
+}
@@ -0,0 +1,86 @@
+use io_ext_adapters::{StdReader, StdWriter};
+use std::io::{Read, Write};
+use text_streams::{InputProfile, LineEndings, TextReader, TextWriter};
+
+fn read(input: &[u8]) -> (String, InputProfile) {
+    let mut reader = TextReader::new(StdReader::generic(input));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    (s, reader.input_profile())
+}
+
+#[test]
+fn test_line_endings() {
+    for (input, line_endings) in &[
+        (&b""[..], LineEndings::None),
+        (b"hello", LineEndings::None),
+        (b"hello\n", LineEndings::Lf),
+        (b"a\nb\n", LineEndings::Lf),
+        (b"a\r\nb\r\n", LineEndings::Crlf),
+        (b"a\rb\r", LineEndings::LoneCr),
+        (b"a\r\nb\n", LineEndings::Mixed),
+        (b"a\rb\n", LineEndings::Mixed),
+    ] {
+        let (_, profile) = read(input);
+        assert_eq!(profile.line_endings, *line_endings, "input {:?}", input);
+        assert!(!profile.bom, "input {:?}", input);
+        assert!(!profile.escape_sequences, "input {:?}", input);
+        assert_eq!(
+            profile.replacements,
+            *line_endings == LineEndings::LoneCr || *input == b"a\rb\n",
+            "input {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_bom_escapes_and_replacements() {
+    let (_, profile) = read("\u{feff}hello\n".as_bytes());
+    assert!(profile.bom);
+    assert!(!profile.replacements);
+
+    // A BOM anywhere else is replaced, rather than removed.
+    let (_, profile) = read("a\u{feff}b\n".as_bytes());
+    assert!(!profile.bom);
+    assert!(profile.replacements);
+
+    let (_, profile) = read(b"\x1b[31mred\x1b[0m\n");
+    assert!(profile.escape_sequences);
+    assert!(!profile.replacements);
+
+    for input in &[
+        &b"\xff\n"[..],
+        b"\x07\n",
+        b"\x0c\n",
+        "\u{fdd0}\n".as_bytes(),
+        "\u{30a}\n".as_bytes(),
+        "\u{149}\n".as_bytes(),
+    ] {
+        let (_, profile) = read(input);
+        assert!(profile.replacements, "input {:?}", input);
+        assert!(!profile.escape_sequences, "input {:?}", input);
+    }
+
+    // Normalization isn't counted as a replacement.
+    let (s, profile) = read("A\u{30a}\n".as_bytes());
+    assert_eq!(s, "\u{c5}\n");
+    assert!(!profile.replacements);
+}
+
+#[test]
+fn test_round_trip() {
+    for input in &[
+        &b"a\nb\n"[..],
+        b"a\r\nb\r\n",
+        "\u{feff}a\nb\n".as_bytes(),
+        "\u{feff}a\r\nb\r\n".as_bytes(),
+    ] {
+        let (s, profile) = read(input);
+        let mut writer =
+            TextWriter::with_input_profile(StdWriter::new(Vec::<u8>::new()), &profile).unwrap();
+        writer.write_all(s.as_bytes()).unwrap();
+        let inner = writer.close_into_inner().unwrap();
+        assert_eq!(inner.get_ref().as_slice(), *input);
+    }
+}
@@ -0,0 +1,120 @@
+use io_ext::ReadExt;
+use io_ext_adapters::StdReader;
+use std::io::{self, Read};
+use text_streams::{is_text, TextReader};
+
+/// An `io::Read` which replays a script of keystrokes, producing one
+/// keystroke per read, as a terminal in raw mode does.
+struct Script {
+    keys: Vec<&'static [u8]>,
+}
+
+impl Script {
+    fn new(keys: &[&'static [u8]]) -> Self {
+        let mut keys = keys.to_vec();
+        keys.reverse();
+        Self { keys }
+    }
+}
+
+impl Read for Script {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.keys.pop() {
+            Some(key) => {
+                buf[..key.len()].copy_from_slice(key);
+                Ok(key.len())
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+/// Run `keys` through a line-editing reader, checking the lines produced
+/// after each read, and return the complete output.
+fn run(keys: &[&'static [u8]]) -> String {
+    let mut reader = TextReader::with_line_editing(StdReader::generic(Script::new(keys)));
+    let mut output = String::new();
+    let mut buf = [0_u8; 4096];
+    loop {
+        let (size, status) = reader.read_with_status(&mut buf).unwrap();
+        output.push_str(std::str::from_utf8(&buf[..size]).unwrap());
+        if status.is_end() {
+            break;
+        }
+    }
+    assert!(is_text(&output), "output {:?}", output);
+    output
+}
+
+/// Like `run`, but with the script given as a single string of keystrokes.
+fn type_(keys: &'static str) -> String {
+    let single = run(&[keys.as_bytes()]);
+    let bytes = keys.as_bytes().chunks(1).collect::<Vec<&'static [u8]>>();
+    assert_eq!(run(&bytes), single, "keys {:?}", keys);
+    single
+}
+
+#[test]
+fn test_plain_lines() {
+    assert_eq!(type_("hello\rworld\r"), "hello\nworld\n");
+    assert_eq!(type_("crlf\r\nand lf\n"), "crlf\nand lf\n");
+    assert_eq!(type_("unfinished"), "unfinished\n");
+    assert_eq!(type_("\r\r"), "\n\n");
+}
+
+#[test]
+fn test_erase_char() {
+    assert_eq!(type_("helo\x08lo\r"), "hello\n");
+    assert_eq!(type_("helo\x7flo\r"), "hello\n");
+    assert_eq!(type_("\x7f\x7fab\x7f\x7f\x7fc\r"), "c\n");
+    assert_eq!(type_("caf\u{e9}\x7fe\r"), "cafe\n");
+}
+
+#[test]
+fn test_erase_word_and_kill_line() {
+    assert_eq!(type_("rm -rf /tmp/x \x17\x17y\r"), "rm y\n");
+    assert_eq!(type_("one two\x15three\r"), "three\n");
+    assert_eq!(type_("one\x1b[Dtwo\x15\r"), "e\n");
+}
+
+#[test]
+fn test_cursor_movement() {
+    assert_eq!(type_("wrld\x1b[D\x1b[D\x1b[Do\r"), "world\n");
+    assert_eq!(type_("acd\x1bOD\x1bODb\r"), "abcd\n");
+    assert_eq!(type_("bc\x1b[Ha\x1b[Fd\r"), "abcd\n");
+    assert_eq!(type_("ab\x1b[9Dx\x1b[9Cy\r"), "xaby\n");
+    assert_eq!(type_("abxc\x1b[2D\x1b[3~\r"), "abc\n");
+    assert_eq!(type_("abc\x1b[2D\x7f\r"), "bc\n");
+}
+
+#[test]
+fn test_other_keys() {
+    // Up and down are ignored, and other control codes are replaced.
+    assert_eq!(type_("a\x1b[Ab\x1b[B\x07c\r"), "ab\u{fffd}c\n");
+    assert_eq!(type_("alt\x1bx\r"), "alt\n");
+}
+
+#[test]
+fn test_keystroke_reads() {
+    // A terminal delivers each key, including multi-byte escape sequences
+    // and UTF-8, in its own read.
+    assert_eq!(
+        run(&[
+            b"l",
+            b"s",
+            b" ",
+            b"-",
+            b"l",
+            b"\x1b[D",
+            b"\x1b[D",
+            b"\x7f",
+            b"\xc3\xa9",
+            b"\r",
+            b"x",
+            b"\x15",
+            b"y",
+            b"\r",
+        ]),
+        "ls\u{e9}-l\ny\n"
+    );
+}
@@ -0,0 +1,167 @@
+#![cfg(any(feature = "log", feature = "tracing-subscriber"))]
+
+use io_ext_adapters::StdWriter;
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+use text_streams::{is_text, TextWriter};
+
+/// An `io::Write` whose contents can be inspected while it's owned by a
+/// logger.
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Shared {
+    fn writer(&self) -> TextWriter<StdWriter<Shared>> {
+        TextWriter::new(StdWriter::new(self.clone()))
+    }
+
+    fn contents(&self) -> String {
+        let s = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+        assert!(is_text(&s), "output {:?}", s);
+        s
+    }
+}
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn test_log() {
+    use log::{Level, Log, Record};
+    use text_streams::TextLogger;
+
+    let shared = Shared::default();
+    let logger = TextLogger::new(shared.writer());
+    let user = "mallory\n[INFO] fake: logged in as admin\x1b[2J";
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("auth")
+            .args(format_args!("login failed for {}", user))
+            .build(),
+    );
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("app")
+            .args(format_args!("bad\u{85}bytes\x7f"))
+            .build(),
+    );
+    logger.flush();
+
+    assert_eq!(
+        shared.contents(),
+        "WARN auth: login failed for mallory\u{2424}[INFO] fake: logged in as admin\u{241b}[2J\n\
+         INFO app: bad\\u{85}bytes\u{2421}\n"
+    );
+}
+
+#[cfg(feature = "tracing-subscriber")]
+#[test]
+fn test_forged_escapes() {
+    use text_streams::TextMakeWriter;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    let shared = Shared::default();
+    let make_writer = TextMakeWriter::new(shared.writer());
+    make_writer
+        .make_writer()
+        .write_all(b"real \x1b[2J literal \\x1b[2J\n")
+        .unwrap();
+    make_writer
+        .make_writer()
+        .write_all(b"real \xff literal \\xff\n")
+        .unwrap();
+    make_writer
+        .make_writer()
+        .write_all("real a\nb literal a\u{2424}b\n".as_bytes())
+        .unwrap();
+    make_writer
+        .make_writer()
+        .write_all("real \x1b\x07 literal \u{241b}\u{2407}\n".as_bytes())
+        .unwrap();
+    make_writer
+        .make_writer()
+        .write_all("real a\r\nb literal a\u{240d}\u{2424}b\n".as_bytes())
+        .unwrap();
+    make_writer
+        .make_writer()
+        .write_all(b"trailing cr\nb\r")
+        .unwrap();
+
+    assert_eq!(
+        shared.contents(),
+        "real \u{241b}[2J literal \\\\x1b[2J\n\
+         real \\xff literal \\\\xff\n\
+         real a\u{2424}b literal a\\u{2424}b\n\
+         real \u{241b}\u{2407} literal \\u{241b}\\u{2407}\n\
+         real a\u{240d}\u{2424}b literal a\\u{240d}\\u{2424}b\n\
+         trailing cr\u{2424}b\u{240d}\n"
+    );
+}
+
+#[cfg(feature = "tracing-subscriber")]
+#[test]
+fn test_make_writer() {
+    use text_streams::TextMakeWriter;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    let shared = Shared::default();
+    let make_writer = TextMakeWriter::new(shared.writer());
+    let mut writer = make_writer.make_writer();
+    writer.write_all(b" INFO user=eve\r\nINFO forged").unwrap();
+    writer.write_all(b" \x1b]0;title\x07\n").unwrap();
+    drop(writer);
+    make_writer
+        .make_writer()
+        .write_all(b" INFO \xffsecond\n")
+        .unwrap();
+
+    assert_eq!(
+        shared.contents(),
+        " INFO user=eve\u{240d}\u{2424}INFO forged \u{241b}]0;title\u{2407}\n\
+         \u{20}INFO \\xffsecond\n"
+    );
+
+    let shared = Shared::default();
+    let make_writer = TextMakeWriter::with_multi_line_records(shared.writer());
+    let mut writer = make_writer.make_writer();
+    writer.write_all(b"two\nlines\x08").unwrap();
+    writer.flush().unwrap();
+    assert_eq!(shared.contents(), "two\nlines\u{2408}\n");
+}
+
+#[cfg(feature = "tracing-subscriber")]
+#[test]
+fn test_tracing() {
+    use text_streams::TextMakeWriter;
+
+    let shared = Shared::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(TextMakeWriter::new(shared.writer()))
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .finish();
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(user = "eve", "first\u{85}");
+        tracing::warn!("second");
+    });
+
+    let contents = shared.contents();
+    let lines = contents.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "output {:?}", contents);
+    assert!(lines[0].contains("first"), "{:?}", lines[0]);
+    assert!(lines[0].contains("user=\"eve\""), "{:?}", lines[0]);
+    assert!(lines[1].ends_with("second"), "{:?}", lines[1]);
+}
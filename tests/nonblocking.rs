@@ -0,0 +1,232 @@
+use io_ext::{ReadExt, Status, WriteExt};
+use io_ext_adapters::StdWriter;
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+};
+use text_streams::{to_text, TextReader, TextWriter, Utf8Reader, NORMALIZATION_BUFFER_SIZE};
+
+/// One step of a `Script`.
+enum Event {
+    Data(Vec<u8>),
+    WouldBlock,
+    Interrupted,
+}
+
+/// A `ReadExt` which delivers its input as a nonblocking stream would,
+/// following a script of data and errors.
+struct Script(VecDeque<Event>);
+
+impl Script {
+    /// Deliver `input` in pieces of `size` bytes, with a `WouldBlock` after
+    /// each piece, and an `Interrupted` after every third.
+    fn new(input: &[u8], size: usize) -> Self {
+        let mut events = VecDeque::new();
+        for (i, piece) in input.chunks(size).enumerate() {
+            events.push_back(Event::Data(piece.to_vec()));
+            events.push_back(Event::WouldBlock);
+            if i % 3 == 2 {
+                events.push_back(Event::Interrupted);
+            }
+        }
+        Self(events)
+    }
+}
+
+impl Read for Script {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_with_status(buf).map(|(size, _)| size)
+    }
+}
+
+impl ReadExt for Script {
+    fn read_with_status(&mut self, buf: &mut [u8]) -> io::Result<(usize, Status)> {
+        match self.0.pop_front() {
+            None => Ok((0, Status::End)),
+            Some(Event::WouldBlock) => Err(io::ErrorKind::WouldBlock.into()),
+            Some(Event::Interrupted) => Err(io::ErrorKind::Interrupted.into()),
+            Some(Event::Data(mut data)) => {
+                let size = data.len().min(buf.len());
+                buf[..size].copy_from_slice(&data[..size]);
+                if size != data.len() {
+                    self.0.push_front(Event::Data(data.split_off(size)));
+                }
+                Ok((size, Status::active()))
+            }
+        }
+    }
+}
+
+/// Read everything from `reader` into a `String` using buffers of `size`
+/// bytes, retrying after `WouldBlock` and `Interrupted`.
+fn read_retrying(reader: &mut impl ReadExt, size: usize) -> String {
+    let mut output = Vec::new();
+    let mut buf = vec![0_u8; size];
+    loop {
+        match reader.read_with_status(&mut buf) {
+            Ok((size, status)) => {
+                output.extend_from_slice(&buf[..size]);
+                if status.is_end() {
+                    return String::from_utf8(output).unwrap();
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+}
+
+const INPUTS: &[&[u8]] = &[
+    b"hello\r\nworld\r\n",
+    b"caf\xc3\xa9 e\xcc\x81\xcc\xa3 \xe2\x82\xac \xf0\x9f\x98\x80\n",
+    b"\xff\xc3 \xe2\x82 \xf0\x9f\x98\n",
+    b"\x1b[31mred\x1b[0m\tand\x07 \xef\xbb\xbf done",
+    b"\xef\xbb\xbfbom\rcr\n\n",
+];
+
+#[test]
+fn test_read_one_byte_at_a_time() {
+    for input in INPUTS {
+        let mut reader = TextReader::new(Script::new(input, 1));
+        assert_eq!(
+            read_retrying(&mut reader, NORMALIZATION_BUFFER_SIZE),
+            to_text(input),
+            "input: {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_read_queued_output() {
+    // Each line is longer than the read buffer, so output is still queued
+    // when the underlying stream reports `WouldBlock`.
+    let mut input = Vec::new();
+    for i in 0..3 {
+        input.resize(input.len() + NORMALIZATION_BUFFER_SIZE * 3 / 2, b'a' + i);
+        input.extend_from_slice(b"\xcc\x81\n");
+    }
+    let mut reader = TextReader::new(Script::new(&input, 200));
+    assert_eq!(
+        read_retrying(&mut reader, NORMALIZATION_BUFFER_SIZE),
+        to_text(&input)
+    );
+}
+
+#[test]
+fn test_utf8_read_replacements() {
+    // Each invalid byte expands to three bytes, so replacements are still
+    // pending when the underlying stream reports `WouldBlock`.
+    let input = b"a\xff\xff\xff\xff\xff\xc3\xa9\xff\xff\xe2\x82\xffz";
+    for size in 1..4 {
+        let mut reader = Utf8Reader::new(Script::new(input, size));
+        assert_eq!(
+            read_retrying(&mut reader, 4),
+            String::from_utf8_lossy(input),
+            "size: {}",
+            size
+        );
+    }
+}
+
+/// A `WriteExt` which accepts output as a nonblocking stream would,
+/// accepting at most `limit` bytes at a time, and failing with
+/// `WouldBlock` or `Interrupted` between writes. Flushes always succeed, as
+/// they would once everything is written.
+struct Trickle {
+    output: Vec<u8>,
+    limit: usize,
+    step: usize,
+    abandoned: bool,
+}
+
+impl Trickle {
+    fn new(limit: usize) -> Self {
+        Self {
+            output: Vec::new(),
+            limit,
+            step: 0,
+            abandoned: false,
+        }
+    }
+
+    /// Return the result of the next step, if it's an error.
+    fn next_step(&mut self) -> io::Result<()> {
+        self.step += 1;
+        match self.step % 4 {
+            1 => Err(io::ErrorKind::WouldBlock.into()),
+            3 => Err(io::ErrorKind::Interrupted.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Write for Trickle {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.next_step()?;
+        let size = buf.len().min(self.limit);
+        self.output.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WriteExt for Trickle {
+    fn flush_with_status(&mut self, _status: Status) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn abandon(&mut self) {
+        self.abandoned = true;
+    }
+}
+
+/// Call `f` until it doesn't fail with `WouldBlock` or `Interrupted`.
+fn retry(mut f: impl FnMut() -> io::Result<()>) {
+    loop {
+        match f() {
+            Ok(()) => return,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+}
+
+#[test]
+fn test_write_trickle() {
+    let pieces = [
+        "hello\n",
+        "caf\u{e9} e\u{301}\u{323} ",
+        "\u{20ac} \u{1f600}",
+        "\n",
+        "\u{301}combining first\n",
+        "the end\n",
+    ];
+
+    let mut expected = TextWriter::new(StdWriter::new(Vec::<u8>::new()));
+    for piece in &pieces {
+        expected.write_str(piece).unwrap();
+    }
+    let expected = expected.close_into_inner().unwrap().get_ref().to_vec();
+
+    for limit in 1..5 {
+        let mut writer = TextWriter::new(Trickle::new(limit));
+        for piece in &pieces {
+            retry(|| writer.write_str(piece));
+        }
+        retry(|| writer.flush_with_status(Status::End));
+        let inner = writer.close_into_inner().unwrap();
+        assert!(!inner.abandoned);
+        assert_eq!(
+            String::from_utf8(inner.output).unwrap(),
+            String::from_utf8(expected.clone()).unwrap(),
+            "limit: {}",
+            limit
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use io_ext_adapters::StdReader;
+use std::io::Read;
+use text_streams::{OffsetMap, TextReader};
+
+mod common;
+
+use common::OneByte;
+
+fn read(input: &[u8], one_byte: bool) -> (String, OffsetMap) {
+    let mut s = String::new();
+    if one_byte {
+        let mut reader = TextReader::new(StdReader::generic(OneByte(input)));
+        reader.record_offset_map();
+        reader.read_to_string(&mut s).unwrap();
+        (s, reader.take_offset_map())
+    } else {
+        let mut reader = TextReader::new(StdReader::generic(input));
+        reader.record_offset_map();
+        reader.read_to_string(&mut s).unwrap();
+        (s, reader.take_offset_map())
+    }
+}
+
+/// Read `input` with and without an offset map and with both buffering
+/// strategies, check that the output is the same, and check that the map
+/// covers all of the input and output.
+fn check(input: &[u8]) -> (String, OffsetMap) {
+    let mut plain = String::new();
+    TextReader::new(StdReader::generic(input))
+        .read_to_string(&mut plain)
+        .unwrap();
+
+    let (s, map) = read(input, false);
+    assert_eq!(s, plain, "input {:?}", input);
+    let (one_byte_s, one_byte_map) = read(input, true);
+    assert_eq!(one_byte_s, plain, "input {:?}", input);
+    assert_eq!(one_byte_map, map, "input {:?}", input);
+
+    let mut output = 0;
+    let mut input_offset = 0;
+    for span in map.spans() {
+        assert_eq!(span.output.start, output, "input {:?}", input);
+        assert_eq!(span.input.start, input_offset, "input {:?}", input);
+        if span.exact {
+            assert_eq!(
+                &s.as_bytes()[span.output.start as usize..span.output.end as usize],
+                &input[span.input.start as usize..span.input.end as usize],
+                "input {:?}",
+                input
+            );
+        }
+        output = span.output.end;
+        input_offset = span.input.end;
+    }
+    assert_eq!(output, s.len() as u64, "input {:?}", input);
+    assert_eq!(input_offset, input.len() as u64, "input {:?}", input);
+
+    (s, map)
+}
+
+#[test]
+fn test_plain() {
+    let (s, map) = check(b"hello\nworld\n");
+    assert_eq!(s, "hello\nworld\n");
+    assert_eq!(map.spans().len(), 1);
+    assert!(map.spans()[0].exact);
+    assert_eq!(map.to_input(6..11), Some(6..11));
+}
+
+#[test]
+fn test_empty() {
+    let (s, map) = check(b"");
+    assert_eq!(s, "");
+    assert!(map.is_empty());
+    assert_eq!(map.to_input(0..0), None);
+}
+
+#[test]
+fn test_bom() {
+    let (s, map) = check("\u{feff}hello\n".as_bytes());
+    assert_eq!(s, "hello\n");
+    assert_eq!(map.to_input(0..1), Some(0..4));
+    assert_eq!(map.to_input(1..5), Some(4..8));
+}
+
+#[test]
+fn test_crlf() {
+    let (s, map) = check(b"one\r\ntwo\r\n");
+    assert_eq!(s, "one\ntwo\n");
+    assert_eq!(map.to_input(3..4), Some(3..5));
+    assert_eq!(map.to_input(4..7), Some(5..8));
+    assert_eq!(map.to_input(4..8), Some(5..10));
+}
+
+#[test]
+fn test_escape_sequences() {
+    let (s, map) = check(b"red \x1b[31mtext\x1b[0m\n");
+    assert_eq!(s, "red text\n");
+    assert_eq!(map.to_input(0..4), Some(0..4));
+    assert_eq!(map.to_input(5..8), Some(10..13));
+    assert_eq!(map.to_input(4..5), Some(4..10));
+
+    // A trailing escape sequence produces no output, but is still covered.
+    let (s, map) = check(b"text\x1b[0m");
+    assert_eq!(s, "text\n");
+    assert_eq!(map.to_input(4..5), Some(4..8));
+}
+
+#[test]
+fn test_composition() {
+    let (s, map) = check("cafe\u{301} ok\n".as_bytes());
+    assert_eq!(s, "caf\u{e9} ok\n");
+    assert_eq!(map.to_input(3..5), Some(3..6));
+    assert_eq!(map.to_input(5..6), Some(6..7));
+    assert!(
+        !map.spans()
+            .iter()
+            .find(|span| span.output == (3..5))
+            .unwrap()
+            .exact
+    );
+}
+
+#[test]
+fn test_expansion() {
+    let (s, map) = check("x\u{149}y\n".as_bytes());
+    assert_eq!(s, "x\u{2bc}ny\n");
+    assert_eq!(map.to_input(1..4), Some(1..3));
+    assert_eq!(map.to_input(4..5), Some(3..4));
+    assert_eq!(map.to_input(2..3), Some(1..3));
+}
+
+#[test]
+fn test_long_runs() {
+    // Runs with nowhere normalization can be split without looking at what
+    // follows: combining marks, which the normalizer breaks up with CGJs,
+    // and characters which aren't in NFC or may compose with what precedes.
+    let marks = format!("a{}\n", "\u{301}\u{316}".repeat(40));
+    let (s, _) = check(marks.as_bytes());
+    assert_eq!(s.matches('\u{34f}').count(), 2);
+    for run in &[
+        format!("x{}\n", "\u{2126}".repeat(80)),
+        format!("\u{1100}{}\n", "\u{1161}".repeat(80)),
+        format!("\u{1100}{}\n", "\u{1161}\u{11a8}".repeat(40)),
+        format!("x{}\n", "\u{2126}\u{301}\u{1161}".repeat(30)),
+        format!("\u{1f3f4}{}\n", "\u{e0067}".repeat(80)),
+    ] {
+        let (s, _) = check(run.as_bytes());
+        assert!(!s.contains('\u{34f}'), "input {:?}", run);
+    }
+}
+
+#[test]
+fn test_invalid_utf8() {
+    let (s, map) = check(b"a\xf0\x90\x80b\xffc\n");
+    assert_eq!(s, "a\u{fffd}b\u{fffd}c\n");
+    assert_eq!(map.to_input(1..4), Some(1..4));
+    assert_eq!(map.to_input(4..5), Some(4..5));
+    assert_eq!(map.to_input(5..8), Some(5..6));
+    assert_eq!(map.to_input(8..9), Some(6..7));
+}
+
+#[test]
+fn test_control_codes() {
+    let (s, map) = check(b"a\x07b\rc\x0cd");
+    assert_eq!(s, "a\u{fffd}b\u{fffd}c d\n");
+    assert_eq!(map.to_input(1..4), Some(1..2));
+    assert_eq!(map.to_input(5..8), Some(3..4));
+    assert_eq!(map.to_input(9..10), Some(5..6));
+    assert_eq!(map.to_input(11..12), Some(7..7));
+}
+
+#[test]
+fn test_mixed() {
+    let mut input = Vec::new();
+    for i in 0..200 {
+        input.extend_from_slice(format!("line {} e\u{301}\u{149}\r\n", i).as_bytes());
+        input.extend_from_slice(b"\x1b[1m\xc0\t\xe2\x80\n");
+    }
+    check(&input);
+}
+
+#[test]
+fn test_take_per_read() {
+    let input = b"hello\r\nworld\r\n".repeat(1000);
+    let mut reader = TextReader::new(StdReader::generic(&input[..]));
+    reader.record_offset_map();
+
+    let mut spans = Vec::new();
+    let mut buf = [0_u8; 1024];
+    let mut total = 0;
+    loop {
+        let n = reader.read(&mut buf).unwrap();
+        spans.extend_from_slice(reader.take_offset_map().spans());
+        if n == 0 {
+            break;
+        }
+        total += n as u64;
+    }
+    assert!(reader.offset_map().is_empty());
+    assert_eq!(spans.last().unwrap().output.end, total);
+    assert_eq!(spans.last().unwrap().input.end, input.len() as u64);
+}
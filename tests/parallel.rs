@@ -0,0 +1,74 @@
+#![cfg(feature = "rayon")]
+
+use text_streams::{to_text, to_text_parallel, to_text_parallel_with_chunk_size};
+
+/// Check that every chunk size produces the same output as sequential
+/// translation.
+fn check(input: &[u8]) {
+    let expected = to_text(input);
+    for chunk_size in 1..=input.len() + 1 {
+        assert_eq!(
+            to_text_parallel_with_chunk_size(input, chunk_size),
+            expected,
+            "input {:?}, chunk size {}",
+            input,
+            chunk_size
+        );
+    }
+    assert_eq!(to_text_parallel(input), expected, "input {:?}", input);
+}
+
+#[test]
+fn test_simple() {
+    check(b"");
+    check(b"\n");
+    check(b"hello\nworld\n");
+    check(b"no final newline");
+    check(b"\n\n\nblank lines\n\n");
+}
+
+#[test]
+fn test_bom_and_line_endings() {
+    check("\u{feff}bom\nline\n".as_bytes());
+    check("line\n\u{feff}not a bom\n".as_bytes());
+    check(b"crlf\r\nline\r\nendings\r\n");
+    check(b"lone\rcr\n\rat start\n\r");
+}
+
+#[test]
+fn test_escape_sequences_across_lines() {
+    check(b"a\x1b]0;title\nstill title\x07b\nc\n");
+    check(b"a\x1b]unterminated\nosc\nsequence\n");
+    check(b"a\x1b[[\nb\nc\n");
+    check(b"esc at end\x1b\nnext\n");
+    check(b"csi\x1b[1\n2m\n");
+}
+
+#[test]
+fn test_normalization() {
+    check("e\u{301}\n\u{301}leading mark\nx\u{149}\n".as_bytes());
+    check("\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}\nflag\n".as_bytes());
+}
+
+#[test]
+fn test_invalid_utf8() {
+    check(b"a\xe2\x80\nb\xff\nc\xf0\x90\x80");
+}
+
+#[test]
+fn test_large() {
+    let mut input = Vec::new();
+    for i in 0..10_000 {
+        input.extend_from_slice(format!("line {} caf\u{65}\u{301}\r\n", i).as_bytes());
+        if i % 1000 == 0 {
+            input.extend_from_slice(b"\x1b]spans\nlines\x07");
+        }
+    }
+    let expected = to_text(&input);
+    for chunk_size in &[1, 100, 4096, 1 << 20] {
+        assert_eq!(
+            to_text_parallel_with_chunk_size(&input, *chunk_size),
+            expected
+        );
+    }
+}
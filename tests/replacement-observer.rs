@@ -0,0 +1,333 @@
+use io_ext_adapters::StdReader;
+use std::{
+    io::{self, Read},
+    sync::{Arc, Mutex},
+};
+use text_streams::{
+    validate, TextReader, TextTransform, TransformStage, Utf8Reader, Violation, ViolationKind,
+};
+
+mod common;
+
+use common::OneByte;
+
+type Events = Arc<Mutex<Vec<(Violation, Vec<u8>)>>>;
+
+fn recorder() -> (Events, impl FnMut(&Violation, &[u8]) + Send) {
+    let events = Events::default();
+    let clone = Arc::clone(&events);
+    (events, move |violation: &Violation, original: &[u8]| {
+        clone
+            .lock()
+            .unwrap()
+            .push((violation.clone(), original.to_vec()))
+    })
+}
+
+#[test]
+fn test_utf8_reader() {
+    let input = b"a\xffb\n\xe2\x82";
+    for one_byte in &[false, true] {
+        let (events, observer) = recorder();
+        let mut s = String::new();
+        let counts = if *one_byte {
+            let mut reader = Utf8Reader::new(StdReader::generic(OneByte(input)));
+            reader.set_replacement_observer(observer);
+            reader.read_to_string(&mut s).unwrap();
+            reader.replacement_counts()
+        } else {
+            let mut reader = Utf8Reader::new(StdReader::generic(&input[..]));
+            reader.set_replacement_observer(observer);
+            reader.read_to_string(&mut s).unwrap();
+            reader.replacement_counts()
+        };
+        assert_eq!(s, "a\u{fffd}b\n\u{fffd}");
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0.kind, ViolationKind::InvalidUtf8);
+        assert_eq!(events[0].0.offset, 1);
+        assert_eq!((events[0].0.line, events[0].0.column), (1, 2));
+        assert_eq!(events[0].0.replacement, "\u{fffd}");
+        assert_eq!(events[0].1, b"\xff");
+        assert_eq!(events[1].0.offset, 4);
+        assert_eq!((events[1].0.line, events[1].0.column), (2, 1));
+        assert_eq!(events[1].1, b"\xe2\x82");
+
+        assert_eq!(counts.get(ViolationKind::InvalidUtf8), 2);
+        assert_eq!(counts.total(), 2);
+    }
+}
+
+/// Check that a `TextReader`'s observer reports what `validate` reports for
+/// `input`, whether it's read all at once or one byte at a time.
+fn assert_matches_validate(input: &[u8]) {
+    let expected = validate(StdReader::generic(input), usize::MAX).unwrap();
+
+    for one_byte in &[false, true] {
+        let (events, observer) = recorder();
+        let mut s = String::new();
+        let counts = if *one_byte {
+            let mut reader = TextReader::new(StdReader::generic(OneByte(input)));
+            reader.set_replacement_observer(observer);
+            reader.read_to_string(&mut s).unwrap();
+            reader.replacement_counts()
+        } else {
+            let mut reader = TextReader::new(StdReader::generic(input));
+            reader.set_replacement_observer(observer);
+            reader.read_to_string(&mut s).unwrap();
+            reader.replacement_counts()
+        };
+
+        let events = events.lock().unwrap();
+        let violations = events.iter().map(|(v, _)| v.clone()).collect::<Vec<_>>();
+        assert_eq!(violations, expected.violations, "input {:?}", input);
+        assert_eq!(counts.total(), violations.len() as u64, "input {:?}", input);
+
+        for (violation, original) in events.iter() {
+            let offset = violation.offset as usize;
+            match violation.codepoint {
+                Some(c) => assert_eq!(
+                    original.as_slice(),
+                    c.to_string().as_bytes(),
+                    "input {:?}",
+                    input
+                ),
+                None if violation.kind == ViolationKind::InvalidUtf8 => assert_eq!(
+                    original.as_slice(),
+                    &input[offset..offset + original.len()],
+                    "input {:?}",
+                    input
+                ),
+                None => assert!(original.is_empty(), "input {:?}", input),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_text_reader_matches_validate() {
+    for input in &[
+        &b"hello\n"[..],
+        b"a\xffb\x07c\n",
+        b"\x1b[31mred\x1b[0m\r\nx\ry\n",
+        "\u{feff}\u{30a}x\u{fdd0}\u{e000}\u{149}\n".as_bytes(),
+        "A\u{30a}\u{2126}\n".as_bytes(),
+        b"no newline\xe2\x82",
+        "\u{2126}\u{e007f}".as_bytes(),
+        "\u{2026}m\u{301}\u{e0067}\u{2126}\u{1161}".as_bytes(),
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e007f}\u{2126}\n".as_bytes(),
+    ] {
+        assert_matches_validate(input);
+    }
+}
+
+/// Pieces to generate inputs from, chosen to exercise how the Text rules
+/// and normalization interact.
+const PIECES: &[&[u8]] = &[
+    b"a",
+    b"\n",
+    b"\r",
+    b"\x07",
+    b"\x1b",
+    b"[",
+    b"\xff",
+    b"\xe2\x82",
+    "\u{feff}".as_bytes(),
+    "\u{301}".as_bytes(),
+    "\u{327}".as_bytes(),
+    "\u{2126}".as_bytes(),
+    "\u{212b}".as_bytes(),
+    "\u{1100}".as_bytes(),
+    "\u{1161}".as_bytes(),
+    "\u{1f3f4}".as_bytes(),
+    "\u{e0067}".as_bytes(),
+    "\u{e007f}".as_bytes(),
+    "\u{e0001}".as_bytes(),
+    "\u{fdd0}".as_bytes(),
+    "\u{149}".as_bytes(),
+    "\u{2028}".as_bytes(),
+];
+
+#[test]
+fn test_text_reader_matches_validate_generated() {
+    // A fixed-seed xorshift generator, so that failures are reproducible.
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..2000 {
+        let len = next() % 12;
+        let mut input = Vec::new();
+        for _ in 0..len {
+            input.extend_from_slice(PIECES[(next() % PIECES.len() as u64) as usize]);
+        }
+        assert_matches_validate(&input);
+    }
+}
+
+#[test]
+fn test_counts_without_observer() {
+    let mut reader = TextReader::new(StdReader::generic(&b"\x07\n"[..]));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert_eq!(reader.replacement_counts().total(), 0);
+
+    let mut reader = TextReader::new(StdReader::generic(&b"\x07\x07\xff\n"[..]));
+    reader.set_replacement_observer(|_: &Violation, _: &[u8]| ());
+    reader.read_to_string(&mut s).unwrap();
+    let counts = reader.replacement_counts();
+    assert_eq!(counts.get(ViolationKind::ControlCode), 2);
+    assert_eq!(counts.get(ViolationKind::InvalidUtf8), 1);
+    assert_eq!(counts.total(), 3);
+}
+
+fn observe<Inner: io_ext::ReadExt>(
+    mut reader: TextReader<Inner>,
+) -> (String, Vec<(Violation, Vec<u8>)>) {
+    let (events, observer) = recorder();
+    reader.set_replacement_observer(observer);
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    let events = events.lock().unwrap().clone();
+    assert_eq!(reader.replacement_counts().total(), events.len() as u64);
+    (s, events)
+}
+
+/// Summarize `events` as their kinds, offsets, replacements, and original
+/// bytes.
+fn summary(events: &[(Violation, Vec<u8>)]) -> Vec<(ViolationKind, u64, &str, &[u8])> {
+    events
+        .iter()
+        .map(|(v, original)| {
+            (
+                v.kind,
+                v.offset,
+                v.replacement.as_str(),
+                original.as_slice(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_control_pictures() {
+    let input = b"a\x07b\xff\rc\r\n";
+    let (s, events) = observe(TextReader::with_control_pictures(StdReader::generic(
+        &input[..],
+    )));
+    assert_eq!(s, "a\u{2407}b\\xff\u{240d}c\n");
+    assert_eq!(
+        summary(&events),
+        [
+            (ViolationKind::ControlCode, 1, "\u{2407}", &b"\x07"[..]),
+            (ViolationKind::InvalidUtf8, 3, "\\xff", &b"\xff"[..]),
+            (ViolationKind::CarriageReturn, 4, "\u{240d}", &b"\r"[..]),
+            (ViolationKind::CarriageReturn, 6, "", &b"\r"[..]),
+        ]
+    );
+}
+
+#[test]
+fn test_line_editing() {
+    // The backspace and the cursor movement are interpreted, the up arrow
+    // is discarded, and the Text rules replace the Ctrl-A which the line
+    // editor left in the line.
+    let input = b"ab\x7fc\x1b[A\x1b[D\x01\n";
+    let (s, events) = observe(TextReader::with_line_editing(StdReader::generic(
+        &input[..],
+    )));
+    assert_eq!(s, "a\u{fffd}c\n");
+    assert_eq!(
+        summary(&events),
+        [
+            (ViolationKind::EscapeSequence, 4, "", &b"\x1b"[..]),
+            (ViolationKind::ControlCode, 1, "\u{fffd}", &b"\x01"[..]),
+        ]
+    );
+}
+
+#[test]
+fn test_transcript() {
+    // The carriage return and overstrikes are interpreted, and the color
+    // sequence and bell are discarded.
+    let input = b"ab\rX\x1b[31mc\x07\xff\n";
+    let (s, events) = observe(TextReader::with_transcript(StdReader::generic(&input[..])));
+    assert_eq!(s, "Xc\u{fffd}\n");
+    assert_eq!(
+        summary(&events),
+        [
+            (ViolationKind::EscapeSequence, 4, "", &b"\x1b"[..]),
+            (ViolationKind::ControlCode, 10, "", &b"\x07"[..]),
+            (ViolationKind::InvalidUtf8, 11, "\u{fffd}", &b"\xff"[..]),
+        ]
+    );
+}
+
+struct XToBell;
+
+impl TextTransform for XToBell {
+    fn transform(&mut self, c: char, _lookahead: &[char], output: &mut String) -> io::Result<()> {
+        output.push(if c == 'x' { '\u{7}' } else { c });
+        Ok(())
+    }
+}
+
+#[test]
+fn test_before_normalization_transform() {
+    // The control code is produced by the transform, so it's only seen by
+    // the Text rules, and its offset is in the transform's output.
+    let input = b"ax\xffy\n";
+    let mut reader = TextReader::new(StdReader::generic(&input[..]));
+    reader.add_transform(TransformStage::BeforeNormalization, XToBell);
+    let (s, events) = observe(reader);
+    assert_eq!(s, "a\u{fffd}\u{fffd}y\n");
+    assert_eq!(
+        summary(&events),
+        [
+            (ViolationKind::InvalidUtf8, 2, "\u{fffd}", &b"\xff"[..]),
+            (ViolationKind::ControlCode, 1, "\u{fffd}", &b"\x07"[..]),
+        ]
+    );
+}
+
+#[test]
+fn test_replacement_text() {
+    // Replacements report what was actually produced, including by the
+    // replacements made after normalization.
+    let input = "x\u{149}\u{fdd0}\u{feff}\u{2126}\n";
+    let (s, events) = observe(TextReader::new(StdReader::generic(input.as_bytes())));
+    assert_eq!(s, "x\u{2bc}n\u{fffd}\u{2060}\u{3a9}\n");
+    assert_eq!(
+        summary(&events),
+        [
+            (
+                ViolationKind::Deprecated,
+                1,
+                "\u{2bc}n",
+                "\u{149}".as_bytes()
+            ),
+            (
+                ViolationKind::Noncharacter,
+                3,
+                "\u{fffd}",
+                "\u{fdd0}".as_bytes()
+            ),
+            (
+                ViolationKind::ByteOrderMark,
+                6,
+                "\u{2060}",
+                "\u{feff}".as_bytes()
+            ),
+            (
+                ViolationKind::ObsoleteCompatibility,
+                9,
+                "\u{3a9}",
+                "\u{2126}".as_bytes()
+            ),
+        ]
+    );
+}
@@ -0,0 +1,55 @@
+//! Compile-time checks that the stream types are `Send` and `Sync` when the
+//! streams they wrap are.
+
+use io_ext::{ReadExt, ReadWriteExt, WriteExt};
+use io_ext_adapters::{StdReader, StdWriter};
+use std::{fs::File, thread};
+use text_streams::{
+    TextConcat, TextReader, TextReaderWriter, TextWriter, Utf8Reader, Utf8ReaderWriter, Utf8Writer,
+};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn readers<Inner: ReadExt + Send + Sync>() {
+    assert_send_sync::<Utf8Reader<Inner>>();
+    assert_send_sync::<TextReader<Inner>>();
+    assert_send_sync::<TextConcat<Inner>>();
+}
+
+fn writers<Inner: WriteExt + Send + Sync>() {
+    assert_send_sync::<Utf8Writer<Inner>>();
+    assert_send_sync::<TextWriter<Inner>>();
+}
+
+// There's no `ReadWriteExt` file adapter to instantiate this with, but the
+// body is still checked.
+#[allow(dead_code)]
+fn reader_writers<Inner: ReadWriteExt + Send + Sync>() {
+    assert_send_sync::<Utf8ReaderWriter<Inner>>();
+    assert_send_sync::<TextReaderWriter<Inner>>();
+}
+
+#[test]
+fn test_file_streams() {
+    readers::<StdReader<File>>();
+    writers::<StdWriter<File>>();
+}
+
+#[test]
+fn test_move_to_thread() {
+    let mut reader = TextReader::new(StdReader::generic(&b"hello\r\nworld"[..]));
+    reader.set_replacement_observer(|_: &_, _: &_| ());
+    let mut writer = TextWriter::new(StdWriter::new(Vec::new()));
+
+    let output = thread::spawn(move || {
+        use std::io::{Read, Write};
+        let mut s = String::new();
+        reader.read_to_string(&mut s).unwrap();
+        writer.write_all(s.as_bytes()).unwrap();
+        writer.close_into_inner().unwrap().get_ref().clone()
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(output, b"hello\nworld\n");
+}
@@ -0,0 +1,148 @@
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use text_streams::{TextLineStr, TextLineString, TextStr, TextString};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Rejecting {
+    #[serde(with = "text_streams::serde::reject")]
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Sanitizing {
+    #[serde(with = "text_streams::serde::sanitize")]
+    name: String,
+}
+
+fn json(name: &str) -> String {
+    serde_json::to_string(&serde_json::json!({ "name": name })).unwrap()
+}
+
+#[test]
+fn test_text_string() {
+    let s: TextString = serde_json::from_str("\"hello\\n\"").unwrap();
+    assert_eq!(s.as_str(), "hello\n");
+    assert_eq!(serde_json::to_string(&s).unwrap(), "\"hello\\n\"");
+
+    // JSON escapes newlines, so the only Text document serde_json can
+    // deserialize without copying is the empty one.
+    let s: &TextStr = serde_json::from_str("\"\"").unwrap();
+    assert_eq!(serde_json::to_string(s).unwrap(), "\"\"");
+
+    assert!(serde_json::from_str::<&TextStr>("\"hello\"").is_err());
+    assert!(serde_json::from_str::<TextString>("\"hello\"").is_err());
+    assert!(serde_json::from_str::<TextString>("\"\\u0007\\n\"").is_err());
+}
+
+#[test]
+fn test_text_line_string() {
+    let s: TextLineString = serde_json::from_str("\"hello\"").unwrap();
+    assert_eq!(s.as_str(), "hello");
+    assert_eq!(serde_json::to_string(&s).unwrap(), "\"hello\"");
+
+    let s: &TextLineStr = serde_json::from_str("\"world\"").unwrap();
+    assert_eq!(serde_json::to_string(s).unwrap(), "\"world\"");
+
+    assert!(serde_json::from_str::<TextLineString>("\"hello\\n\"").is_err());
+    assert!(serde_json::from_str::<TextLineString>("\"\\u0007\"").is_err());
+}
+
+#[test]
+fn test_reject() {
+    for name in &["", "Alice", "Alice\n", "\u{c5}sa", "caf\u{e9}"] {
+        let value: Rejecting = serde_json::from_str(&json(name)).unwrap();
+        assert_eq!(value.name, *name);
+        assert_eq!(serde_json::to_string(&value).unwrap(), json(name));
+    }
+
+    for name in &[
+        "\u{7}",
+        "a\u{1b}[31mb",
+        "A\u{30a}",
+        "\u{30a}",
+        "\u{fdd0}",
+        "\u{e000}",
+        "\u{206a}",
+        "a\r\nb",
+        "\u{feff}a",
+        "\u{202e}evil",
+        "a\u{2066}b\u{2069}",
+    ] {
+        assert!(
+            serde_json::from_str::<Rejecting>(&json(name)).is_err(),
+            "name {:?}",
+            name
+        );
+        assert!(
+            serde_json::to_string(&Rejecting {
+                name: name.to_string()
+            })
+            .is_err(),
+            "name {:?}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_sanitize() {
+    for (name, expected) in &[
+        ("", ""),
+        ("Alice", "Alice"),
+        ("Alice\n", "Alice\n"),
+        ("\u{7}", "\u{fffd}"),
+        ("a\u{1b}[31mb", "ab"),
+        ("A\u{30a}", "\u{c5}"),
+        ("\u{fdd0}x", "\u{fffd}x"),
+        ("a\r\nb", "a\nb"),
+        ("\u{feff}a", "a"),
+        ("\u{202e}evil", "\u{fffd}evil"),
+        ("a\u{2066}b\u{2069}\u{7}", "a\u{fffd}b\u{fffd}\u{fffd}"),
+        ("\u{200f}a", "\u{200f}a"),
+    ] {
+        let value: Sanitizing = serde_json::from_str(&json(name)).unwrap();
+        assert_eq!(value.name, *expected, "name {:?}", name);
+        assert_eq!(
+            serde_json::to_string(&Sanitizing {
+                name: name.to_string()
+            })
+            .unwrap(),
+            json(expected),
+            "name {:?}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_reject_bidi_controls() {
+    use text_streams::ViolationKind;
+
+    // Bidirectional marks are permitted; only the controls which affect the
+    // display order of the text around them are rejected.
+    let value: Rejecting = serde_json::from_str(&json("\u{200e}a\u{61c}")).unwrap();
+    assert_eq!(value.name, "\u{200e}a\u{61c}");
+
+    for c in ('\u{202a}'..='\u{202e}').chain('\u{2066}'..='\u{2069}') {
+        let name = format!("x\n{}y", c);
+        let error = serde_json::from_str::<Rejecting>(&json(&name))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains(&ViolationKind::BidiControl.to_string()),
+            "error {:?}",
+            error
+        );
+        assert!(error.starts_with("2:1: "), "error {:?}", error);
+    }
+
+    // The first violation is the one reported.
+    let error = serde_json::from_str::<Rejecting>(&json("\u{7}\u{202e}"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("control code"), "error {:?}", error);
+
+    // The validated string types follow the Text format, which permits them.
+    assert!(serde_json::from_str::<TextString>("\"\\u202e\\n\"").is_ok());
+}
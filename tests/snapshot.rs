@@ -0,0 +1,157 @@
+use io_ext::ReadExt;
+use io_ext_adapters::StdReader;
+use std::io::{BufRead, Read};
+use text_streams::{to_text, TextReader, TextReaderSnapshot, NORMALIZATION_BUFFER_SIZE};
+
+mod common;
+
+use common::OneByte;
+
+/// Read `reader` to the end, after `output` has already been read.
+fn finish(mut reader: impl Read, mut output: Vec<u8>) -> String {
+    reader.read_to_end(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Check that resuming from a snapshot taken after each read of `input`
+/// produces the same output as an uninterrupted read.
+fn check(input: &[u8]) {
+    let expected = to_text(input);
+    let mut reads = 0;
+    loop {
+        let mut reader = TextReader::new(StdReader::generic(OneByte(input)));
+        reader.record_snapshots();
+        let mut output = Vec::new();
+        let mut buf = [0_u8; NORMALIZATION_BUFFER_SIZE];
+        let mut ended = false;
+        for _ in 0..reads {
+            let (size, status) = reader.read_with_status(&mut buf).unwrap();
+            output.extend_from_slice(&buf[..size]);
+            ended = status.is_end();
+            if ended {
+                break;
+            }
+        }
+        if ended {
+            return;
+        }
+
+        let snapshot = reader.snapshot().unwrap();
+        let snapshot = round_trip(snapshot);
+        let offset = snapshot.offset() as usize;
+        let resumed = TextReader::resume(StdReader::generic(OneByte(&input[offset..])), &snapshot);
+        assert_eq!(
+            finish(resumed, output),
+            expected,
+            "input {:?} after {} reads",
+            input,
+            reads
+        );
+        reads += 1;
+    }
+}
+
+#[cfg(feature = "serde")]
+fn round_trip(snapshot: TextReaderSnapshot) -> TextReaderSnapshot {
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let result = serde_json::from_str(&json).unwrap();
+    assert_eq!(snapshot, result);
+    result
+}
+
+#[cfg(not(feature = "serde"))]
+fn round_trip(snapshot: TextReaderSnapshot) -> TextReaderSnapshot {
+    snapshot.clone()
+}
+
+#[test]
+fn test_lines() {
+    check(b"");
+    check(b"one\ntwo\nthree\n");
+    check(b"crlf\r\nlines\r\n\r\n");
+    check(b"no final newline");
+    check("\u{feff}bom\nthen \u{feff} text\n".as_bytes());
+}
+
+#[test]
+fn test_mid_sequence() {
+    check(b"esc\x1b[31mape\x1b]0;osc\nspanning\x07lines\n");
+    check("utf-8 \u{e9}\u{1f600}\n\u{4e2d}\u{6587}\n".as_bytes());
+    check(b"invalid\xe2\x82 and \xff\nbytes\xf0\n");
+    check(b"lone\rcr\n\rand trailing\r");
+    check("combining e\u{301}\n\u{301}leading\n".as_bytes());
+}
+
+#[test]
+fn test_long_lines() {
+    let mut input = Vec::new();
+    for i in 0..20 {
+        input.extend_from_slice(format!("{} caf\u{65}\u{301} ", i).as_bytes());
+        if i % 8 == 0 {
+            input.push(b'\n');
+        }
+    }
+    check(&input);
+}
+
+#[test]
+fn test_resume_twice() {
+    let input = b"first\nsecond \x1b[1mbold\x1b[0m\nthird\n";
+    let expected = to_text(input);
+
+    let mut reader = TextReader::new(StdReader::generic(OneByte(input)));
+    reader.record_snapshots();
+    let mut buf = [0_u8; NORMALIZATION_BUFFER_SIZE];
+    let mut output = Vec::new();
+    while output.len() < 8 {
+        let (size, _) = reader.read_with_status(&mut buf).unwrap();
+        output.extend_from_slice(&buf[..size]);
+    }
+    let snapshot = reader.snapshot().unwrap();
+
+    let offset = snapshot.offset() as usize;
+    let mut resumed = TextReader::resume(StdReader::generic(OneByte(&input[offset..])), &snapshot);
+    while output.len() < 20 {
+        let (size, _) = resumed.read_with_status(&mut buf).unwrap();
+        output.extend_from_slice(&buf[..size]);
+    }
+    let snapshot = resumed.snapshot().unwrap();
+
+    let offset = snapshot.offset() as usize;
+    let resumed = TextReader::resume(StdReader::generic(&input[offset..]), &snapshot);
+    assert_eq!(finish(resumed, output), expected);
+}
+
+#[test]
+fn test_fill_buf() {
+    let input = "alpha\nbeta\ngamma \u{3b3}\n".as_bytes();
+    let expected = to_text(input);
+    for consumed in 0..expected.len() {
+        let mut reader = TextReader::new(StdReader::generic(input));
+        reader.record_snapshots();
+        let mut output = Vec::new();
+        while output.len() < consumed {
+            let data = reader.fill_buf().unwrap();
+            let mut amt = data.len().min(consumed - output.len());
+            while !expected.is_char_boundary(output.len() + amt) {
+                amt += 1;
+            }
+            output.extend_from_slice(&data[..amt]);
+            reader.consume(amt);
+        }
+        let snapshot = reader.snapshot().unwrap();
+        let offset = snapshot.offset() as usize;
+        let resumed = TextReader::resume(StdReader::generic(&input[offset..]), &snapshot);
+        assert_eq!(finish(resumed, output), expected, "consumed {}", consumed);
+    }
+}
+
+#[test]
+fn test_unsupported() {
+    let reader = TextReader::new(StdReader::generic(&b"text\n"[..]));
+    assert!(reader.snapshot().is_err());
+
+    let mut reader = TextReader::with_transcript(StdReader::generic(&b"text\n"[..]));
+    reader.record_snapshots();
+    assert!(reader.snapshot().is_err());
+}
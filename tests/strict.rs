@@ -0,0 +1,119 @@
+use io_ext::ReadExt;
+use io_ext_adapters::StdReader;
+use std::io;
+use text_streams::{TextReader, Violation, ViolationKind};
+
+mod common;
+
+use common::OneByte;
+
+/// Read `input` in strict mode, returning the output produced before the
+/// first violation, and the violation, if any. Reads are done both all at
+/// once and one byte at a time, and must agree.
+fn read(input: &[u8]) -> (String, Option<Violation>) {
+    let whole = read_from(TextReader::with_strict_validation(StdReader::generic(
+        input,
+    )));
+    let bytewise = read_from(TextReader::with_strict_validation(StdReader::generic(
+        OneByte(input),
+    )));
+    assert_eq!(whole, bytewise, "input {:?}", input);
+    whole
+}
+
+fn read_from(mut reader: impl ReadExt) -> (String, Option<Violation>) {
+    let mut output = Vec::new();
+    let mut buf = [0_u8; 4096];
+    loop {
+        match reader.read_with_status(&mut buf) {
+            Ok((size, status)) => {
+                output.extend_from_slice(&buf[..size]);
+                if status.is_end() {
+                    return (String::from_utf8(output).unwrap(), None);
+                }
+            }
+            Err(err) => {
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+                let violation = err
+                    .get_ref()
+                    .and_then(|inner| inner.downcast_ref::<Violation>())
+                    .unwrap()
+                    .clone();
+
+                // The error is sticky.
+                let again = reader.read_with_status(&mut buf).unwrap_err();
+                assert_eq!(again.kind(), io::ErrorKind::InvalidData);
+
+                return (String::from_utf8(output).unwrap(), Some(violation));
+            }
+        }
+    }
+}
+
+fn check_violation(input: &[u8], prefix: &str, kind: ViolationKind, offset: u64) {
+    let (output, violation) = read(input);
+    let violation = violation.unwrap_or_else(|| panic!("no violation in {:?}", input));
+    assert_eq!(output, prefix, "input {:?}", input);
+    assert_eq!(violation.kind, kind, "input {:?}", input);
+    assert_eq!(violation.offset, offset, "input {:?}", input);
+}
+
+#[test]
+fn test_valid() {
+    for input in &["", "hello\n", "tab\tand\nlines\n", "caf\u{e9}\n\u{1f600}\n"] {
+        assert_eq!(read(input.as_bytes()), (input.to_string(), None));
+    }
+}
+
+#[test]
+fn test_violations() {
+    check_violation(
+        b"first\nsecond\r\n",
+        "first\nsecond",
+        ViolationKind::CarriageReturn,
+        12,
+    );
+    check_violation(
+        b"plain\n\x1b[31mred\n",
+        "plain\n",
+        ViolationKind::EscapeSequence,
+        6,
+    );
+    check_violation(
+        "ok\nnon\u{fdd0}char\n".as_bytes(),
+        "ok\nnon",
+        ViolationKind::Noncharacter,
+        6,
+    );
+    check_violation(b"bad\xffbyte\n", "bad", ViolationKind::InvalidUtf8, 3);
+    check_violation(b"bell\x07\n", "bell", ViolationKind::ControlCode, 4);
+}
+
+#[test]
+fn test_normalization() {
+    check_violation(
+        "\u{301}leading\n".as_bytes(),
+        "",
+        ViolationKind::LeadingNonstarter,
+        0,
+    );
+
+    // The "e" may be followed by a combining mark, so it isn't returned
+    // until the violation is found, and then it's excluded with it.
+    check_violation(
+        "line\ncafe\u{301}\n".as_bytes(),
+        "line\ncaf",
+        ViolationKind::NotNormalized,
+        8,
+    );
+}
+
+#[test]
+fn test_missing_final_newline() {
+    check_violation(
+        b"one\ntwo",
+        "one\ntwo",
+        ViolationKind::MissingFinalNewline,
+        7,
+    );
+}
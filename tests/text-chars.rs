@@ -0,0 +1,50 @@
+use text_streams::{TextChars, TextProfile, ViolationKind};
+
+#[test]
+fn test_text_replacements() {
+    let replaced: String = "a\u{feff}b\u{fdd0}c\n"
+        .chars()
+        .text_replacements()
+        .collect();
+    assert_eq!(replaced, "a\u{2060}b\u{fffd}c\n");
+
+    let flag = "\u{1f3f4}\u{e0067}\u{e0062}\u{e0077}\u{e006c}\u{e0073}\u{e007f}";
+    let replaced: String = flag.chars().text_replacements().collect();
+    assert_eq!(replaced, flag);
+
+    let replaced: String = flag
+        .chars()
+        .text_replacements_with_profile(TextProfile::Text2020)
+        .collect();
+    assert_ne!(replaced, flag);
+}
+
+#[test]
+fn test_text_categorize() {
+    let chars: Result<String, _> = "hello\tworld\n".chars().text_categorize().collect();
+    assert_eq!(chars.unwrap(), "hello\tworld\n");
+
+    for (input, kind, codepoint) in &[
+        ("a\u{7}b", ViolationKind::ControlCode, '\u{7}'),
+        ("\u{feff}", ViolationKind::ByteOrderMark, '\u{feff}'),
+        ("\u{212b}", ViolationKind::ObsoleteCompatibility, '\u{212b}'),
+        ("\u{fdd0}", ViolationKind::Noncharacter, '\u{fdd0}'),
+        ("\u{e000}", ViolationKind::PrivateUse, '\u{e000}'),
+        ("\u{e0065}", ViolationKind::TagCharacter, '\u{e0065}'),
+    ] {
+        let err = input
+            .chars()
+            .text_categorize()
+            .collect::<Result<String, _>>()
+            .unwrap_err();
+        assert_eq!(err.kind, *kind, "input {:?}", input);
+        assert_eq!(err.codepoint, *codepoint, "input {:?}", input);
+    }
+
+    // Errors don't stop the iterator.
+    let items: Vec<_> = "a\u{7}b".chars().text_categorize().collect();
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0], Ok('a'));
+    assert!(items[1].is_err());
+    assert_eq!(items[2], Ok('b'));
+}
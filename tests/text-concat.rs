@@ -0,0 +1,198 @@
+use io_ext::{ReadExt, Status, WriteExt};
+use io_ext_adapters::{StdReader, StdWriter};
+use std::io::{self, BufRead, Read, Write};
+use text_streams::{TextConcat, TextReader, TextWriter};
+use unicode_normalization::is_nfc_stream_safe;
+
+fn concat(inputs: &[&str]) -> String {
+    let mut reader = TextConcat::new(
+        inputs
+            .iter()
+            .map(|input| TextReader::new(StdReader::generic(input.as_bytes()))),
+    );
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert!(is_nfc_stream_safe(&s));
+    s
+}
+
+fn concat_via_fill_buf(inputs: &[&str]) -> String {
+    let mut reader = TextConcat::new(
+        inputs
+            .iter()
+            .map(|input| TextReader::new(StdReader::generic(input.as_bytes()))),
+    );
+    let mut bytes = Vec::new();
+    loop {
+        let buf = reader.fill_buf().unwrap();
+        if buf.is_empty() {
+            break;
+        }
+        let len = buf.len();
+        bytes.extend_from_slice(buf);
+        reader.consume(len);
+    }
+    String::from_utf8(bytes).unwrap()
+}
+
+fn append(writes: &[&str]) -> io::Result<String> {
+    let mut writer = TextWriter::with_append_mode(StdWriter::new(Vec::<u8>::new()));
+    for s in writes {
+        writer.write_all(s.as_bytes())?;
+    }
+    let inner = writer.close_into_inner()?;
+    let s = String::from_utf8(inner.get_ref().to_vec()).unwrap();
+    assert!(is_nfc_stream_safe(&s));
+    Ok(s)
+}
+
+#[test]
+fn test_concat() {
+    for (inputs, expected) in &[
+        (&[][..], ""),
+        (&[""][..], ""),
+        (&["", ""][..], ""),
+        (&["hello"][..], "hello\n"),
+        (&["hello", "world"][..], "hello\nworld\n"),
+        (&["hello\n", "", "world\n"][..], "hello\nworld\n"),
+        (&["\u{feff}hello", "\u{feff}world"][..], "hello\nworld\n"),
+        (&["A", "\u{30a}"][..], "A\n\u{fffd}\n"),
+        (&["\u{1100}", "\u{1161}"][..], "\u{1100}\n\u{1161}\n"),
+    ] {
+        assert_eq!(concat(inputs), *expected, "inputs {:?}", inputs);
+        assert_eq!(
+            concat_via_fill_buf(inputs),
+            *expected,
+            "inputs {:?}",
+            inputs
+        );
+    }
+}
+
+#[test]
+fn test_concat_long() {
+    let long = "hello world\n".repeat(1000);
+    let expected = format!("{}{}", long, long);
+    assert_eq!(concat(&[&long, &long]), expected);
+    assert_eq!(concat_via_fill_buf(&[&long, &long]), expected);
+}
+
+#[test]
+fn test_append() {
+    assert_eq!(append(&[]).unwrap(), "");
+    assert_eq!(append(&["hello\n"]).unwrap(), "hello\n");
+    assert_eq!(append(&["hello", " world\n"]).unwrap(), "hello world\n");
+
+    // A non-starter at the beginning of the stream is separated from
+    // whatever it's being appended to.
+    assert_eq!(append(&["\u{30a}\n"]).unwrap(), "\u{34f}\u{30a}\n");
+
+    // Writes that would compose or reorder with previous writes.
+    assert_eq!(append(&["A", "\u{30a}\n"]).unwrap(), "A\u{34f}\u{30a}\n");
+    assert_eq!(
+        append(&["a\u{301}", "\u{316}\n"]).unwrap(),
+        "\u{e1}\u{34f}\u{316}\n"
+    );
+    assert_eq!(
+        append(&["\u{1100}", "\u{1161}\n"]).unwrap(),
+        "\u{1100}\u{34f}\u{1161}\n"
+    );
+    assert_eq!(
+        append(&["\u{ac00}", "\u{11a8}\n"]).unwrap(),
+        "\u{ac00}\u{34f}\u{11a8}\n"
+    );
+
+    // Writes that wouldn't.
+    assert_eq!(append(&["A", "B\n"]).unwrap(), "AB\n");
+    assert_eq!(append(&["A\n", "B\n"]).unwrap(), "A\nB\n");
+
+    // Long sequences of non-starters stay Stream-Safe.
+    let nonstarters = "\u{300}".repeat(20);
+    let first = format!("a{}", nonstarters);
+    let second = format!("{}\n", nonstarters);
+    let output = append(&[&first, &second]).unwrap();
+    assert_eq!(output.matches('\u{34f}').count(), 1);
+
+    // Append mode doesn't relax any other rules.
+    assert!(append(&["hello"]).is_err());
+    assert!(append(&["\u{feff}hello\n"]).is_err());
+}
+
+fn append_after(existing: &str, writes: &[&str]) -> String {
+    let mut writer = TextWriter::with_append_mode_after(
+        StdWriter::new(existing.as_bytes().to_vec()),
+        existing.chars().next_back(),
+    );
+    for s in writes {
+        writer.write_all(s.as_bytes()).unwrap();
+    }
+    let inner = writer.close_into_inner().unwrap();
+    let s = String::from_utf8(inner.get_ref().to_vec()).unwrap();
+    assert!(is_nfc_stream_safe(&s));
+    s
+}
+
+#[test]
+fn test_append_to_existing() {
+    // When the existing content isn't known, anything which could compose
+    // with it is separated from it.
+    assert_eq!(append(&["\u{1161}\n"]).unwrap(), "\u{34f}\u{1161}\n");
+    assert_eq!(append(&["\u{11a8}\n"]).unwrap(), "\u{34f}\u{11a8}\n");
+    assert_eq!(append(&["\u{9be}\n"]).unwrap(), "\u{34f}\u{9be}\n");
+
+    // When it's known, a U+034F is only inserted where it's needed.
+    assert_eq!(
+        append_after("\u{1100}", &["\u{1161}\n"]),
+        "\u{1100}\u{34f}\u{1161}\n"
+    );
+    assert_eq!(
+        append_after("\u{ac00}", &["\u{11a8}\n"]),
+        "\u{ac00}\u{34f}\u{11a8}\n"
+    );
+    assert_eq!(append_after("a\n", &["\u{1161}\n"]), "a\n\u{1161}\n");
+    assert_eq!(append_after("", &["\u{1161}\n"]), "\u{1161}\n");
+    assert_eq!(append_after("a\n", &["\u{30a}\n"]), "a\n\u{34f}\u{30a}\n");
+}
+
+#[test]
+fn test_append_after_push() {
+    let mut writer = TextWriter::with_append_mode(StdWriter::new(Vec::<u8>::new()));
+    writer.write_str("A").unwrap();
+    writer.flush_with_status(Status::push()).unwrap();
+    writer.write_str("\u{30a}\n").unwrap();
+    let inner = writer.close_into_inner().unwrap();
+    assert_eq!(inner.get_ref(), "A\u{34f}\u{30a}\n".as_bytes());
+
+    // Without append mode, the non-starter is an error.
+    let mut writer = TextWriter::new(StdWriter::new(Vec::<u8>::new()));
+    writer.write_str("A").unwrap();
+    writer.flush_with_status(Status::push()).unwrap();
+    assert!(writer.write_str("\u{30a}\n").is_err());
+}
+
+/// A stream which never has any data available yet, as a nonblocking
+/// stream would report.
+struct Idle;
+
+impl Read for Idle {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl ReadExt for Idle {
+    fn read_with_status(&mut self, _buf: &mut [u8]) -> io::Result<(usize, Status)> {
+        Ok((0, Status::active()))
+    }
+}
+
+#[test]
+fn test_concat_no_data_yet() {
+    let mut reader = TextConcat::new(vec![TextReader::new(Idle)]);
+    assert!(reader.fill_buf().unwrap().is_empty());
+    let mut buf = [0_u8; text_streams::NORMALIZATION_BUFFER_SIZE];
+    assert_eq!(
+        reader.read_with_status(&mut buf).unwrap(),
+        (0, Status::active())
+    );
+}
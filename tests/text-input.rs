@@ -12,6 +12,14 @@ fn to_text(input: &str) -> String {
     s
 }
 
+fn to_text_with_unassigned_preserved(input: &str) -> String {
+    use std::io::Read;
+    let mut reader = TextReader::with_unassigned_preserved(StdReader::generic(input.as_bytes()));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    s
+}
+
 #[test]
 fn test_text_input_nfc() {
     // TODO: Test that all the following are done:
@@ -135,3 +143,102 @@ fn test_text_input_rules() {
     assert_eq!(to_text("hello\r\nworld"), "hello\nworld\n");
     assert_eq!(to_text("hello\r\nworld\r\n"), "hello\nworld\n");
 }
+
+#[test]
+fn test_emoji_tag_sequences() {
+    // Subdivision flags are permitted.
+    for flag in &[
+        // England
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}",
+        // Scotland
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0073}\u{e0063}\u{e0074}\u{e007f}",
+        // Wales
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0077}\u{e006c}\u{e0073}\u{e007f}",
+    ] {
+        assert_eq!(to_text(flag), format!("{}\n", flag));
+        assert_eq!(
+            to_text(&format!("hello {} world\n", flag)),
+            format!("hello {} world\n", flag)
+        );
+        assert_eq!(to_text(&flag.repeat(2)), format!("{}{}\n", flag, flag));
+    }
+
+    // A plain waving black flag is fine too.
+    assert_eq!(to_text("\u{1f3f4}"), "\u{1f3f4}\n");
+    assert_eq!(to_text("\u{1f3f4}\u{1f3f4}"), "\u{1f3f4}\u{1f3f4}\n");
+
+    // Stray tag characters are replaced.
+    assert_eq!(to_text("\u{e0067}\u{e007f}"), "\u{fffd}\u{fffd}\n");
+    assert_eq!(
+        to_text("A\u{e0067}\u{e0062}\u{e007f}"),
+        "A\u{fffd}\u{fffd}\u{fffd}\n"
+    );
+
+    // A tag sequence without a CANCEL TAG is replaced.
+    assert_eq!(
+        to_text("\u{1f3f4}\u{e0067}\u{e0062}"),
+        "\u{1f3f4}\u{fffd}\u{fffd}\n"
+    );
+    assert_eq!(
+        to_text("\u{1f3f4}\u{e0067}\u{e0062}x"),
+        "\u{1f3f4}\u{fffd}\u{fffd}x\n"
+    );
+
+    // A CANCEL TAG with an empty tag spec is replaced.
+    assert_eq!(to_text("\u{1f3f4}\u{e007f}"), "\u{1f3f4}\u{fffd}\n");
+
+    // Tag specs are limited to digits and lowercase letters.
+    assert_eq!(
+        to_text("\u{1f3f4}\u{e0047}\u{e0042}\u{e007f}"),
+        "\u{1f3f4}\u{fffd}\u{fffd}\u{fffd}\n"
+    );
+
+    // Overlong tag specs are replaced.
+    assert_eq!(
+        to_text("\u{1f3f4}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e007f}"),
+        "\u{1f3f4}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\n"
+    );
+
+    // Language tags are replaced.
+    assert_eq!(
+        to_text("\u{e0001}\u{e0065}\u{e006e}hello"),
+        "\u{fffd}\u{fffd}\u{fffd}hello\n"
+    );
+}
+
+#[test]
+fn test_unassigned() {
+    assert_eq!(
+        text_streams::UNICODE_VERSION,
+        unicode_normalization::UNICODE_VERSION
+    );
+
+    // Unassigned codepoints are replaced with U+FFFD (REPLACEMENT CHARACTER).
+    for c in &['\u{378}', '\u{61d}', '\u{2fffd}', '\u{40000}', '\u{e0080}'] {
+        assert_eq!(
+            to_text(&c.to_string()),
+            "\u{fffd}\n",
+            "unassigned codepoint {:?} was not replaced",
+            c
+        );
+        assert_eq!(
+            to_text(&format!("hello{}world\n", c)),
+            "hello\u{fffd}world\n"
+        );
+    }
+
+    // Assigned codepoints at the edges of ranges are passed through.
+    for c in &['\u{377}', '\u{37a}', '\u{30000}', '\u{3134a}', '\u{e01ef}'] {
+        assert_eq!(to_text(&c.to_string()), format!("{}\n", c));
+    }
+
+    // As an option, unassigned codepoints are preserved.
+    assert_eq!(to_text_with_unassigned_preserved("\u{378}"), "\u{378}\n");
+    assert_eq!(
+        to_text_with_unassigned_preserved("hello\u{40000}world"),
+        "hello\u{40000}world\n"
+    );
+
+    // Noncharacters are still replaced.
+    assert_eq!(to_text_with_unassigned_preserved("\u{fffe}"), "\u{fffd}\n");
+}
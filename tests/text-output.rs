@@ -133,3 +133,123 @@ fn test_text_output_rules() {
         io::ErrorKind::Other
     );
 }
+
+#[test]
+fn test_emoji_tag_sequences() {
+    // Subdivision flags are permitted.
+    for flag in &[
+        // England
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}",
+        // Scotland
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0073}\u{e0063}\u{e0074}\u{e007f}",
+        // Wales
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0077}\u{e006c}\u{e0073}\u{e007f}",
+    ] {
+        assert_eq!(
+            to_text(&format!("{}\n", flag)).unwrap(),
+            format!("{}\n", flag)
+        );
+        assert_eq!(
+            to_text(&format!("hello {} world\n", flag)).unwrap(),
+            format!("hello {} world\n", flag)
+        );
+        assert_eq!(
+            to_text(&format!("{}{}\n", flag, flag)).unwrap(),
+            format!("{}{}\n", flag, flag)
+        );
+    }
+
+    // A plain waving black flag is fine too.
+    assert_eq!(to_text("\u{1f3f4}\n").unwrap(), "\u{1f3f4}\n");
+
+    // Fail at malformed tag sequences and stray or language tags.
+    for s in &[
+        "\u{e0067}\u{e007f}\n",
+        "A\u{e0067}\u{e0062}\u{e007f}\n",
+        "\u{1f3f4}\u{e0067}\u{e0062}\n",
+        "\u{1f3f4}\u{e007f}\n",
+        "\u{1f3f4}\u{e0047}\u{e0042}\u{e007f}\n",
+        "\u{1f3f4}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e0061}\u{e007f}\n",
+        "\u{e0001}\u{e0065}\u{e006e}hello\n",
+    ] {
+        assert_eq!(
+            to_text(s).unwrap_err().kind(),
+            io::ErrorKind::Other,
+            "malformed tag sequence {:?} was not rejected",
+            s,
+        );
+    }
+}
+
+fn write_pieces(pieces: &[&str]) -> io::Result<String> {
+    use io_ext::WriteExt;
+    let mut writer = TextWriter::new(StdWriter::new(Vec::<u8>::new()));
+    for piece in pieces {
+        writer.write_str(piece)?;
+    }
+    let inner = writer.close_into_inner()?;
+    Ok(String::from_utf8(inner.get_ref().to_vec()).unwrap())
+}
+
+#[test]
+fn test_emoji_tag_sequences_split_across_writes() {
+    let england = "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}";
+    let expected = format!("hello {} world\n", england);
+
+    // Split the flag at every position.
+    let chars = england.char_indices().map(|(i, _)| i).skip(1);
+    for split in chars {
+        let (first, second) = england.split_at(split);
+        assert_eq!(
+            write_pieces(&["hello ", first, second, " world\n"]).unwrap(),
+            expected,
+            "split at {}",
+            split
+        );
+        assert_eq!(
+            write_pieces(&[&format!("hello {}", first), &format!("{} world\n", second)]).unwrap(),
+            expected,
+            "split at {}",
+            split
+        );
+    }
+
+    // One char at a time.
+    let pieces = expected
+        .char_indices()
+        .map(|(i, c)| &expected[i..i + c.len_utf8()])
+        .collect::<Vec<_>>();
+    assert_eq!(write_pieces(&pieces).unwrap(), expected);
+
+    // A plain waving black flag at the end of a write is fine.
+    assert_eq!(write_pieces(&["\u{1f3f4}", "\n"]).unwrap(), "\u{1f3f4}\n");
+
+    // Incomplete sequences are still rejected, when the next write doesn't
+    // complete them, or at a flush or close.
+    assert!(write_pieces(&["\u{1f3f4}\u{e0067}", "\u{e0062}\n"]).is_err());
+    assert!(write_pieces(&["\u{1f3f4}\u{e0067}", "\u{e0062}"]).is_err());
+
+    use io_ext::WriteExt;
+    let mut writer = TextWriter::new(StdWriter::new(Vec::<u8>::new()));
+    writer.write_str("\u{1f3f4}\u{e0067}").unwrap();
+    assert!(writer.flush_with_status(io_ext::Status::active()).is_err());
+    writer.abandon();
+}
+
+#[test]
+fn test_unassigned() {
+    // Fail at unassigned codepoints.
+    for c in &['\u{378}', '\u{61d}', '\u{2fffd}', '\u{40000}', '\u{e0080}'] {
+        assert_eq!(
+            to_text(&format!("{}\n", c)).unwrap_err().kind(),
+            io::ErrorKind::Other,
+            "unassigned codepoint {:?} was not rejected",
+            c,
+        );
+    }
+
+    // Assigned codepoints at the edges of ranges are accepted.
+    for c in &['\u{377}', '\u{37a}', '\u{30000}', '\u{3134a}', '\u{e01ef}'] {
+        assert_eq!(to_text(&format!("{}\n", c)).unwrap(), format!("{}\n", c));
+    }
+}
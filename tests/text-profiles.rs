@@ -0,0 +1,148 @@
+//! Pin the behavior of each version of the Text format rules, so that
+//! changes to the rules can't silently change the behavior of an existing
+//! version.
+
+use io_ext_adapters::{StdReader, StdWriter};
+use std::io;
+use text_streams::{TextProfile, TextReader, TextWriter};
+
+fn to_text(input: &str, profile: TextProfile) -> String {
+    use io::Read;
+    let mut reader = TextReader::with_profile(StdReader::generic(input.as_bytes()), profile);
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    s
+}
+
+fn write_text(input: &str, profile: TextProfile) -> io::Result<String> {
+    use io::Write;
+    let mut writer = TextWriter::with_profile(StdWriter::new(Vec::<u8>::new()), profile);
+    writer.write_all(input.as_bytes())?;
+    let inner = writer.close_into_inner()?;
+    Ok(String::from_utf8(inner.get_ref().to_vec()).unwrap())
+}
+
+const ENGLAND: &str = "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}";
+
+/// Input, followed by the output of a `TextReader` using `Text2020` and
+/// `Text2026`.
+const INPUT_VECTORS: &[(&str, &str, &str)] = &[
+    ("", "", ""),
+    ("hello", "hello\n", "hello\n"),
+    ("\u{feff}hello\r\n", "hello\n", "hello\n"),
+    ("\r", "\u{fffd}\n", "\u{fffd}\n"),
+    ("\u{7}\u{c}\u{1b}[A", "\u{fffd} \n", "\u{fffd} \n"),
+    (
+        "\u{212b}\u{149}\u{17a4}",
+        "\u{c5}\u{2bc}n\u{17a2}\u{17b6}\n",
+        "\u{c5}\u{2bc}n\u{17a2}\u{17b6}\n",
+    ),
+    ("\u{30a}", "\u{fffd}\n", "\u{fffd}\n"),
+    (
+        "\u{fdd0}\u{e000}\u{206a}",
+        "\u{fffd}\u{fffd}\u{fffd}\n",
+        "\u{fffd}\u{fffd}\u{fffd}\n",
+    ),
+    (
+        "\u{e0001}\u{e0065}",
+        "\u{fffd}\u{fffd}\n",
+        "\u{fffd}\u{fffd}\n",
+    ),
+    (
+        ENGLAND,
+        "\u{1f3f4}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\n",
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}\n",
+    ),
+    ("\u{378}", "\u{378}\n", "\u{fffd}\n"),
+    ("\u{40000}", "\u{40000}\n", "\u{fffd}\n"),
+];
+
+/// Input, followed by whether a `TextWriter` accepts it using `Text2020` and
+/// `Text2026`.
+const OUTPUT_VECTORS: &[(&str, bool, bool)] = &[
+    ("", true, true),
+    ("hello\n", true, true),
+    ("hello", false, false),
+    ("\u{feff}hello\n", false, false),
+    ("hello\r\n", false, false),
+    ("\u{7}\n", false, false),
+    ("\u{212b}\n", false, false),
+    ("\u{fdd0}\n", false, false),
+    ("\u{e0001}\u{e0065}\n", false, false),
+    ("\u{1f3f4}\n", true, true),
+    (
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}\n",
+        false,
+        true,
+    ),
+    ("\u{1f3f4}\u{e0067}\u{e0062}\n", false, false),
+    ("\u{378}\n", true, false),
+    ("\u{40000}\n", true, false),
+];
+
+#[test]
+fn test_latest() {
+    assert_eq!(TextProfile::default(), TextProfile::TextLatest);
+    assert_eq!(TextProfile::TextLatest.resolve(), TextProfile::Text2026);
+    assert_eq!(TextProfile::Text2020.resolve(), TextProfile::Text2020);
+}
+
+#[test]
+fn test_text2020_input() {
+    for (input, expected, _) in INPUT_VECTORS {
+        assert_eq!(
+            to_text(input, TextProfile::Text2020),
+            *expected,
+            "input {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_text2026_input() {
+    for (input, _, expected) in INPUT_VECTORS {
+        assert_eq!(
+            to_text(input, TextProfile::Text2026),
+            *expected,
+            "input {:?}",
+            input
+        );
+        assert_eq!(
+            to_text(input, TextProfile::TextLatest),
+            *expected,
+            "input {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_text2020_output() {
+    for (input, accepted, _) in OUTPUT_VECTORS {
+        assert_eq!(
+            write_text(input, TextProfile::Text2020).is_ok(),
+            *accepted,
+            "output {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_text2026_output() {
+    for (input, _, accepted) in OUTPUT_VECTORS {
+        assert_eq!(
+            write_text(input, TextProfile::Text2026).is_ok(),
+            *accepted,
+            "output {:?}",
+            input
+        );
+        assert_eq!(
+            write_text(input, TextProfile::TextLatest).is_ok(),
+            *accepted,
+            "output {:?}",
+            input
+        );
+    }
+}
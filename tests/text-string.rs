@@ -0,0 +1,131 @@
+use std::convert::TryFrom;
+use text_streams::{TextLineStr, TextLineString, TextStr, TextString, ViolationKind};
+
+#[test]
+fn test_try_from() {
+    for input in &["", "\n", "hello\n", "\u{c5}\n", "\u{1100}\n\u{1161}\n"] {
+        let text = <&TextStr>::try_from(*input).unwrap();
+        assert_eq!(text.as_str(), *input);
+        assert_eq!(&**text, *input);
+        assert_eq!(TextString::try_from(*input).unwrap().as_str(), *input);
+        assert_eq!(
+            TextString::try_from(input.to_string())
+                .unwrap()
+                .into_string(),
+            *input
+        );
+    }
+
+    for (input, kind) in &[
+        ("hello", ViolationKind::MissingFinalNewline),
+        ("hello\r\n", ViolationKind::CarriageReturn),
+        ("\u{feff}hello\n", ViolationKind::ByteOrderMark),
+        ("A\u{30a}\n", ViolationKind::NotNormalized),
+        ("\u{30a}\n", ViolationKind::LeadingNonstarter),
+        ("\u{7}\n", ViolationKind::ControlCode),
+        ("\u{fdd0}\n", ViolationKind::Noncharacter),
+    ] {
+        assert_eq!(<&TextStr>::try_from(*input).unwrap_err().kind, *kind);
+        assert_eq!(TextString::try_from(*input).unwrap_err().kind, *kind);
+    }
+}
+
+#[test]
+fn test_from_lossy() {
+    assert_eq!(TextString::from_lossy(b"").as_str(), "");
+    assert_eq!(TextString::from_lossy(b"hello").as_str(), "hello\n");
+    assert_eq!(
+        TextString::from_lossy(b"\xffhello\x1b[m\r\n").as_str(),
+        "\u{fffd}hello\n"
+    );
+    assert_eq!(
+        TextString::from_lossy("A\u{30a}\n".as_bytes()).as_str(),
+        "\u{c5}\n"
+    );
+
+    // U+0149 is replaced with a sequence which composes with what follows.
+    let s = TextString::from_lossy("\u{149}\u{301}".as_bytes());
+    assert_eq!(s.as_str(), "\u{2bc}\u{144}\n");
+    assert!(<&TextStr>::try_from(s.as_str()).is_ok());
+}
+
+#[test]
+fn test_concat() {
+    let hello = <&TextStr>::try_from("hello\n").unwrap();
+    let world = <&TextStr>::try_from("world\n").unwrap();
+    let jamo = <&TextStr>::try_from("\u{1161}\n").unwrap();
+
+    let mut s = TextString::new();
+    s += hello;
+    s.push_text(world);
+    let s = s + jamo;
+    assert_eq!(s.as_str(), "hello\nworld\n\u{1161}\n");
+
+    // The result is still valid Text.
+    assert!(<&TextStr>::try_from(s.as_str()).is_ok());
+}
+
+#[test]
+fn test_borrow() {
+    let owned = TextString::try_from("hello\n").unwrap();
+    let borrowed: &TextStr = &owned;
+    assert_eq!(borrowed.to_owned(), owned);
+    assert_eq!(TextString::from(borrowed), owned);
+    assert_eq!(owned.len(), 6);
+    assert_eq!(format!("{}", owned), "hello\n");
+    assert_eq!(format!("{:?}", borrowed), "\"hello\\n\"");
+}
+
+#[test]
+fn test_line_try_from() {
+    for input in &["", "hello", "\u{c5}", "a\tb"] {
+        let line = <&TextLineStr>::try_from(*input).unwrap();
+        assert_eq!(line.as_str(), *input);
+        assert_eq!(TextLineString::try_from(*input).unwrap().as_str(), *input);
+    }
+
+    for (input, kind) in &[
+        ("hello\n", ViolationKind::Newline),
+        ("hello\nworld", ViolationKind::Newline),
+        ("hello\r", ViolationKind::CarriageReturn),
+        ("\u{7}\nhello", ViolationKind::ControlCode),
+        ("A\u{30a}", ViolationKind::NotNormalized),
+        ("\u{30a}", ViolationKind::LeadingNonstarter),
+    ] {
+        assert_eq!(<&TextLineStr>::try_from(*input).unwrap_err().kind, *kind);
+        assert_eq!(TextLineString::try_from(*input).unwrap_err().kind, *kind);
+    }
+
+    let violation = <&TextLineStr>::try_from("h\u{e9}llo\n").unwrap_err();
+    assert_eq!((violation.offset, violation.column), (6, 6));
+}
+
+#[test]
+fn test_line_from_lossy() {
+    assert_eq!(TextLineString::from_lossy(b"").as_str(), "");
+    assert_eq!(TextLineString::from_lossy(b"hello\n").as_str(), "hello");
+    assert_eq!(
+        TextLineString::from_lossy(b"hello\r\nworld\n").as_str(),
+        "hello world"
+    );
+    assert_eq!(
+        TextLineString::from_lossy("a\n\u{301}".as_bytes()).as_str(),
+        "a \u{301}"
+    );
+    assert_eq!(
+        TextLineString::from_lossy("\u{149}\u{301}".as_bytes()).as_str(),
+        "\u{2bc}\u{144}"
+    );
+}
+
+#[test]
+fn test_push_line() {
+    let hello = <&TextLineStr>::try_from("hello").unwrap();
+    let jamo = <&TextLineStr>::try_from("\u{1161}").unwrap();
+
+    let mut s = TextString::from(hello);
+    s.push_line(jamo);
+    s.push_line(<&TextLineStr>::try_from("").unwrap());
+    assert_eq!(s.as_str(), "hello\n\u{1161}\n\n");
+    assert!(<&TextStr>::try_from(s.as_str()).is_ok());
+}
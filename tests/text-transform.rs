@@ -0,0 +1,221 @@
+use io_ext_adapters::{StdReader, StdWriter};
+use std::io::{self, Read, Write};
+use text_streams::{TextReader, TextTransform, TextWriter, TransformStage};
+
+/// Map curly quotes to their ASCII equivalents.
+struct SmartQuotes;
+
+impl TextTransform for SmartQuotes {
+    fn transform(&mut self, c: char, _lookahead: &[char], output: &mut String) -> io::Result<()> {
+        output.push(match c {
+            '\u{2018}' | '\u{2019}' => '\'',
+            '\u{201c}' | '\u{201d}' => '"',
+            c => c,
+        });
+        Ok(())
+    }
+}
+
+/// Strip U+200B (ZERO WIDTH SPACE).
+struct StripZwsp;
+
+impl TextTransform for StripZwsp {
+    fn transform(&mut self, c: char, _lookahead: &[char], output: &mut String) -> io::Result<()> {
+        if c != '\u{200b}' {
+            output.push(c);
+        }
+        Ok(())
+    }
+}
+
+/// Reject a blocklist of codepoints.
+struct Blocklist(&'static [char]);
+
+impl TextTransform for Blocklist {
+    fn transform(&mut self, c: char, _lookahead: &[char], output: &mut String) -> io::Result<()> {
+        if self.0.contains(&c) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "blocked"));
+        }
+        output.push(c);
+        Ok(())
+    }
+}
+
+/// Replace "--" with an em dash, using one `char` of lookahead.
+#[derive(Default)]
+struct EmDash {
+    skip: bool,
+}
+
+impl TextTransform for EmDash {
+    fn lookahead(&self) -> usize {
+        1
+    }
+
+    fn transform(&mut self, c: char, lookahead: &[char], output: &mut String) -> io::Result<()> {
+        if std::mem::replace(&mut self.skip, false) {
+            return Ok(());
+        }
+        if c == '-' && lookahead.first() == Some(&'-') {
+            self.skip = true;
+            output.push('\u{2014}');
+        } else {
+            output.push(c);
+        }
+        Ok(())
+    }
+}
+
+/// Emit `c` followed by the given `char`s, to test that output is
+/// normalized and checked again.
+struct Append(char, &'static str);
+
+impl TextTransform for Append {
+    fn transform(&mut self, c: char, _lookahead: &[char], output: &mut String) -> io::Result<()> {
+        output.push(c);
+        if c == self.0 {
+            output.push_str(self.1);
+        }
+        Ok(())
+    }
+}
+
+/// Drop '\n's.
+struct StripNewlines;
+
+impl TextTransform for StripNewlines {
+    fn transform(&mut self, c: char, _lookahead: &[char], output: &mut String) -> io::Result<()> {
+        if c != '\n' {
+            output.push(c);
+        }
+        Ok(())
+    }
+}
+
+fn reader(input: &str) -> TextReader<StdReader<&[u8]>> {
+    TextReader::new(StdReader::generic(input.as_bytes()))
+}
+
+fn read_all(mut reader: TextReader<StdReader<&[u8]>>) -> io::Result<String> {
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+fn writer() -> TextWriter<StdWriter<Vec<u8>>> {
+    TextWriter::new(StdWriter::new(Vec::<u8>::new()))
+}
+
+fn write_all(mut writer: TextWriter<StdWriter<Vec<u8>>>, input: &str) -> io::Result<String> {
+    writer.write_all(input.as_bytes())?;
+    let inner = writer.close_into_inner()?;
+    Ok(String::from_utf8(inner.get_ref().to_vec()).unwrap())
+}
+
+#[test]
+fn test_reader_transforms() {
+    for stage in &[
+        TransformStage::BeforeNormalization,
+        TransformStage::AfterNormalization,
+    ] {
+        let mut r = reader("\u{201c}it\u{2019}s\u{201d} a\u{200b}b -- c\n");
+        r.add_transform(*stage, SmartQuotes);
+        r.add_transform(*stage, StripZwsp);
+        r.add_transform(*stage, EmDash::default());
+        assert_eq!(
+            read_all(r).unwrap(),
+            "\"it's\" ab \u{2014} c\n",
+            "{:?}",
+            stage
+        );
+
+        // Lookahead is flushed at the end of the stream.
+        let mut r = reader("a-");
+        r.add_transform(*stage, EmDash::default());
+        assert_eq!(read_all(r).unwrap(), "a-\n", "{:?}", stage);
+
+        let mut r = reader("a\u{2620}b\n");
+        r.add_transform(*stage, Blocklist(&['\u{2620}']));
+        assert_eq!(
+            read_all(r).unwrap_err().kind(),
+            io::ErrorKind::InvalidData,
+            "{:?}",
+            stage
+        );
+    }
+}
+
+#[test]
+fn test_reader_renormalizes() {
+    // Output of transforms before normalization goes through the full
+    // pipeline, including escape-sequence handling.
+    let mut r = reader("ab\n");
+    r.add_transform(
+        TransformStage::BeforeNormalization,
+        Append('a', "\u{1b}[m\u{7}"),
+    );
+    assert_eq!(read_all(r).unwrap(), "a\u{fffd}b\n");
+
+    for stage in &[
+        TransformStage::BeforeNormalization,
+        TransformStage::AfterNormalization,
+    ] {
+        let mut r = reader("A\n");
+        r.add_transform(*stage, Append('A', "\u{30a}"));
+        assert_eq!(read_all(r).unwrap(), "\u{c5}\n", "{:?}", stage);
+
+        let mut r = reader("A\n");
+        r.add_transform(*stage, Append('A', "\u{7}\u{feff}\u{fdd0}"));
+        assert_eq!(
+            read_all(r).unwrap(),
+            "A\u{fffd}\u{2060}\u{fffd}\n",
+            "{:?}",
+            stage
+        );
+
+        let mut r = reader("a\nb\n");
+        r.add_transform(*stage, StripNewlines);
+        assert_eq!(read_all(r).unwrap(), "ab\n", "{:?}", stage);
+    }
+}
+
+#[test]
+fn test_writer_transforms() {
+    for stage in &[
+        TransformStage::BeforeNormalization,
+        TransformStage::AfterNormalization,
+    ] {
+        let mut w = writer();
+        w.add_transform(*stage, SmartQuotes);
+        w.add_transform(*stage, StripZwsp);
+        w.add_transform(*stage, EmDash::default());
+        assert_eq!(
+            write_all(w, "\u{201c}it\u{2019}s\u{201d} a\u{200b}b -- c\n").unwrap(),
+            "\"it's\" ab \u{2014} c\n",
+            "{:?}",
+            stage
+        );
+
+        let mut w = writer();
+        w.add_transform(*stage, Blocklist(&['\u{2620}']));
+        assert!(write_all(w, "a\u{2620}b\n").is_err(), "{:?}", stage);
+
+        let mut w = writer();
+        w.add_transform(*stage, Append('A', "\u{30a}"));
+        assert_eq!(write_all(w, "A\n").unwrap(), "\u{c5}\n", "{:?}", stage);
+
+        let mut w = writer();
+        w.add_transform(*stage, Append('A', "\u{7}"));
+        assert!(write_all(w, "A\n").is_err(), "{:?}", stage);
+    }
+}
+
+#[test]
+fn test_writer_lookahead_across_writes() {
+    let mut w = writer();
+    w.add_transform(TransformStage::BeforeNormalization, EmDash::default());
+    w.write_all(b"a-").unwrap();
+    w.write_all(b"-b\n").unwrap();
+    let inner = w.close_into_inner().unwrap();
+    assert_eq!(inner.get_ref().as_slice(), "a\u{2014}b\n".as_bytes());
+}
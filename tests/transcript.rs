@@ -0,0 +1,97 @@
+use io_ext_adapters::StdReader;
+use std::io::Read;
+use text_streams::{is_text, TextReader};
+
+mod common;
+
+use common::OneByte;
+
+fn read(input: &[u8]) -> String {
+    let mut s = String::new();
+    TextReader::with_transcript(StdReader::generic(input))
+        .read_to_string(&mut s)
+        .unwrap();
+    assert!(is_text(&s), "output {:?}", s);
+
+    let mut bytewise = String::new();
+    TextReader::with_transcript(StdReader::generic(OneByte(input)))
+        .read_to_string(&mut bytewise)
+        .unwrap();
+    assert_eq!(bytewise, s, "input {:?}", input);
+    s
+}
+
+#[test]
+fn test_plain() {
+    assert_eq!(read(b"one\ntwo\n"), "one\ntwo\n");
+    assert_eq!(read(b"crlf\r\nline\r\n"), "crlf\nline\n");
+    assert_eq!(read(b"no newline"), "no newline\n");
+    assert_eq!(read(b"tab\tstop\n"), "tab\tstop\n");
+}
+
+#[test]
+fn test_carriage_return() {
+    assert_eq!(
+        read(b"[    ]  0%\r[==  ] 50%\r[====]100%\n"),
+        "[====]100%\n"
+    );
+    assert_eq!(read(b"longer line\rshort\n"), "shortr line\n");
+    assert_eq!(read(b"longer line\r\x1b[Kshort\n"), "short\n");
+    assert_eq!(read(b"trailing\r"), "trailing\n");
+}
+
+#[test]
+fn test_overstrike() {
+    // nroff bold and underline.
+    assert_eq!(read(b"N\x08NA\x08AM\x08ME\x08E\n"), "NAME\n");
+    assert_eq!(read(b"_\x08f_\x08i_\x08l_\x08e\n"), "file\n");
+    assert_eq!(read(b"f\x08_\n"), "f\n");
+    assert_eq!(read(b"ab\x08\x08\x08xy\n"), "xy\n");
+}
+
+#[test]
+fn test_csi() {
+    assert_eq!(read(b"\x1b[1;31merror\x1b[0m: bad\n"), "error: bad\n");
+    assert_eq!(read(b"abcdef\x1b[3Dxy\n"), "abcxyf\n");
+    assert_eq!(read(b"ab\x1b[3Cc\n"), "ab   c\n");
+    assert_eq!(read(b"abcdef\x1b[3Gx\n"), "abxdef\n");
+    assert_eq!(read(b"abcdef\x1b[2D\x1b[1K\n"), "     f\n");
+    assert_eq!(read(b"abc\x1b[2Kdef\n"), "   def\n");
+    assert_eq!(read(b"up\x1b[Aok\n"), "upok\n");
+}
+
+#[test]
+fn test_huge_cursor_movement() {
+    // The cursor stops at the last column of a 4096-column terminal.
+    let s = read(b"a\x1b[100000000Cb\n");
+    assert_eq!(s, format!("a{}b\n", " ".repeat(4094)));
+    let s = read(b"\x1b[100000000Gx\n");
+    assert_eq!(s, format!("{}x\n", " ".repeat(4095)));
+    let s = read(b"\x1b[99999999999999999999999999Cx\n");
+    assert_eq!(s, " x\n");
+
+    // Or at the end of a line which is already longer.
+    let long = "x".repeat(5000);
+    let s = read(format!("{}\x1b[100000000Gy\n", long).as_bytes());
+    assert_eq!(s, format!("{}y\n", long));
+    let s = read(format!("{}\x1b[1G\x1b[100000000Cy\n", long).as_bytes());
+    assert_eq!(s, format!("{}y\n", long));
+}
+
+#[test]
+fn test_osc_and_controls() {
+    assert_eq!(read(b"\x1b]0;title\x07prompt$ \n"), "prompt$ \n");
+    assert_eq!(read(b"\x1b]0;title\x1b\\prompt$ \n"), "prompt$ \n");
+    assert_eq!(read(b"bell\x07\x7f\n"), "bell\n");
+    assert_eq!(read(b"bad\xff\n"), "bad\u{fffd}\n");
+}
+
+#[test]
+fn test_combining_marks() {
+    assert_eq!(read("cafe\u{301}\n".as_bytes()), "caf\u{e9}\n");
+    assert_eq!(read("e\u{301}\rx\n".as_bytes()), "x\n");
+    assert_eq!(read(b"\x1b[C\xcc\x81"), " \u{301}\n");
+    assert_eq!(read(b"\x1b[5C\xcc\x81\n"), "     \u{301}\n");
+    assert_eq!(read(b"abc\x1b[2K\xcc\x81\n"), "   \u{301}\n");
+    assert_eq!(read(b"ab\x1b[3C\xcc\x81\n"), "ab   \u{301}\n");
+}
@@ -0,0 +1,258 @@
+use io_ext_adapters::StdReader;
+use std::io::Read;
+use text_streams::{validate, validate_with_profile, TextProfile, TextReader, ViolationKind};
+
+const MAX_VIOLATIONS: usize = 100;
+
+fn violations(input: &[u8]) -> Vec<(ViolationKind, Option<char>, u64, String)> {
+    let report = validate(StdReader::generic(input), MAX_VIOLATIONS).unwrap();
+    assert!(!report.truncated);
+    report
+        .violations
+        .into_iter()
+        .map(|v| (v.kind, v.codepoint, v.offset, v.replacement))
+        .collect()
+}
+
+fn to_text(input: &[u8]) -> Vec<u8> {
+    let mut reader = TextReader::new(StdReader::generic(input));
+    let mut v = Vec::new();
+    reader.read_to_end(&mut v).unwrap();
+    v
+}
+
+fn one(
+    kind: ViolationKind,
+    c: Option<char>,
+    offset: u64,
+    replacement: &str,
+) -> Vec<(ViolationKind, Option<char>, u64, String)> {
+    vec![(kind, c, offset, replacement.to_owned())]
+}
+
+#[test]
+fn test_valid() {
+    for input in &[
+        "",
+        "\n",
+        "hello world\n",
+        "\tindented\n\n",
+        "\u{c5}\n",
+        "\u{ac00}\n",
+        "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}\n",
+        "a\u{34f}\u{30a}\n",
+    ] {
+        assert!(
+            validate(StdReader::generic(input.as_bytes()), MAX_VIOLATIONS)
+                .unwrap()
+                .is_valid(),
+            "input {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_kinds() {
+    use ViolationKind::*;
+
+    assert_eq!(
+        violations(b"a\xffb\n"),
+        one(InvalidUtf8, None, 1, "\u{fffd}")
+    );
+    assert_eq!(violations(b"a\xe2\x82"), {
+        let mut v = one(InvalidUtf8, None, 1, "\u{fffd}");
+        v.extend(one(MissingFinalNewline, None, 3, "\n"));
+        v
+    });
+    assert_eq!(
+        violations("\u{feff}hello\n".as_bytes()),
+        one(ByteOrderMark, Some('\u{feff}'), 0, "")
+    );
+    assert_eq!(
+        violations("a\u{feff}b\n".as_bytes()),
+        one(ByteOrderMark, Some('\u{feff}'), 1, "\u{2060}")
+    );
+    assert_eq!(
+        violations(b"hello\r\n"),
+        one(CarriageReturn, Some('\r'), 5, "")
+    );
+    assert_eq!(
+        violations(b"a\rb\n"),
+        one(CarriageReturn, Some('\r'), 1, "\u{fffd}")
+    );
+    assert_eq!(
+        violations(b"a\x07b\n"),
+        one(ControlCode, Some('\x07'), 1, "\u{fffd}")
+    );
+    assert_eq!(
+        violations(b"a\x0cb\n"),
+        one(ControlCode, Some('\x0c'), 1, " ")
+    );
+    assert_eq!(
+        violations(b"a\x1b[31mb\n"),
+        one(EscapeSequence, Some('\x1b'), 1, "")
+    );
+    assert_eq!(
+        violations(b"a\x1b]title\x07b\n"),
+        one(EscapeSequence, Some('\x1b'), 1, "")
+    );
+    assert_eq!(
+        violations(b"a\x1b\x01\n"),
+        vec![
+            (ControlCode, Some('\x1b'), 1, "\u{fffd}".to_owned()),
+            (ControlCode, Some('\x01'), 2, "\u{fffd}".to_owned()),
+        ]
+    );
+    assert_eq!(
+        violations(b"\x1b\xcc\xa7"),
+        vec![
+            (ControlCode, Some('\x1b'), 0, "\u{fffd}".to_owned()),
+            (LeadingNonstarter, Some('\u{327}'), 1, "\u{fffd}".to_owned()),
+            (MissingFinalNewline, None, 3, "\n".to_owned()),
+        ]
+    );
+    assert_eq!(to_text(b"\x1b\xcc\xa7"), "\u{fffd}\u{fffd}\n".as_bytes());
+    assert_eq!(
+        violations("A\u{30a}\n".as_bytes()),
+        one(NotNormalized, Some('A'), 0, "\u{c5}")
+    );
+    assert_eq!(
+        violations("x\u{301}\u{316}\n".as_bytes()),
+        one(NotNormalized, Some('\u{301}'), 1, "\u{316}\u{301}")
+    );
+    assert_eq!(
+        violations("x\u{212b}\n".as_bytes()),
+        one(ObsoleteCompatibility, Some('\u{212b}'), 1, "\u{c5}")
+    );
+    assert_eq!(
+        violations("\u{30a}\n".as_bytes()),
+        one(LeadingNonstarter, Some('\u{30a}'), 0, "\u{fffd}")
+    );
+    assert_eq!(
+        violations("\u{149}\n".as_bytes()),
+        one(Deprecated, Some('\u{149}'), 0, "\u{2bc}n")
+    );
+    assert_eq!(
+        violations("\u{232a}\n".as_bytes()),
+        one(Deprecated, Some('\u{232a}'), 0, "\u{fffd}")
+    );
+    assert_eq!(
+        violations("\u{206a}\n".as_bytes()),
+        one(DeprecatedFormatCharacter, Some('\u{206a}'), 0, "\u{fffd}")
+    );
+    assert_eq!(
+        violations("\u{e0065}\n".as_bytes()),
+        one(TagCharacter, Some('\u{e0065}'), 0, "\u{fffd}")
+    );
+    assert_eq!(
+        violations("\u{1f3f4}\u{e0067}\u{e0062}\n".as_bytes()),
+        vec![
+            (TagCharacter, Some('\u{e0067}'), 4, "\u{fffd}".to_owned()),
+            (TagCharacter, Some('\u{e0062}'), 8, "\u{fffd}".to_owned()),
+        ]
+    );
+    assert_eq!(
+        violations("\u{fdd0}\n".as_bytes()),
+        one(Noncharacter, Some('\u{fdd0}'), 0, "\u{fffd}")
+    );
+    assert_eq!(
+        violations("\u{e000}\n".as_bytes()),
+        one(PrivateUse, Some('\u{e000}'), 0, "\u{fffd}")
+    );
+    assert_eq!(
+        violations("\u{378}\n".as_bytes()),
+        one(Unassigned, Some('\u{378}'), 0, "\u{fffd}")
+    );
+    assert_eq!(
+        violations(b"hello"),
+        one(MissingFinalNewline, None, 5, "\n")
+    );
+}
+
+#[test]
+fn test_positions() {
+    let input = "one\ntwo \u{7}\n\u{e9}\u{e000}\n";
+    let report = validate(StdReader::generic(input.as_bytes()), MAX_VIOLATIONS).unwrap();
+    let positions = report
+        .violations
+        .iter()
+        .map(|v| (v.line, v.column, v.offset))
+        .collect::<Vec<_>>();
+    assert_eq!(positions, vec![(2, 5, 8), (3, 2, 12)]);
+    assert_eq!(
+        report.violations[0].to_string(),
+        "2:5: control code U+0007 at byte 8, replaced with \"\u{fffd}\""
+    );
+}
+
+#[test]
+fn test_order() {
+    // The escape sequence is found before the normalization problem that
+    // precedes it, but they're reported in order.
+    let report = validate(
+        StdReader::generic("A\u{1b}[m\u{30a}\n".as_bytes()),
+        MAX_VIOLATIONS,
+    )
+    .unwrap();
+    let offsets = report
+        .violations
+        .iter()
+        .map(|v| v.offset)
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, vec![0, 1]);
+}
+
+#[test]
+fn test_truncated() {
+    let input = "\u{7}".repeat(10000);
+    let report = validate(StdReader::generic(input.as_bytes()), 10).unwrap();
+    assert!(report.truncated);
+    assert_eq!(report.violations.len(), 10);
+    assert_eq!(report.violations[9].offset, 9);
+
+    let report = validate(StdReader::generic(&b"\x07\x07\n"[..]), 2).unwrap();
+    assert!(!report.truncated);
+    assert_eq!(report.violations.len(), 2);
+}
+
+#[test]
+fn test_profile() {
+    let report = validate_with_profile(
+        StdReader::generic("\u{378}\n".as_bytes()),
+        TextProfile::Text2020,
+        10,
+    )
+    .unwrap();
+    assert!(report.is_valid());
+}
+
+#[test]
+fn test_agrees_with_reader() {
+    for input in &[
+        &b""[..],
+        b"hello\n",
+        b"hello",
+        b"\xff\n",
+        b"\r\n\r",
+        b"\x1b[[A\n",
+        b"\x1b",
+        b"\x1b\xcc\xa7\n",
+        "\u{feff}\u{feff}\n".as_bytes(),
+        "\u{2126}\u{212a}\n".as_bytes(),
+        "a\u{301}\u{316}\n".as_bytes(),
+        "\u{f900}\n".as_bytes(),
+        "\u{1100}\u{1161}\n".as_bytes(),
+        "\u{1f3f4}\u{e0067}\u{e007f}\n".as_bytes(),
+        "\u{1f3f4}\u{e007f}\n".as_bytes(),
+        "\u{40000}\n".as_bytes(),
+    ] {
+        let report = validate(StdReader::generic(*input), MAX_VIOLATIONS).unwrap();
+        assert_eq!(
+            report.is_valid(),
+            to_text(input) == *input,
+            "input {:?}",
+            input
+        );
+    }
+}